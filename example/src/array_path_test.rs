@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+
+    const ORDER_RULE: &str = "
+    rule ORDER_RULE
+    when
+        order.items[0].price > 10;
+        order.items[-1].sku == 'c';
+        contain(order.items[*].sku, 'b')
+    then
+        result.skus = order.items[*].sku;
+        result.lines[0].sku = order.items[-1].sku
+    ";
+
+    //cargo test --color=always --bin example array_path_test::test::test_array_path --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_array_path() {
+        let rh = Rush::from(Into::<ExprEngine>::into([ORDER_RULE]));
+        let input = r#"{"order":{"items":[{"sku":"a","price":20},{"sku":"b","price":5},{"sku":"c","price":1}]}}"#;
+        let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+        assert_eq!(res["result"]["skus"].to_string(), r#"["a","b","c"]"#);
+        assert_eq!(res["result"]["lines"][0]["sku"], "c");
+
+        // 下标越界视为字段不存在，条件不满足
+        let res: Value = rh
+            .flow(r#"{"order":{"items":[]}}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.to_string(), "{}");
+    }
+}
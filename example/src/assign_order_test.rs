@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::{json, Value};
+
+    const PRICE_RULE: &str = r#"
+    rule PRICE_RULE
+    when
+        true
+    then
+        total = price * count;
+        total = $out.total - discount;
+        size = $out.total > 100 ? 'big' : 'small';
+    "#;
+
+    const SHIPPING_RULE: &str = r#"
+    rule SHIPPING_RULE
+    when
+        true
+    then
+        shipping = $out.size == 'big' ? 0 : 10;
+        pay = $out.total + $out.shipping;
+    "#;
+
+    //cargo test --color=always --bin example assign_order_test::test::test_assign_order --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_assign_order() {
+        let rh = Rush::from(Into::<ExprEngine>::into([PRICE_RULE, SHIPPING_RULE]));
+        let res: Value = rh
+            .flow(json!({"price": 30, "count": 4, "discount": 10}))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"total":110,"size":"big","shipping":0,"pay":110})
+        );
+        let res: Value = rh
+            .flow(json!({"price": 30, "count": 2, "discount": 10}))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"total":50,"size":"small","shipping":10,"pay":60})
+        );
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    const CHAIN_RULE_DISCOUNT: &str = "
+    rule CHAIN_RULE_DISCOUNT
+    when
+        vip == true;
+    then
+        discount = 20
+    ";
+    const CHAIN_RULE_VIP: &str = "
+    rule CHAIN_RULE_VIP
+    when
+        amount > 1000;
+    then
+        vip = true
+    ";
+
+    #[derive(Deserialize, Debug, Default)]
+    struct Resp {
+        #[serde(default = "Default::default")]
+        vip: bool,
+        #[serde(default = "Default::default")]
+        discount: i64,
+    }
+
+    #[test]
+    fn test_forward_chaining() {
+        let ee = ExprEngine::from([CHAIN_RULE_DISCOUNT, CHAIN_RULE_VIP]);
+
+        // single pass: vip is only in output, the discount rule never sees it
+        let rh = Rush::from(ee);
+        let resp: Resp = rh
+            .flow(r#"{"amount":2000}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert!(resp.vip);
+        assert_eq!(resp.discount, 0);
+
+        let rh = rh.forward_chaining(8);
+        let (resp, record): (Resp, _) = rh
+            .flow_chain(r#"{"amount":2000}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert!(resp.vip);
+        assert_eq!(resp.discount, 20);
+        assert!(record.fixpoint);
+        assert_eq!(
+            record.cycles,
+            vec![vec!["CHAIN_RULE_VIP"], vec!["CHAIN_RULE_DISCOUNT"]]
+        );
+
+        let resp: Resp = rh
+            .flow(r#"{"amount":20}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert!(!resp.vip);
+        assert_eq!(resp.discount, 0);
+    }
+}
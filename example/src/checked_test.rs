@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{ArithmeticError, ArithmeticErrorKind, RuleFlow, Rush};
+    use rush_expr_engine::{ArithmeticPolicy, ExprEngine};
+    use serde_json::Value;
+
+    const AVG_RULE: &str = r#"
+    rule AVG_RULE
+    when
+        order.count >= 0
+    then
+        avg = order.amount / order.count
+    "#;
+
+    const BONUS_RULE: &str = r#"
+    rule BONUS_RULE
+    when
+        order.amount / order.count > 10
+    then
+        bonus = order.amount * 1000
+    "#;
+
+    fn flow(rh: &Rush, input: &str) -> anyhow::Result<String> {
+        let res: Value = rh.flow(input.parse::<Value>().unwrap())?;
+        Ok(res.to_string())
+    }
+
+    //cargo test --color=always --bin example checked_test::test::test_division_by_zero --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_division_by_zero() {
+        let rh = Rush::from(Into::<ExprEngine>::into([AVG_RULE, BONUS_RULE]));
+        assert_eq!(
+            flow(&rh, r#"{"order":{"amount":100,"count":4}}"#).unwrap(),
+            r#"{"avg":25,"bonus":100000}"#
+        );
+        // 条件和赋值中的错误都带上规则名
+        let err = flow(&rh, r#"{"order":{"amount":100,"count":0}}"#).unwrap_err();
+        let ae = err.downcast_ref::<ArithmeticError>().unwrap();
+        assert_eq!(ae.kind, ArithmeticErrorKind::DivisionByZero);
+        assert_eq!(ae.rule.as_deref(), Some("BONUS_RULE"));
+        assert_eq!(
+            err.to_string(),
+            "rule[BONUS_RULE] division by zero: (order.amount / order.count)"
+        );
+        let err = flow(&rh, r#"{"order":{"amount":9223372036854775807,"count":1}}"#).unwrap_err();
+        let ae = err.downcast_ref::<ArithmeticError>().unwrap();
+        assert_eq!(ae.kind, ArithmeticErrorKind::Overflow);
+        assert_eq!(ae.rule.as_deref(), Some("BONUS_RULE"));
+    }
+
+    //cargo test --color=always --bin example checked_test::test::test_arithmetic_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_arithmetic_policy() {
+        let mut ee = ExprEngine::default();
+        ee.set_arithmetic_policy(ArithmeticPolicy::Null);
+        ee.register_rule(AVG_RULE).unwrap();
+        let rh = Rush::from(ee);
+        assert_eq!(
+            flow(&rh, r#"{"order":{"amount":100,"count":0}}"#).unwrap(),
+            r#"{"avg":null}"#
+        );
+
+        let mut ee = ExprEngine::default();
+        ee.set_arithmetic_policy(ArithmeticPolicy::Saturate);
+        ee.register_rule(BONUS_RULE).unwrap();
+        let rh = Rush::from(ee);
+        assert_eq!(
+            flow(&rh, r#"{"order":{"amount":9223372036854775807,"count":1}}"#).unwrap(),
+            r#"{"bonus":9223372036854775807}"#
+        );
+        assert!(flow(&rh, r#"{"order":{"amount":100,"count":0}}"#).is_err());
+    }
+}
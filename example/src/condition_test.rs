@@ -0,0 +1,34 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+
+    const LEVEL_RULE: &str = "
+    rule LEVEL_RULE
+    when
+        case when vip then score > 50 else score > 80 end
+    then
+        level = case when score > 90 then 'high' when score > 70 then 'mid' else 'low' end;
+        discount = vip ? 0.8 : if(score > 90, 0.9, 1)
+    ";
+
+    //cargo test --color=always --bin example condition_test::test::test_condition_rule --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_condition_rule() {
+        let rh = Rush::from(Into::<ExprEngine>::into([LEVEL_RULE]));
+        let flow = |input: &str| -> String {
+            let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+            res.to_string()
+        };
+        assert_eq!(
+            flow(r#"{"vip":true,"score":60}"#),
+            r#"{"discount":0.8,"level":"low"}"#
+        );
+        assert_eq!(
+            flow(r#"{"vip":false,"score":95}"#),
+            r#"{"discount":0.9,"level":"high"}"#
+        );
+        assert_eq!(flow(r#"{"vip":false,"score":60}"#), "{}");
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::{ExprEngine, NumberMode};
+    use serde_json::Value;
+
+    const FEE_RULE: &str = r#"
+    rule FEE_RULE
+    when
+        order.amount * order.rate >= 0.3
+    then
+        fee = round_half_even(order.amount * order.rate, 2);
+        total = order.amount + $out.fee;
+        split = order.amount / 3
+    "#;
+
+    //cargo test --color=always --bin example decimal_test::test::test_decimal_mode --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_decimal_mode() {
+        let mut ee = ExprEngine::default();
+        ee.set_number_mode(NumberMode::Decimal);
+        ee.register_rule(FEE_RULE).unwrap();
+        let rh = Rush::from(ee);
+        let flow = |input: &str| -> String {
+            let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+            res.to_string()
+        };
+        // 浮点数计算时 10.1 * 0.03 = 0.30299999999999994
+        assert_eq!(
+            flow(r#"{"order":{"amount":10.1,"rate":0.03}}"#),
+            r#"{"fee":0.3,"split":3.3666666666666667,"total":10.4}"#
+        );
+        assert_eq!(
+            flow(r#"{"order":{"amount":10.5,"rate":0.03}}"#),
+            r#"{"fee":0.32,"split":3.5,"total":10.82}"#
+        );
+        assert_eq!(flow(r#"{"order":{"amount":9.9,"rate":0.03}}"#), "{}");
+    }
+}
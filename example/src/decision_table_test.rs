@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::{DecisionTable, ExprEngine, HitPolicy};
+    use serde_json::Value;
+
+    fn flow(rh: &Rush, input: &str) -> String {
+        let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+        res.to_string()
+    }
+
+    //cargo test --color=always --bin example decision_table_test::test::test_table_file --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_table_file() {
+        let mut ee = ExprEngine::default();
+        ee.load_table_file("tables/discount.csv").unwrap();
+        ee.load_table_file("tables/tags.json").unwrap();
+        let rh = Rush::from(ee);
+
+        assert_eq!(
+            flow(&rh, r#"{"age":20,"channel":"app","level":3}"#),
+            r#"{"discount":0.8,"reason":"young app user","tags":["vip","mobile"]}"#
+        );
+        assert_eq!(
+            flow(&rh, r#"{"age":20,"channel":"web","level":5}"#),
+            r#"{"discount":0.9,"reason":"young user","tags":["vip","super"]}"#
+        );
+        assert_eq!(
+            flow(&rh, r#"{"age":40,"channel":"web","level":1}"#),
+            r#"{"discount":1}"#
+        );
+    }
+
+    //cargo test --color=always --bin example decision_table_test::test::test_priority_table --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_priority_table() {
+        let table = DecisionTable::from_csv(
+            "RISK",
+            "score, => risk, priority\n> 60, 'middle', 1\n> 80, 'high', 2\n-, 'low', 0",
+        )
+        .unwrap()
+        .hit_policy(HitPolicy::Priority);
+        let rh = Rush::from(table.build().unwrap());
+        assert_eq!(flow(&rh, r#"{"score":90}"#), r#"{"risk":"high"}"#);
+        assert_eq!(flow(&rh, r#"{"score":70}"#), r#"{"risk":"middle"}"#);
+        assert_eq!(flow(&rh, r#"{"score":10}"#), r#"{"risk":"low"}"#);
+
+        // unique策略不允许重叠
+        let err = table.hit_policy(HitPolicy::Unique).build().unwrap_err();
+        assert!(err.to_string().contains("overlap"), "{}", err);
+    }
+}
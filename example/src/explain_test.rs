@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{MultiRush, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+
+    const EXPLAIN_RULE_VIP: &str = "
+    rule EXPLAIN_RULE_VIP
+    when
+        country == '中国';
+        order.amount > 1000 && user.level > 3;
+    then
+        tag = 'vip';
+        data.discount = order.amount / 10;
+    ";
+    const EXPLAIN_RULE_NEW: &str = "
+    rule EXPLAIN_RULE_NEW
+    when
+        country == '中国';
+        user.orders == 0;
+    then
+        tag = 'new'
+    ";
+
+    const EXPLAIN_RULE_DISCOUNT: &str = "
+    rule EXPLAIN_RULE_DISCOUNT
+    when
+        tag == 'vip';
+    then
+        discount = 20
+    ";
+    const EXPLAIN_RULE_COUPON: &str = "
+    rule EXPLAIN_RULE_COUPON missing=fail_rule
+    when
+        country == '中国';
+    then
+        coupon = 'VIP';
+        coupon_owner = user.name;
+    ";
+
+    fn rush() -> Rush {
+        Rush::from(Into::<ExprEngine>::into([
+            EXPLAIN_RULE_VIP,
+            EXPLAIN_RULE_NEW,
+        ]))
+    }
+
+    #[test]
+    fn test_flow_explain() {
+        let trace = rush()
+            .flow_explain(
+                r#"{"country":"中国","order":{"amount":2000},"user":{"level":5}}"#
+                    .parse::<Value>()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let vip = trace.rule("EXPLAIN_RULE_VIP").unwrap();
+        assert!(vip.matched);
+        assert_eq!(vip.written_keys, vec!["data.discount", "tag"]);
+        let amount = vip.conditions[1]
+            .values
+            .iter()
+            .find(|x| x.expr == "order.amount")
+            .unwrap();
+        assert_eq!(amount.value, Value::from(2000));
+
+        let new = trace.rule("EXPLAIN_RULE_NEW").unwrap();
+        assert!(!new.matched);
+        assert_eq!(new.failed_condition, Some(1));
+        assert_eq!(new.conditions[1].missing_fields, vec!["user.orders"]);
+        assert!(new.written_keys.is_empty());
+
+        assert_eq!(trace.output.get("tag").unwrap(), "vip");
+    }
+
+    //cargo test --color=always --bin example explain_test::test::test_flow_explain_chain --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_flow_explain_chain() {
+        let rh = Rush::from(Into::<ExprEngine>::into([
+            EXPLAIN_RULE_DISCOUNT,
+            EXPLAIN_RULE_VIP,
+            EXPLAIN_RULE_COUPON,
+        ]))
+        .forward_chaining(8);
+        let input = r#"{"country":"中国","order":{"amount":2000},"user":{"level":5}}"#
+            .parse::<Value>()
+            .unwrap();
+        let trace = rh.flow_explain(input.clone()).unwrap();
+        let (output, _): (Value, _) = rh.flow_chain(input).unwrap();
+        assert_eq!(trace.output, output);
+        assert_eq!(trace.output.get("discount").unwrap(), 20);
+
+        // 第二轮才命中的规则记录最后一次的计算结果
+        let discount = trace.rule("EXPLAIN_RULE_DISCOUNT").unwrap();
+        assert!(discount.matched);
+        assert_eq!(discount.written_keys, vec!["discount"]);
+
+        // 规则失败时回滚，不记录写入的key
+        let coupon = trace.rule("EXPLAIN_RULE_COUPON").unwrap();
+        assert!(coupon.error.is_some());
+        assert!(coupon.written_keys.is_empty());
+        assert!(trace.output.get("coupon").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_multi_flow_explain() {
+        let mr: MultiRush = rush().into();
+        let trace = mr
+            .flow_explain(r#"{"country":"美国"}"#.parse::<Value>().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(trace.rules.len(), 2);
+        for i in trace.rules.iter() {
+            assert!(!i.matched);
+            assert_eq!(i.failed_condition, Some(0));
+            assert_eq!(i.conditions[0].result, Some(false));
+        }
+    }
+}
@@ -72,11 +72,11 @@ mod test {
         payment.discount_amount == coupon_discount_amount(coupons);
         2>>1 == 1;
         1<<2 == 4;
-        1 | 2 == 3;
-        3 & 2 == 2;
+        (1 | 2) == 3;
+        (3 & 2) == 2;
         contain([1,2,3,4],status) && !contain([5],status);
         sub([1,2,3,4],[1]) && !sub([1,2,3,4],[5]);
-        1 ^ 2 == 3;
+        (1 ^ 2) == 3;
         ~ 1 == -2;
         true;
         true || false;
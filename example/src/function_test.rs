@@ -14,7 +14,7 @@ mod test {
         abs(revenue.low) < 100 && abs(revenue.high) > 1000;
     then
         message = str_splice(str_rev(country),city,'贫富差距大');
-        revenue.avg = abs(revenue.low) + abs(revenue.high) / 2;
+        revenue.avg = (abs(revenue.low) + abs(revenue.high)) / 2;
     ";
 
     //可以是函数
@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+
+    const BIG_ORDER_RULE: &str = "
+    rule BIG_ORDER_RULE
+    when
+        any(order.items, x -> x.price > 100);
+        all(order.items, x -> x.count >= 1)
+    then
+        result.total = sum(order.items, x -> x.price * x.count);
+        result.expensive = map(filter(order.items, x -> x.price > 100), x -> x.sku);
+        result.cheapest = min(order.items[*].price)
+    ";
+
+    //cargo test --color=always --bin example lambda_test::test::test_lambda_rule --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_lambda_rule() {
+        let rh = Rush::from(Into::<ExprEngine>::into([BIG_ORDER_RULE]));
+        let input = r#"{"order":{"items":[
+            {"sku":"a","price":150,"count":1},
+            {"sku":"b","price":20,"count":3}
+        ]}}"#;
+        let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+        assert_eq!(
+            res.to_string(),
+            r#"{"result":{"cheapest":20,"expensive":["a"],"total":210}}"#
+        );
+
+        let input = r#"{"order":{"items":[{"sku":"b","price":20,"count":3}]}}"#;
+        let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+        assert_eq!(res.to_string(), "{}");
+    }
+}
@@ -1,9 +1,31 @@
 mod array_env_test;
+mod array_path_test;
+mod assign_order_test;
+mod chain_test;
+mod checked_test;
+mod condition_test;
 mod custom_rule_exec;
+mod decimal_test;
+mod decision_table_test;
+mod explain_test;
 mod expr_test;
 mod function_test;
+mod lambda_test;
 mod lua_test;
 mod many_async_test;
+mod merge_test;
+mod missing_policy_test;
+mod mutation_test;
+mod null_safe_test;
+mod reload_test;
+mod rete_test;
+mod rule_file_test;
+mod rule_set_test;
+mod salience_test;
+mod std_lib_test;
+mod schema_test;
+mod string_op_test;
+mod time_test;
 mod wasm_test;
 
 fn main() {}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{MergeStrategy, RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::{json, Value};
+
+    const DEVICE_RULE: &str = r#"
+    rule DEVICE_RULE
+    when
+        new_device
+    then
+        risk_score = 30;
+        tags = 'new_device';
+        reason = 'device';
+    "#;
+
+    const AMOUNT_RULE: &str = r#"
+    rule AMOUNT_RULE
+    when
+        amount > 1000
+    then
+        risk_score = 50;
+        tags = ['large_amount', 'manual_review'];
+        reason = 'amount';
+    "#;
+
+    //cargo test --color=always --bin example merge_test::test::test_merge_strategy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_merge_strategy() {
+        let rh = Rush::from(Into::<ExprEngine>::into([DEVICE_RULE, AMOUNT_RULE]))
+            .merge_strategy(MergeStrategy::KeepFirst)
+            .merge_path("risk_score", MergeStrategy::Sum)
+            .merge_path("tags", MergeStrategy::Collect);
+        let res: Value = rh
+            .flow(json!({"new_device": true, "amount": 2000}))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"risk_score":80,"tags":["new_device","large_amount","manual_review"],"reason":"device"})
+        );
+        let res: Value = rh
+            .flow(json!({"new_device": false, "amount": 2000}))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"risk_score":50,"tags":["large_amount","manual_review"],"reason":"amount"})
+        );
+    }
+
+    const BONUS_RULE: &str = r#"
+    rule LOGIN_BONUS
+    when
+        login
+    then
+        score += 10;
+        seen.push('login');
+
+    rule ORDER_BONUS
+    when
+        ordered
+    then
+        score += 10;
+        seen.push('order');
+        double = $out.score * 2;
+    "#;
+
+    //cargo test --color=always --bin example merge_test::test::test_merge_compound --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_merge_compound() {
+        // 复合赋值和$out读取已经合并的输出，不会被Sum再加一次
+        let rh = Rush::from(Into::<ExprEngine>::into([BONUS_RULE]))
+            .merge_strategy(MergeStrategy::KeepFirst)
+            .merge_path("score", MergeStrategy::Sum);
+        let res: Value = rh.flow(json!({"login": true, "ordered": true})).unwrap();
+        assert_eq!(
+            res,
+            json!({"score":20,"seen":["login","order"],"double":40})
+        );
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod test {
+    use rush_core::Rush;
+    use rush_expr_engine::{ExprEngine, MissingPolicy};
+    use serde_json::{json, Value};
+
+    const ORDER_RULE: &str = r#"
+    rule ORDER_RULE
+    when
+        order.amount > 100
+    then
+        discount = order.amount * 0.1;
+        level = user.level;
+    "#;
+
+    const COUPON_RULE: &str = r#"
+    rule COUPON_RULE missing=fail_rule
+    when
+        order.amount > 100
+    then
+        coupon = 'VIP';
+        coupon_owner = user.name;
+    "#;
+
+    //cargo test --color=always --bin example missing_policy_test::test::test_missing_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_missing_policy() {
+        let mut ee = ExprEngine::default();
+        ee.set_missing_policy(MissingPolicy::Skip);
+        ee.register_rule(ORDER_RULE).unwrap();
+        ee.register_rule(COUPON_RULE).unwrap();
+        let rh = Rush::from(ee);
+
+        let input = json!({"order":{"amount":200}});
+        let (out, report): (Value, _) = rh.flow_report(&input).unwrap();
+        assert_eq!(out, json!({"discount":20.0}));
+        assert_eq!(report.skipped[0].key, "level");
+        assert_eq!(report.failed_rules[0].rule, "COUPON_RULE");
+
+        let input = json!({"order":{"amount":200},"user":{"level":2,"name":"ann"}});
+        let (out, report): (Value, _) = rh.flow_report(&input).unwrap();
+        assert_eq!(
+            out,
+            json!({"discount":20.0,"level":2,"coupon":"VIP","coupon_owner":"ann"})
+        );
+        assert!(report.skipped.is_empty() && report.failed_rules.is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::{json, Value};
+
+    const AGE_RULE: &str = r#"
+    rule AGE_RULE
+    when
+        age >= 18
+    then
+        score += 10;
+        reasons.push('adult');
+    "#;
+
+    const VIP_RULE: &str = r#"
+    rule VIP_RULE
+    when
+        vip
+    then
+        score += 20;
+        score *= 2;
+        reasons.push('vip');
+        level ??= 'gold';
+    "#;
+
+    const CLEAN_RULE: &str = r#"
+    rule CLEAN_RULE
+    when
+        true
+    then
+        level ??= 'normal';
+        debug = $out.score ?? 0;
+        delete debug;
+    "#;
+
+    //cargo test --color=always --bin example mutation_test::test::test_mutation --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_mutation() {
+        let rh = Rush::from(Into::<ExprEngine>::into([AGE_RULE, VIP_RULE, CLEAN_RULE]));
+        let res: Value = rh.flow(json!({"age": 20, "vip": true})).unwrap();
+        assert_eq!(
+            res,
+            json!({"score":60,"reasons":["adult","vip"],"level":"gold"})
+        );
+        let res: Value = rh.flow(json!({"age": 16, "vip": false})).unwrap();
+        assert_eq!(res, json!({"level":"normal"}));
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::{ExprEngine, NullMode};
+    use serde_json::Value;
+
+    const PROFILE_RULE: &str = r#"
+    rule PROFILE_RULE
+    when
+        user?.age >= 18 || is_null(user?.age)
+    then
+        name = user?.nick ?? user?.name ?? 'guest';
+        has_phone = exists(user?.phone)
+    "#;
+
+    const SCORE_RULE: &str = r#"
+    rule SCORE_RULE
+    when
+        !(user.score < 60)
+    then
+        bonus = user.score * 2
+    "#;
+
+    //cargo test --color=always --bin example null_safe_test::test::test_null_safe --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_null_safe() {
+        let rh = Rush::from(Into::<ExprEngine>::into([PROFILE_RULE]));
+        let flow = |input: &str| -> String {
+            let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+            res.to_string()
+        };
+        assert_eq!(
+            flow(r#"{"user":{"age":20,"name":"ann","phone":null}}"#),
+            r#"{"has_phone":true,"name":"ann"}"#
+        );
+        assert_eq!(flow(r#"{"user":{"age":12}}"#), "{}");
+        assert_eq!(flow(r#"{}"#), r#"{"has_phone":false,"name":"guest"}"#);
+    }
+
+    //cargo test --color=always --bin example null_safe_test::test::test_three_valued --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_three_valued() {
+        let mut ee = ExprEngine::default();
+        ee.set_null_mode(NullMode::ThreeValued);
+        ee.register_rule(SCORE_RULE).unwrap();
+        let rh = Rush::from(ee);
+        let flow = |input: &str| -> String {
+            let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+            res.to_string()
+        };
+        assert_eq!(flow(r#"{"user":{"score":80}}"#), r#"{"bonus":160}"#);
+        assert_eq!(flow(r#"{"user":{"score":40}}"#), "{}");
+        // score不存在时条件为null，不命中
+        assert_eq!(flow(r#"{"user":{}}"#), "{}");
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{AsyncRuleFlow, MultiRush, ReloadableRush, RuleSetLoader, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const RULE_V1: &str = "rule DISCOUNT when level > 1 then discount = 0.9";
+    const RULE_V2: &str = "rule DISCOUNT when level > 1 then discount = 0.8";
+
+    //cargo test --color=always --bin example reload_test::test::test_watch_dir --no-fail-fast -- --exact unstable-options --nocapture
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_watch_dir() {
+        let dir = std::env::temp_dir().join(format!("rush_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 先写到目录外再改名，避免扫描到写了一半的文件
+        let write = |text: &str| {
+            let tmp = dir.with_extension("tmp");
+            std::fs::write(&tmp, text).unwrap();
+            std::fs::rename(tmp, dir.join("discount.rush")).unwrap();
+        };
+        write(RULE_V1);
+
+        let loader = RuleSetLoader::new().register_engine("expr", ExprEngine::default());
+        let build =
+            move |dir: &std::path::Path| loader.load_dir(Rush::new(), dir).map(MultiRush::from);
+        let rr = ReloadableRush::new(build(&dir).unwrap());
+        let errors = Arc::new(AtomicUsize::new(0));
+        let counter = errors.clone();
+        let watcher = rr.watch_dir(&dir, Duration::from_millis(20), build, move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let input = r#"{"level":2}"#.parse::<Value>().unwrap();
+        let res: Value = rr.async_flow(input.clone()).await.unwrap();
+        assert_eq!(res.to_string(), r#"{"discount":0.9}"#);
+
+        // 编译失败时保持旧版本
+        write("rule DISCOUNT when level >");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(rr.version(), 1);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+
+        write(RULE_V2);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(rr.version(), 2);
+        let res: Value = rr.async_flow(input).await.unwrap();
+        assert_eq!(res.to_string(), r#"{"discount":0.8}"#);
+
+        watcher.abort();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
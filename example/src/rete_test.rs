@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde::Deserialize;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const RETE_RULE_ONE: &str = "
+    rule RETE_RULE_ONE
+    when
+        country == '中国';
+        age <= 18;
+    then
+        tag = '中国的年轻人'
+    ";
+    const RETE_RULE_TWO: &str = "
+    rule RETE_RULE_TWO
+    when
+        country == '中国';
+        age > 18 && age < 30;
+    then
+        tag = '中国的青年人'
+    ";
+    const RETE_RULE_THREE: &str = "
+    rule RETE_RULE_THREE
+    when
+        country == '美国';
+        age <= 18;
+    then
+        tag = '美国的年轻人'
+    ";
+
+    #[derive(Deserialize)]
+    struct Tag {
+        #[serde(default = "Default::default")]
+        tag: String,
+    }
+
+    #[test]
+    fn test_rete_network() {
+        let rh = Rush::from(Into::<ExprEngine>::into([
+            RETE_RULE_ONE,
+            RETE_RULE_TWO,
+            RETE_RULE_THREE,
+        ]))
+        .enable_rete();
+
+        let network = rh.network().unwrap();
+        println!("{}", network);
+        // country == '中国' and age <= 18 are shared, age > 18 && age < 30 is split into two tests
+        assert_eq!(network.alpha_nodes().len(), 5);
+        assert_eq!(network.shared_alpha_nodes().len(), 2);
+
+        let res: Tag = rh
+            .flow(r#"{"country":"中国","age":19}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.tag.as_str(), "中国的青年人");
+        let res: Tag = rh
+            .flow(r#"{"country":"美国","age":17}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.tag.as_str(), "美国的年轻人");
+        let res: Tag = rh.flow(r#"{"age":17}"#.parse::<Value>().unwrap()).unwrap();
+        assert_eq!(res.tag.as_str(), "");
+
+        // 先开启再逐条注册，网络在第一次使用时才构建
+        let mut rh = Rush::new().enable_rete();
+        for (name, calc, exec) in
+            Into::<ExprEngine>::into([RETE_RULE_ONE, RETE_RULE_TWO, RETE_RULE_THREE])
+        {
+            rh = rh.register_rule(name, calc, exec);
+        }
+        assert_eq!(rh.network().unwrap().alpha_nodes().len(), 5);
+        rh.delete_rule("RETE_RULE_ONE");
+        assert_eq!(rh.network().unwrap().beta_nodes().len(), 2);
+    }
+
+    const RETE_RULE_ADULT: &str = "
+    rule RETE_RULE_ADULT
+    when
+        is_china(country) && age > 18
+    then
+        tag = '中国的成年人'
+    ";
+    const RETE_RULE_CHINA: &str = "
+    rule RETE_RULE_CHINA
+    when
+        is_china(country)
+    then
+        tag = '中国人'
+    ";
+
+    //cargo test --color=always --bin example rete_test::test::test_rete_share_conjunct --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_rete_share_conjunct() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let rh = Rush::from(Into::<ExprEngine>::into([RETE_RULE_ADULT, RETE_RULE_CHINA]))
+            .register_function("is_china", move |country: String| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(country == "中国")
+            })
+            .enable_rete();
+
+        let network = rh.network().unwrap();
+        // is_china(country) 在两条规则间共享
+        assert_eq!(network.alpha_nodes().len(), 2);
+        assert_eq!(network.shared_alpha_nodes().len(), 1);
+
+        let res: Value = rh
+            .flow(r#"{"country":"中国","age":19}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert!(res["tag"] == "中国的成年人" || res["tag"] == "中国人");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let _: Value = rh
+            .flow(r#"{"country":"美国","age":19}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
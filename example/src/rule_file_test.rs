@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::{ExprEngine, ParseError};
+    use serde_json::Value;
+
+    const MULTI_RULE: &str = r#"
+    rule MULTI_RULE_ONE
+    when
+        whenever > 1
+    then
+        data.one = "when; then"
+    rule MULTI_RULE_TWO
+    when
+        thence == 'rule'
+    then
+        data.two = true
+    "#;
+
+    #[test]
+    fn test_multi_rule() {
+        let rh = Rush::from(Into::<ExprEngine>::into([MULTI_RULE]));
+        let res: Value = rh
+            .flow(r#"{"whenever":2,"thence":"rule"}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(
+            res.to_string(),
+            r#"{"data":{"one":"when; then","two":true}}"#
+        );
+    }
+
+    #[test]
+    fn test_load_rule_file() {
+        let mut ee = ExprEngine::default();
+        ee.load_file("rules/member.rush").unwrap();
+        let rh = Rush::from(ee);
+        let res: Value = rh
+            .flow(r#"{"level":3,"channel":"app"}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(
+            res.to_string(),
+            r#"{"member":{"grade":"gold","note":"level; >= 3"}}"#
+        );
+
+        let err = ExprEngine::default()
+            .register_rule("rule BAD when a > 1 then b = 'x")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::UnterminatedString { .. })
+        ));
+    }
+}
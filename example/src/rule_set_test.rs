@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{MultiRush, RuleFlow, RuleSetLoader, Rush};
+    use rush_expr_engine::ExprEngine;
+    use rush_lua_engine::LuaRuntimeFactory;
+    use serde_json::Value;
+
+    //cargo test --color=always --bin example rule_set_test::test::test_load_rule_dir --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_load_rule_dir() {
+        let loader = RuleSetLoader::new()
+            .register_engine("expr", ExprEngine::default())
+            .register_engine("lua", LuaRuntimeFactory::new());
+        let rh = loader.load_dir(Rush::new(), "rules").unwrap();
+        let res: Value = rh
+            .flow(r#"{"level":1,"channel":"app"}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(
+            res.to_string(),
+            r#"{"member":{"grade":"normal","tag":"level_1"}}"#
+        );
+
+        let err = RuleSetLoader::new()
+            .register_engine("expr", ExprEngine::default())
+            .load_dir(Rush::new(), "rules")
+            .unwrap_err();
+        assert!(err.to_string().contains("MEMBER_TAG"), "{}", err);
+    }
+
+    //cargo test --color=always --bin example rule_set_test::test::test_multi_flow_rule_dir --no-fail-fast -- --exact unstable-options --nocapture
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_multi_flow_rule_dir() {
+        let loader = RuleSetLoader::new()
+            .register_engine("expr", ExprEngine::default())
+            .register_engine("lua", LuaRuntimeFactory::new());
+        let mr: MultiRush = loader.load_dir(Rush::new(), "rules").unwrap().into();
+        // lua规则和表达式规则一起在异步运行时中执行
+        for level in [1, 5] {
+            let res: Value = mr
+                .multi_flow(serde_json::json!({"level":level,"channel":"app"}))
+                .await
+                .unwrap();
+            assert_eq!(res["member"]["tag"], format!("level_{level}"));
+        }
+        let res: Value = mr
+            .flow(r#"{"level":3,"channel":"app"}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(
+            res.to_string(),
+            r#"{"member":{"grade":"gold","note":"level; >= 3","tag":"level_3"}}"#
+        );
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{ConflictStrategy, MultiRush, RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    const SALIENCE_RULE_VIP: &str = "
+    rule SALIENCE_RULE_VIP 会员 expr salience=10
+    when
+        level > 3;
+    then
+        tag = '会员'
+    ";
+    const SALIENCE_RULE_ADULT: &str = "
+    rule SALIENCE_RULE_ADULT 成人 expr salience=1
+    when
+        age > 18;
+    then
+        tag = '成人'
+    ";
+    const SALIENCE_RULE_DEFAULT: &str = "
+    rule SALIENCE_RULE_DEFAULT
+    when
+        age > 0;
+    then
+        tag = '用户'
+    ";
+
+    #[derive(Deserialize)]
+    struct Tag {
+        #[serde(default = "Default::default")]
+        tag: String,
+    }
+
+    fn rush(strategy: ConflictStrategy) -> Rush {
+        Rush::from(Into::<ExprEngine>::into([
+            SALIENCE_RULE_VIP,
+            SALIENCE_RULE_ADULT,
+            SALIENCE_RULE_DEFAULT,
+        ]))
+        .conflict_strategy(strategy)
+    }
+
+    #[test]
+    fn test_conflict_strategy() {
+        let input = || r#"{"age":20,"level":5}"#.parse::<Value>().unwrap();
+
+        let res: Tag = rush(ConflictStrategy::LastWins).flow(input()).unwrap();
+        assert_eq!(res.tag.as_str(), "用户");
+
+        // 优先级高的先执行，相同的key不会被后执行的规则覆盖
+        let res: Tag = rush(ConflictStrategy::AllMatches).flow(input()).unwrap();
+        assert_eq!(res.tag.as_str(), "会员");
+
+        let res: Tag = rush(ConflictStrategy::HighestSalience)
+            .flow(input())
+            .unwrap();
+        assert_eq!(res.tag.as_str(), "会员");
+
+        let res: Tag = rush(ConflictStrategy::FirstMatch).flow(input()).unwrap();
+        assert_eq!(res.tag.as_str(), "会员");
+
+        let res: Tag = rush(ConflictStrategy::FirstMatch)
+            .flow(r#"{"age":20,"level":1}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.tag.as_str(), "成人");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_multi_conflict_strategy() {
+        let mr: MultiRush = rush(ConflictStrategy::HighestSalience).into();
+        for _ in 0..10 {
+            let res: Tag = mr
+                .multi_flow(r#"{"age":20,"level":5}"#.parse::<Value>().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.tag.as_str(), "会员");
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::{ExprEngine, SchemaError};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    const SCHEMA_RULE_ADULT: &str = "
+    rule SCHEMA_RULE_ADULT 成人
+    when
+        age > 18;
+        contain(tags, 'vip');
+    then
+        stage = '成人';
+        profile.name = name;
+    ";
+    const SCHEMA_RULE_TYPO: &str = "
+    rule SCHEMA_RULE_TYPO 拼写错误
+    when
+        agee > 18;
+    then
+        stage = '成人';
+    ";
+
+    fn engine() -> ExprEngine {
+        let mut ee = ExprEngine::default();
+        ee.set_input_schema(&json!({
+            "type": "object",
+            "properties": {
+                "age": {"type": "integer"},
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        }))
+        .unwrap();
+        ee.set_output_schema(&json!({
+            "type": "object",
+            "properties": {
+                "stage": {"type": "string"},
+                "profile": {"type": "object", "properties": {"name": {"type": "string"}}}
+            }
+        }))
+        .unwrap();
+        ee.declare_function("contain", 2);
+        ee
+    }
+
+    #[derive(Deserialize)]
+    struct Stage {
+        stage: String,
+    }
+
+    #[test]
+    fn test_schema_check() {
+        let mut ee = engine();
+        ee.register_rule(SCHEMA_RULE_ADULT).unwrap();
+        let rh = Rush::from(ee);
+        let res: Stage = rh
+            .flow(r#"{"age":20,"name":"jack","tags":["vip"]}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.stage.as_str(), "成人");
+
+        let err = engine().register_rule(SCHEMA_RULE_TYPO).unwrap_err();
+        let err = err.downcast_ref::<SchemaError>().unwrap();
+        assert_eq!(err.rule.as_str(), "SCHEMA_RULE_TYPO");
+        assert!(err.errors[0].contains("agee"));
+    }
+
+    #[test]
+    fn test_schema_check_later() {
+        let mut ee = ExprEngine::default();
+        ee.register_rule(SCHEMA_RULE_TYPO).unwrap();
+        ee.set_input_schema(&json!({"properties": {"age": {"type": "integer"}}}))
+            .unwrap();
+        assert!(ee.check().is_err());
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush, StdGroup};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::{json, Value};
+
+    const STD_RULE: &str = r#"
+    rule STD_RULE
+    when
+        len(trim(name)) > 0 && type_of(tags) == 'array';
+    then
+        name = upper(substr(trim(name), 0, 3));
+        code = pad(to_string(id), 6, '0');
+        tags = sort(distinct(union(tags, split(extra, ','))));
+        price = round(price * 1.13, 2);
+        level = max(floor(score / 10), 1);
+        items = max(items, x -> x.price);
+    "#;
+
+    //cargo test --color=always --bin example std_lib_test::test::test_std_lib --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_std_lib() {
+        let rh = Rush::from(Into::<ExprEngine>::into([STD_RULE]));
+        let res: Value = rh
+            .flow(json!({
+                "name": "  alice ", "id": 42, "tags": ["b", "a"], "extra": "c,a",
+                "price": 10, "score": 57, "items": [{"price": 3}, {"price": 5}]
+            }))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"name":"ALI","code":"000042","tags":["a","b","c"],"price":11.3,"level":5,"items":5})
+        );
+
+        // 去掉String分组之后条件中的trim不存在
+        let rh = Rush::from(Into::<ExprEngine>::into([STD_RULE])).std_groups(&[
+            StdGroup::Math,
+            StdGroup::Collection,
+            StdGroup::Type,
+        ]);
+        let res: anyhow::Result<Value> = rh.flow(json!({"name": "a", "tags": []}));
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::Value;
+
+    const MARKETING_RULE: &str = r#"
+    rule MARKETING_RULE
+    when
+        country in ['中国', '美国'];
+        channel like 'app_%';
+        phone =~ '^1[3-9]\d{9}$'
+    then
+        coupon = 'NEW_' + channel
+    "#;
+
+    //cargo test --color=always --bin example string_op_test::test::test_string_operator --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_string_operator() {
+        let rh = Rush::from(Into::<ExprEngine>::into([MARKETING_RULE]));
+        let flow = |input: &str| -> String {
+            let res: Value = rh.flow(input.parse::<Value>().unwrap()).unwrap();
+            res.to_string()
+        };
+        assert_eq!(
+            flow(r#"{"country":"中国","channel":"app_ios","phone":"13800000000"}"#),
+            r#"{"coupon":"NEW_app_ios"}"#
+        );
+        assert_eq!(
+            flow(r#"{"country":"中国","channel":"web","phone":"13800000000"}"#),
+            "{}"
+        );
+        assert_eq!(
+            flow(r#"{"country":"日本","channel":"app_ios","phone":"13800000000"}"#),
+            "{}"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use rush_core::{FixedClock, RuleFlow, Rush};
+    use rush_expr_engine::ExprEngine;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    const TIME_RULE: &str = r#"
+    rule OLD_ACCOUNT
+    when
+        days_between(created, now()) > 30
+    then
+        old_account = true;
+
+    rule WEEKEND_ORDER
+    when
+        weekday(order_time, 'Asia/Shanghai') >= 6
+    then
+        weekend = true;
+
+    rule BUSINESS_HOURS
+    when
+        hour(now(), 'Asia/Shanghai') >= 9 && hour(now(), 'Asia/Shanghai') < 18
+    then
+        business_hours = true;
+
+    rule PROMOTION
+    when
+        order_time >= @2024-01-01 && order_time < add(@2024-01-01, '7d')
+    then
+        promotion = true;
+        expire = add(order_time, '2h');
+    "#;
+
+    //cargo test --color=always --bin example time_test::test::test_time --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_time() {
+        // 上海时间 2024-01-06 星期六 10:00
+        let clock = Arc::new(FixedClock::parse("2024-01-06T02:00:00Z").unwrap());
+        let rh = Rush::from(Into::<ExprEngine>::into([TIME_RULE])).set_clock(clock.clone());
+        let res: Value = rh
+            .flow(json!({"created": 1700000000, "order_time": "2024-01-06T23:30:00+08:00"}))
+            .unwrap();
+        assert_eq!(
+            res,
+            json!({"old_account":true,"weekend":true,"business_hours":true,"promotion":true,"expire":"2024-01-07T01:30:00+08:00"})
+        );
+
+        // 上海时间 2024-01-06 20:00
+        clock.set(1704542400000);
+        let res: Value = rh
+            .flow(json!({"created": "2024-01-01", "order_time": "2024-01-08T00:30:00Z"}))
+            .unwrap();
+        assert_eq!(res, json!({}));
+    }
+}
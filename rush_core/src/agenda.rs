@@ -0,0 +1,152 @@
+use crate::Rush;
+
+/// 冲突消解策略：多条规则同时命中时，决定哪些规则执行以及执行顺序
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ConflictStrategy {
+    /// 全部执行，按注册顺序，后执行的规则覆盖先执行的结果
+    #[default]
+    LastWins,
+    /// 全部执行，按优先级从高到低，同优先级按注册顺序
+    /// 没有配置合并策略时，写入相同key以先执行的规则为准
+    AllMatches,
+    /// 全部执行，按优先级从低到高，写入相同key时以优先级最高的规则为准
+    HighestSalience,
+    /// 只执行第一条命中的规则，按注册顺序
+    FirstMatch,
+}
+
+impl Rush {
+    pub fn conflict_strategy(mut self, strategy: ConflictStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+    pub fn salience<S: AsRef<str>>(&self, rule: S) -> i32 {
+        self.exec
+            .get(rule.as_ref())
+            .map(|x| x.salience())
+            .unwrap_or(0)
+    }
+    /// 按冲突消解策略对命中的规则排序，rules需要是注册顺序
+    pub fn agenda(&self, mut rules: Vec<String>) -> Vec<String> {
+        match self.strategy {
+            ConflictStrategy::LastWins => {}
+            ConflictStrategy::AllMatches => {
+                rules.sort_by_key(|x| -(self.salience(x) as i64));
+            }
+            ConflictStrategy::HighestSalience => {
+                rules.sort_by_key(|x| self.salience(x));
+            }
+            ConflictStrategy::FirstMatch => {
+                rules.truncate(1);
+            }
+        }
+        rules
+    }
+    // 恢复为注册顺序，并发计算时规则返回的顺序是不确定的
+    pub(crate) fn sort_by_seq(&self, rules: &mut [String]) {
+        rules.sort_by_key(|x| {
+            self.nodes_seq
+                .iter()
+                .position(|n| n == x)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CalcNode, ConflictStrategy, Exec, FunctionSet, Rush};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    struct Always;
+    impl CalcNode for Always {
+        fn when(&self, _fs: Arc<dyn FunctionSet>, _input: &Value) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    struct Salience(i32);
+    impl Exec for Salience {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            _output: &mut Value,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn salience(&self) -> i32 {
+            self.0
+        }
+    }
+
+    fn rush(strategy: ConflictStrategy) -> Rush {
+        Rush::new()
+            .register_rule("r1", vec![Always], Salience(1))
+            .register_rule("r2", vec![Always], Salience(10))
+            .register_rule("r3", vec![Always], Salience(1))
+            .conflict_strategy(strategy)
+    }
+
+    //cargo test --color=always --lib agenda::test::test_agenda --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_agenda() {
+        let rules = || vec!["r1".to_string(), "r2".into(), "r3".into()];
+        assert_eq!(
+            rush(ConflictStrategy::LastWins).agenda(rules()),
+            vec!["r1", "r2", "r3"]
+        );
+        assert_eq!(
+            rush(ConflictStrategy::AllMatches).agenda(rules()),
+            vec!["r2", "r1", "r3"]
+        );
+        assert_eq!(
+            rush(ConflictStrategy::HighestSalience).agenda(rules()),
+            vec!["r1", "r3", "r2"]
+        );
+        assert_eq!(
+            rush(ConflictStrategy::FirstMatch).agenda(rules()),
+            vec!["r1"]
+        );
+    }
+
+    struct Write(i32, &'static str);
+    impl Exec for Write {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            if let Value::Object(map) = output {
+                map.insert("level".into(), self.1.into());
+            }
+            Ok(())
+        }
+        fn salience(&self) -> i32 {
+            self.0
+        }
+    }
+
+    //cargo test --color=always --lib agenda::test::test_agenda_same_key --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_agenda_same_key() {
+        let flow = |strategy: ConflictStrategy| -> Value {
+            Rush::new()
+                .register_rule("low", vec![Always], Write(1, "low"))
+                .register_rule("high", vec![Always], Write(10, "high"))
+                .register_rule("other", vec![Always], Write(1, "other"))
+                .conflict_strategy(strategy)
+                .flow_value(json!({}))
+                .unwrap()
+        };
+        assert_eq!(flow(ConflictStrategy::LastWins), json!({"level":"other"}));
+        assert_eq!(flow(ConflictStrategy::AllMatches), json!({"level":"high"}));
+        assert_eq!(
+            flow(ConflictStrategy::HighestSalience),
+            json!({"level":"high"})
+        );
+        assert_eq!(flow(ConflictStrategy::FirstMatch), json!({"level":"low"}));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{FlowReport, Rush};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// 正向推理的执行记录
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChainRecord {
+    // 每一轮触发的规则，按执行顺序
+    pub cycles: Vec<Vec<String>>,
+    // true: 没有新的规则可以触发，推理收敛; false: 达到了最大轮次
+    pub fixpoint: bool,
+}
+
+impl ChainRecord {
+    pub fn fired(&self) -> Vec<&String> {
+        self.cycles.iter().flatten().collect()
+    }
+}
+
+impl Rush {
+    /// 开启正向推理：规则的输出会合并进工作内存，作为后续轮次的输入
+    /// 每条规则在一次flow中最多触发一次，直到没有新规则命中或达到max_cycles
+    pub fn forward_chaining(mut self, max_cycles: usize) -> Self {
+        self.chain = Some(max_cycles);
+        self
+    }
+    pub fn flow_chain<Obj: Serialize, Out: for<'a> Deserialize<'a>>(
+        &self,
+        obj: Obj,
+    ) -> anyhow::Result<(Out, ChainRecord)> {
+        let value = serde_json::to_value(obj)?;
+        let (result, record) = self.chain_value(value, &mut FlowReport::default())?;
+        let out = Out::deserialize(result)?;
+        Ok((out, record))
+    }
+    pub(crate) fn chain_value(
+        &self,
+        obj: Value,
+        report: &mut FlowReport,
+    ) -> anyhow::Result<(Value, ChainRecord)> {
+        let max_cycles = self.chain.unwrap_or(1);
+        let mut memory = obj.clone();
+        let mut output = Value::Object(Map::new());
+        let mut fired = HashSet::new();
+        let mut record = ChainRecord::default();
+        for cycle in 0..=max_cycles {
+            let rules = self
+                .matched_rules(&memory)?
+                .into_iter()
+                .filter(|x| !fired.contains(x))
+                .collect::<Vec<_>>();
+            if rules.is_empty() {
+                record.fixpoint = true;
+                break;
+            }
+            // 最后一次只检查是否还有规则可以触发，恰好在max_cycles轮收敛也是fixpoint
+            if cycle == max_cycles {
+                break;
+            }
+            let rules = self.agenda(rules);
+            for name in rules.iter() {
+                self.execute_rule(name, &memory, &mut output, report)?;
+                fired.insert(name.clone());
+            }
+            record.cycles.push(rules);
+
+            memory = obj.clone();
+            merge_value(&mut memory, &output);
+        }
+        Ok((output, record))
+    }
+}
+
+/// 把patch合并到base上，对象递归合并，其它类型直接覆盖
+/// base不是对象时无法合并，保持原样
+pub fn merge_value(base: &mut Value, patch: &Value) {
+    if let (Value::Object(base), Value::Object(patch)) = (base, patch) {
+        for (k, v) in patch.iter() {
+            match base.get_mut(k) {
+                Some(b) if b.is_object() && v.is_object() => merge_value(b, v),
+                _ => {
+                    base.insert(k.clone(), v.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CalcNode, Exec, FunctionSet, Rush};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    struct Has(&'static str);
+    impl CalcNode for Has {
+        fn when(&self, _fs: Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<bool> {
+            Ok(input.get(self.0).is_some())
+        }
+    }
+    struct Set(&'static str);
+    impl Exec for Set {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            if let Value::Object(obj) = output {
+                obj.insert(self.0.to_string(), Value::Bool(true));
+            }
+            Ok(())
+        }
+    }
+
+    fn rush(max_cycles: usize) -> Rush {
+        Rush::new()
+            .register_rule("c_to_d", vec![Has("c")], Set("d"))
+            .register_rule("b_to_c", vec![Has("b")], Set("c"))
+            .register_rule("a_to_b", vec![Has("a")], Set("b"))
+            .forward_chaining(max_cycles)
+    }
+
+    //cargo test --color=always --lib chain::test::test_forward_chaining --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_forward_chaining() {
+        let (res, record): (HashMap<String, bool>, _) = rush(10)
+            .flow_chain(r#"{"a":true}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.len(), 3);
+        assert!(record.fixpoint);
+        assert_eq!(
+            record.cycles,
+            vec![vec!["a_to_b"], vec!["b_to_c"], vec!["c_to_d"]]
+        );
+
+        let (res, record): (HashMap<String, bool>, _) = rush(2)
+            .flow_chain(r#"{"a":true}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert!(!record.fixpoint);
+
+        let (res, record): (HashMap<String, bool>, _) = rush(3)
+            .flow_chain(r#"{"a":true}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(record.cycles.len(), 3);
+        assert!(record.fixpoint);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ConditionTrace;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
@@ -23,6 +24,27 @@ pub trait AsyncRuleFlow: RuleFlow + Sync + Send {
 // 计算节点
 pub trait CalcNode: Send + Sync {
     fn when(&self, fs: Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<bool>;
+    // 节点标识，标识相同的节点在rete网络中共享，每次输入只计算一次
+    // 返回None表示该节点不可共享
+    fn identity(&self) -> Option<String> {
+        None
+    }
+    // 最外层由 && 连接的条件拆分为独立的测试，在rete网络中分别共享，返回空表示不拆分
+    fn conjuncts(&self) -> Vec<Arc<dyn CalcNode>> {
+        vec![]
+    }
+    // 解释计算过程，默认只记录结果，表达式引擎可以给出子表达式的值
+    fn explain(&self, fs: Arc<dyn FunctionSet>, input: &Value) -> ConditionTrace {
+        let mut trace = ConditionTrace {
+            expr: self.identity().unwrap_or_default(),
+            ..Default::default()
+        };
+        match self.when(fs, input) {
+            Ok(b) => trace.result = Some(b),
+            Err(e) => trace.error = Some(e.to_string()),
+        }
+        trace
+    }
 }
 // 运算规则
 pub trait Exec: Send + Sync {
@@ -32,6 +54,35 @@ pub trait Exec: Send + Sync {
         input: &Value,
         output: &mut Value,
     ) -> anyhow::Result<()>;
+    // 执行并返回被跳过的赋值：(key, 原因)，默认不跳过
+    // 返回RuleFailedError时只放弃这条规则，不中断flow
+    fn execute_skip(
+        &self,
+        fs: Arc<dyn FunctionSet>,
+        input: &Value,
+        output: &mut Value,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        self.execute(fs, input, output).map(|_| vec![])
+    }
+    // 按合并策略执行：普通赋值写入own，之后按策略合并到output
+    // 复合赋值和$out可以基于已经合并的output计算并直接更新它，默认全部写入own
+    fn execute_merge(
+        &self,
+        fs: Arc<dyn FunctionSet>,
+        input: &Value,
+        _output: &mut Value,
+        own: &mut Value,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        self.execute_skip(fs, input, own)
+    }
+    // 返回RuleFailedError时保证没有写入输出，为true时执行前不需要备份输出用于回滚
+    fn keeps_output_on_failure(&self) -> bool {
+        false
+    }
+    // 规则优先级，值越大越优先，冲突消解时使用
+    fn salience(&self) -> i32 {
+        0
+    }
 }
 // 函数
 pub trait Function: Send + Sync {
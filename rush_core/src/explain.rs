@@ -0,0 +1,205 @@
+use crate::{merge_value, MultiRush, RuleFailedError, Rush};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// 子表达式的计算结果
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExprTrace {
+    pub expr: String,
+    pub value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 一个条件的计算过程
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    pub expr: String,
+    // None: 计算出错
+    pub result: Option<bool>,
+    pub values: Vec<ExprTrace>,
+    pub missing_fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 一条规则的计算过程
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RuleTrace {
+    pub rule: String,
+    pub matched: bool,
+    // 第一个不成立的条件下标
+    pub failed_condition: Option<usize>,
+    pub conditions: Vec<ConditionTrace>,
+    // 规则执行时写入的key，按路径展开，例如：data.code
+    pub written_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Rush::flow_explain 的结果，规则按注册顺序排列
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FlowTrace {
+    pub rules: Vec<RuleTrace>,
+    pub output: Value,
+}
+
+impl FlowTrace {
+    pub fn rule<S: AsRef<str>>(&self, name: S) -> Option<&RuleTrace> {
+        self.rules.iter().find(|x| x.rule == name.as_ref())
+    }
+}
+
+impl Rush {
+    /// 和flow相同的计算过程，但返回每条规则的条件计算细节和写入的key
+    /// 开启前向链式推理时，没有执行的规则记录最后一轮的计算结果
+    /// 条件计算出错不会中断，错误记录在对应的trace中
+    pub fn flow_explain<Obj: Serialize>(&self, obj: Obj) -> anyhow::Result<FlowTrace> {
+        let obj = serde_json::to_value(obj)?;
+        let rules = self
+            .nodes_seq
+            .iter()
+            .map(|x| self.explain_rule(x, &obj))
+            .collect();
+        Ok(self.explain_execute(&obj, rules))
+    }
+    pub(crate) fn explain_rule(&self, rule: &str, obj: &Value) -> RuleTrace {
+        let mut trace = RuleTrace {
+            rule: rule.to_string(),
+            matched: true,
+            ..Default::default()
+        };
+        let nodes = match self.nodes.get(rule) {
+            Some(s) => s,
+            None => return trace,
+        };
+        for (i, node) in nodes.iter().enumerate() {
+            let ct = node.explain(self.functions.share(), obj);
+            if ct.result != Some(true) {
+                trace.matched = false;
+                if trace.failed_condition.is_none() {
+                    trace.failed_condition = Some(i);
+                }
+                if let Some(ref e) = ct.error {
+                    trace.error = Some(e.clone());
+                }
+            }
+            trace.conditions.push(ct);
+        }
+        trace
+    }
+    // 和flow一样按前向链式推理的轮数执行，rules是第一轮对输入的计算结果
+    pub(crate) fn explain_execute(&self, obj: &Value, mut rules: Vec<RuleTrace>) -> FlowTrace {
+        let mut memory = obj.clone();
+        let mut output = Value::Object(Map::new());
+        let mut fired = HashSet::new();
+        for cycle in 0..self.chain.unwrap_or(1) {
+            // 后续轮次用合并了输出的数据重新计算还没有执行的规则
+            if cycle > 0 {
+                for trace in rules.iter_mut().filter(|x| !fired.contains(&x.rule)) {
+                    *trace = self.explain_rule(&trace.rule, &memory);
+                }
+            }
+            let matched = rules
+                .iter()
+                .filter(|x| x.matched && !fired.contains(&x.rule))
+                .map(|x| x.rule.clone())
+                .collect::<Vec<_>>();
+            if matched.is_empty() {
+                break;
+            }
+            for name in self.agenda(matched) {
+                if let Some(trace) = rules.iter_mut().find(|x| x.rule == name) {
+                    self.explain_fire(trace, &memory, &mut output);
+                }
+                fired.insert(name);
+            }
+            memory = obj.clone();
+            merge_value(&mut memory, &output);
+        }
+        FlowTrace { rules, output }
+    }
+    // RuleFailedError和flow一样回滚这条规则的写入
+    fn explain_fire(&self, trace: &mut RuleTrace, obj: &Value, output: &mut Value) {
+        let exec = match self.exec.get(&trace.rule) {
+            Some(s) => s,
+            None => return,
+        };
+        let before = output.clone();
+        let result = self.merge_execute(exec.as_ref(), obj, output);
+        if let Err(ref e) = result {
+            trace.error = Some(e.to_string());
+            if e.downcast_ref::<RuleFailedError>().is_some() {
+                *output = before;
+                return;
+            }
+        }
+        trace.written_keys = diff_paths(&before, output);
+    }
+}
+
+impl MultiRush {
+    pub async fn flow_explain<Obj: Serialize>(&self, obj: Obj) -> anyhow::Result<FlowTrace> {
+        let obj = Arc::new(serde_json::to_value(obj)?);
+        let mut tasks = vec![];
+        for name in self.rush.nodes_seq.iter() {
+            let rh = self.rush.clone();
+            let obj = obj.clone();
+            let name = name.clone();
+            tasks.push(tokio::spawn(async move { rh.explain_rule(&name, &obj) }));
+        }
+        let mut rules = vec![];
+        for i in tasks {
+            rules.push(i.await?);
+        }
+        Ok(self.rush.explain_execute(&obj, rules))
+    }
+}
+
+// 找出after相对before新增或修改的叶子路径
+pub(crate) fn diff_paths(before: &Value, after: &Value) -> Vec<String> {
+    let mut paths = vec![];
+    match after {
+        Value::Object(obj) => {
+            for (k, v) in obj.iter() {
+                diff_value(k.clone(), before.get(k), v, &mut paths);
+            }
+        }
+        _ => diff_value(String::new(), Some(before), after, &mut paths),
+    }
+    paths
+}
+fn diff_value(path: String, before: Option<&Value>, after: &Value, paths: &mut Vec<String>) {
+    if before == Some(after) {
+        return;
+    }
+    match after {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (k, v) in obj.iter() {
+                diff_value(
+                    format!("{path}.{k}"),
+                    before.and_then(|x| x.get(k)),
+                    v,
+                    paths,
+                );
+            }
+        }
+        _ => paths.push(path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::diff_paths;
+    use serde_json::Value;
+
+    //cargo test --color=always --lib explain::test::test_diff_paths --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_diff_paths() {
+        let before: Value = r#"{"a":1,"b":{"c":1,"d":2}}"#.parse().unwrap();
+        let after: Value = r#"{"a":1,"b":{"c":2,"d":2,"e":{}},"f":[1]}"#.parse().unwrap();
+        assert_eq!(diff_paths(&before, &after), vec!["b.c", "b.e", "f"]);
+    }
+}
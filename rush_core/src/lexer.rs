@@ -0,0 +1,296 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+pub const RULE_TAG: &str = "rule";
+pub const WHEN_TAG: &str = "when";
+pub const THEN_TAG: &str = "then";
+pub const CASE_TAG: &str = "case";
+pub const END_TAG: &str = "end";
+
+/// 源码中的字节区间 [start, end)
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    pub fn offset(self, n: usize) -> Self {
+        Self::new(self.start + n, self.end + n)
+    }
+}
+
+/// 规则文本的词法单元
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lexeme {
+    // 标识符、字段路径、数字
+    Word(String),
+    // 字符串常量，已去掉引号并处理转义
+    Str(String),
+    // ;
+    Semi,
+    // 赋值用的 =，不包括 == != >= <=
+    Assign,
+    // 其它运算符和括号
+    Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleToken {
+    pub kind: Lexeme,
+    pub span: Span,
+}
+
+impl RuleToken {
+    pub fn is_keyword(&self, key: &str) -> bool {
+        matches!(self.kind, Lexeme::Word(ref w) if w.eq_ignore_ascii_case(key))
+    }
+}
+
+/// 词法错误，字符串或者块注释没有闭合
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LexError {
+    UnterminatedString(Span),
+    UnterminatedComment(Span),
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnterminatedString(s) => write!(f, "string is not closed at {}", s.start),
+            LexError::UnterminatedComment(s) => write!(f, "/* and */ mismatch at {}", s.start),
+        }
+    }
+}
+
+impl Error for LexError {}
+
+/// 扫描一个字符串常量，s以引号开始，支持 ' 和 "
+/// 转义：\\ \' \" \n \t，其它 \x 原样保留，方便书写正则
+/// 返回去掉引号后的值和常量在s中的字节长度，没有闭合时返回None
+pub fn scan_string(s: &str) -> Option<(String, usize)> {
+    let mut chars = s.char_indices();
+    let quote = chars.next()?.1;
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            return Some((value, i + c.len_utf8()));
+        }
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, 'n')) => value.push('\n'),
+            Some((_, 't')) => value.push('\t'),
+            Some((_, e)) if e == '\\' || e == '\'' || e == '"' => value.push(e),
+            Some((_, e)) => {
+                value.push('\\');
+                value.push(e);
+            }
+            None => return None,
+        }
+    }
+    None
+}
+
+/// 跳过注释，支持 /* */ 和 //，s不以注释开始时返回Some(0)，块注释没有闭合时返回None
+pub fn skip_comment(s: &str) -> Option<usize> {
+    if s.starts_with("//") {
+        return Some(s.find('\n').unwrap_or(s.len()));
+    }
+    if let Some(body) = s.strip_prefix("/*") {
+        return body.find("*/").map(|i| i + 4);
+    }
+    Some(0)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+/// 按需产生词法单元，遇到错误后结束
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<RuleToken, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let src = self.src;
+        let mut i = self.pos;
+        loop {
+            let rest = &src[i..];
+            let c = rest.chars().next()?;
+            if c.is_whitespace() {
+                i += c.len_utf8();
+                continue;
+            }
+            match skip_comment(rest) {
+                Some(0) => break,
+                Some(n) => i += n,
+                None => {
+                    self.pos = src.len();
+                    return Some(Err(LexError::UnterminatedComment(Span::new(i, i + 2))));
+                }
+            }
+        }
+        let rest = &src[i..];
+        let c = rest.chars().next()?;
+        let (kind, len) = if c == '\'' || c == '"' {
+            match scan_string(rest) {
+                Some((s, n)) => (Lexeme::Str(s), n),
+                None => {
+                    self.pos = src.len();
+                    return Some(Err(LexError::UnterminatedString(Span::new(i, src.len()))));
+                }
+            }
+        } else if c == ';' {
+            (Lexeme::Semi, 1)
+        } else if c == '=' {
+            if rest[1..].starts_with(['=', '~']) {
+                (Lexeme::Symbol, 2)
+            } else {
+                (Lexeme::Assign, 1)
+            }
+        } else if "!<>".contains(c) && rest[1..].starts_with('=') {
+            (Lexeme::Symbol, 2)
+        } else if is_word_char(c) {
+            // ascii和非ascii字符分开成词，例如：描述when 中的when依然是关键字
+            let n = rest
+                .find(|e: char| !is_word_char(e) || e.is_ascii() != c.is_ascii())
+                .unwrap_or(rest.len());
+            (Lexeme::Word(rest[..n].to_string()), n)
+        } else {
+            (Lexeme::Symbol, c.len_utf8())
+        };
+        self.pos = i + len;
+        Some(Ok(RuleToken {
+            kind,
+            span: Span::new(i, i + len),
+        }))
+    }
+}
+
+pub fn tokenize(src: &str) -> Result<Vec<RuleToken>, LexError> {
+    Lexer::new(src).collect()
+}
+
+/// 按 ; 拆分语句，去掉空语句
+pub fn split_statements(tokens: &[RuleToken]) -> Vec<&[RuleToken]> {
+    tokens
+        .split(|x| x.kind == Lexeme::Semi)
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+pub fn statement_span(stmt: &[RuleToken]) -> Span {
+    match (stmt.first(), stmt.last()) {
+        (Some(f), Some(l)) => Span::new(f.span.start, l.span.end),
+        _ => Span::default(),
+    }
+}
+
+/// 规则以行首或语句开始处的 rule 关键字开始，rule = ... 是对rule字段赋值
+pub fn is_rule_start(src: &str, tokens: &[RuleToken], i: usize, stmt_start: bool) -> bool {
+    if !tokens[i].is_keyword(RULE_TAG) {
+        return false;
+    }
+    if matches!(tokens.get(i + 1), Some(t) if t.kind == Lexeme::Assign) {
+        return false;
+    }
+    stmt_start || i == 0 || src[tokens[i - 1].span.end..tokens[i].span.start].contains('\n')
+}
+
+/// 一条规则的各部分在单元中的位置，没有找到的关键字为None
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSections {
+    // rule关键字之后到when之前
+    pub header: Range<usize>,
+    pub when: Option<usize>,
+    pub then: Option<usize>,
+    // 下一条规则开始的位置
+    pub end: usize,
+}
+
+/// 从tokens[start]的rule关键字开始，找到头部、when、then和规则的结束位置
+/// 条件中的 rule 不是规则开始，case when ... then ... end 中的then不是关键字
+pub fn scan_rule(src: &str, tokens: &[RuleToken], start: usize) -> RuleSections {
+    let mut i = start + 1;
+    while i < tokens.len() && !tokens[i].is_keyword(WHEN_TAG) {
+        if tokens[i].is_keyword(THEN_TAG) || is_rule_start(src, tokens, i, false) {
+            break;
+        }
+        i += 1;
+    }
+    let mut sections = RuleSections {
+        header: start + 1..i,
+        end: i,
+        ..Default::default()
+    };
+    if i >= tokens.len() || !tokens[i].is_keyword(WHEN_TAG) {
+        return sections;
+    }
+    sections.when = Some(i);
+    i += 1;
+    let mut case_depth = 0;
+    while i < tokens.len() && (case_depth > 0 || !tokens[i].is_keyword(THEN_TAG)) {
+        if tokens[i].is_keyword(CASE_TAG)
+            && matches!(tokens.get(i + 1), Some(t) if t.is_keyword(WHEN_TAG))
+        {
+            case_depth += 1;
+        } else if case_depth > 0 && tokens[i].is_keyword(END_TAG) {
+            case_depth -= 1;
+        }
+        i += 1;
+    }
+    sections.end = i;
+    if i >= tokens.len() {
+        return sections;
+    }
+    sections.then = Some(i);
+    // 赋值，直到下一条规则开始
+    i += 1;
+    let mut stmt_start = true;
+    while i < tokens.len() && !is_rule_start(src, tokens, i, stmt_start) {
+        stmt_start = tokens[i].kind == Lexeme::Semi;
+        i += 1;
+    }
+    sections.end = i;
+    sections
+}
+
+/// 头部的各项，相邻且中间没有空白的单元组成一项，例如：salience=10
+/// 返回每项的文本、区间和是否包含 =
+pub fn header_items(src: &str, tokens: &[RuleToken]) -> Vec<(String, Span, bool)> {
+    let mut items: Vec<(String, Span, bool)> = vec![];
+    let mut last_end = None;
+    for t in tokens.iter() {
+        let text = match t.kind {
+            Lexeme::Str(ref s) => s.clone(),
+            _ => src[t.span.start..t.span.end].to_string(),
+        };
+        match items.last_mut() {
+            Some((item, span, attr)) if last_end == Some(t.span.start) => {
+                item.push_str(&text);
+                span.end = t.span.end;
+                *attr |= t.kind == Lexeme::Assign;
+            }
+            _ => items.push((text, t.span, t.kind == Lexeme::Assign)),
+        }
+        last_end = Some(t.span.end);
+    }
+    items
+}
@@ -1,10 +1,32 @@
+mod agenda;
+mod chain;
 mod define;
+mod explain;
 mod function;
+mod lexer;
+mod loader;
+mod merge;
+mod reload;
+mod report;
+mod rete;
 mod rush;
 mod std_tool;
 mod task_pool;
+mod time;
 
+pub use agenda::*;
+pub use chain::*;
 pub use define::*;
+pub use explain::*;
 pub use function::*;
+pub use lexer::*;
+pub use loader::*;
+pub use merge::*;
+pub use reload::*;
+pub use report::*;
+pub use rete::*;
 pub use rush::*;
+#[cfg(feature = "std-lib")]
+pub use std_tool::StdGroup;
 pub use task_pool::*;
+pub use time::*;
@@ -0,0 +1,371 @@
+use crate::{header_items, is_rule_start, scan_rule, CalcNode, Exec, Lexer, RuleToken, Rush};
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wd_tools::PFErr;
+
+pub const DEFAULT_RULE_ENGINE: &str = "expr";
+pub const RULE_FILE_EXTENSION: &str = "rush";
+
+/// 一条规则的源码，头部格式：rule [name] [description] [engine] [...]
+#[derive(Debug, Clone, Default)]
+pub struct RuleSource {
+    pub name: String,
+    pub description: String,
+    // 头部没有指定时为 expr
+    pub engine: String,
+    // 包含头部的完整源码
+    pub source: String,
+    pub file: Option<PathBuf>,
+}
+
+/// 引擎编译出的规则，条件为空表示总是命中
+pub struct CompiledRule {
+    pub name: String,
+    pub nodes: Vec<Arc<dyn CalcNode>>,
+    pub exec: Box<dyn Exec>,
+}
+
+impl CompiledRule {
+    pub fn new<S: Into<String>, E: Exec + 'static>(name: S, exec: E) -> Self {
+        Self {
+            name: name.into(),
+            nodes: vec![],
+            exec: Box::new(exec),
+        }
+    }
+    pub fn add_node<C: CalcNode + 'static>(mut self, node: C) -> Self {
+        self.nodes.push(Arc::new(node));
+        self
+    }
+}
+
+/// 规则引擎，把规则源码编译为条件和执行
+/// expr, lua, wasm 引擎分别在对应的crate中实现
+pub trait RuleEngine: Send + Sync {
+    fn compile(&self, rule: &RuleSource) -> anyhow::Result<Vec<CompiledRule>>;
+}
+
+/// 从目录、清单文件或文本加载规则，按头部的engine字段分发给对应的引擎，生成一个Rush
+/// 清单文件每行一个规则文件或目录，相对于清单所在目录，#开始的行是注释
+pub struct RuleSetLoader {
+    engines: HashMap<String, Arc<dyn RuleEngine>>,
+    extension: String,
+}
+
+impl Default for RuleSetLoader {
+    fn default() -> Self {
+        Self {
+            engines: HashMap::new(),
+            extension: RULE_FILE_EXTENSION.to_string(),
+        }
+    }
+}
+
+impl RuleSetLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn register_engine<S: Into<String>, E: RuleEngine + 'static>(
+        mut self,
+        name: S,
+        engine: E,
+    ) -> Self {
+        self.engines
+            .insert(name.into().to_lowercase(), Arc::new(engine));
+        self
+    }
+    /// 加载目录时识别的文件后缀，默认为 rush
+    pub fn extension<S: Into<String>>(mut self, ext: S) -> Self {
+        self.extension = ext.into();
+        self
+    }
+
+    pub fn load_str<S: AsRef<str>>(&self, rush: Rush, src: S) -> anyhow::Result<Rush> {
+        let rules = split_rules(src.as_ref(), None)?;
+        self.build(rush, rules)
+    }
+    pub fn load_file<P: AsRef<Path>>(&self, rush: Rush, path: P) -> anyhow::Result<Rush> {
+        let rules = self.read_file(path.as_ref())?;
+        self.build(rush, rules)
+    }
+    /// 递归加载目录下的规则文件，按路径排序
+    pub fn load_dir<P: AsRef<Path>>(&self, rush: Rush, dir: P) -> anyhow::Result<Rush> {
+        let mut rules = vec![];
+        for file in self.list_dir(dir.as_ref())? {
+            rules.extend(self.read_file(&file)?);
+        }
+        self.build(rush, rules)
+    }
+    pub fn load_manifest<P: AsRef<Path>>(&self, rush: Rush, manifest: P) -> anyhow::Result<Rush> {
+        let rules = self.read_manifest(manifest.as_ref())?;
+        self.build(rush, rules)
+    }
+
+    pub fn read_manifest(&self, manifest: &Path) -> anyhow::Result<Vec<RuleSource>> {
+        let text = std::fs::read_to_string(manifest)
+            .map_err(|e| anyhow!("read manifest[{}] failed:{}", manifest.display(), e))?;
+        let base = manifest.parent().unwrap_or_else(|| Path::new(""));
+        let mut rules = vec![];
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let path = base.join(line);
+            if path.is_dir() {
+                for file in self.list_dir(&path)? {
+                    rules.extend(self.read_file(&file)?);
+                }
+            } else {
+                rules.extend(self.read_file(&path)?);
+            }
+        }
+        Ok(rules)
+    }
+    pub fn read_file(&self, path: &Path) -> anyhow::Result<Vec<RuleSource>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("read rule file[{}] failed:{}", path.display(), e))?;
+        split_rules(&text, Some(path))
+    }
+    pub fn list_dir(&self, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let entries = std::fs::read_dir(&dir)
+                .map_err(|e| anyhow!("read rule dir[{}] failed:{}", dir.display(), e))?;
+            for entry in entries {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().and_then(|x| x.to_str()) == Some(&self.extension) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// 编译全部规则并注册到rush中，任意一条失败则返回错误
+    pub fn build(&self, mut rush: Rush, rules: Vec<RuleSource>) -> anyhow::Result<Rush> {
+        for rule in rules.iter() {
+            let engine = match self.engines.get(&rule.engine.to_lowercase()) {
+                Some(s) => s,
+                None => {
+                    return anyhow!(
+                        "rule[{}] engine[{}] is not registered{}",
+                        rule.name,
+                        rule.engine,
+                        file_hint(rule)
+                    )
+                    .err()
+                }
+            };
+            let compiled = engine.compile(rule).map_err(|e| {
+                e.context(format!(
+                    "compile rule[{}] failed{}",
+                    rule.name,
+                    file_hint(rule)
+                ))
+            })?;
+            for i in compiled {
+                rush = rush.register_raw_rule(i.name, i.nodes, i.exec);
+            }
+        }
+        Ok(rush)
+    }
+}
+
+fn file_hint(rule: &RuleSource) -> String {
+    match rule.file {
+        Some(ref f) => format!(" in file[{}]", f.display()),
+        None => String::new(),
+    }
+}
+
+// 行首是规则头部时返回true，规则体不一定能被词法分析，只分析这一行
+fn is_header_line(line: &str) -> bool {
+    let tokens: Vec<RuleToken> = Lexer::new(line).take(2).map_while(Result::ok).collect();
+    !tokens.is_empty() && is_rule_start(line, &tokens, 0, true)
+}
+
+// 第一行之后的下一个规则头部的位置
+fn next_header_line(src: &str) -> usize {
+    let mut pos = 0;
+    for (i, line) in src.split_inclusive('\n').enumerate() {
+        if i > 0 && is_header_line(line) {
+            return pos;
+        }
+        pos += line.len();
+    }
+    src.len()
+}
+
+// expr规则的结束位置，先只分析到下一个行首的规则头部
+// 同一行中也可以开始下一条规则，条件中行首的rule是字段，这时继续分析到再下一个头部
+// 词法错误之后的部分不参与拆分，由引擎编译时报告
+fn expr_rule_end(src: &str, mut stop: usize) -> usize {
+    loop {
+        let (mut tokens, mut complete) = (vec![], true);
+        for t in Lexer::new(&src[..stop]) {
+            match t {
+                Ok(t) => tokens.push(t),
+                Err(_) => {
+                    complete = false;
+                    break;
+                }
+            }
+        }
+        let sections = scan_rule(&src[..stop], &tokens, 0);
+        match tokens.get(sections.end) {
+            Some(t) => return t.span.start,
+            None if complete && stop < src.len() && sections.then.is_none() => {
+                stop += next_header_line(&src[stop..])
+            }
+            None => return stop,
+        }
+    }
+}
+
+/// 把一段文本拆分为多条规则
+/// 先找到下一个行首的规则头部，其它引擎的规则体到这里为止，不做词法分析
+/// expr规则只分析到这里，按规则语法找到结束位置，同一行中也可以开始下一条规则
+pub fn split_rules(src: &str, file: Option<&Path>) -> anyhow::Result<Vec<RuleSource>> {
+    let mut rules: Vec<RuleSource> = vec![];
+    let mut pos = 0;
+    while pos < src.len() {
+        let rest = &src[pos..];
+        if rules.is_empty() {
+            let line = rest.split_inclusive('\n').next().unwrap_or(rest);
+            let text = line.trim();
+            if text.is_empty() || text.starts_with("//") || text.starts_with('#') {
+                pos += line.len();
+                continue;
+            }
+            if !is_header_line(line) {
+                return anyhow!("rule must start with 'rule', found:{}", text).err();
+            }
+        }
+        // 先按行首的规则头部切分，头部只分析第一行，只有expr规则的规则体需要词法分析
+        let chunk = &rest[..next_header_line(rest)];
+        let line = &chunk[..chunk.find('\n').unwrap_or(chunk.len())];
+        let tokens: Vec<RuleToken> = Lexer::new(line).map_while(Result::ok).collect();
+        let sections = scan_rule(line, &tokens, 0);
+        // 头部属性 key=value 不是描述和引擎
+        let mut names = header_items(line, &tokens[sections.header])
+            .into_iter()
+            .filter(|(_, _, attr)| !attr)
+            .map(|(item, _, _)| item);
+        let name = names.next().unwrap_or_default();
+        let description = names.next().unwrap_or_default();
+        let engine = names
+            .next()
+            .unwrap_or_else(|| DEFAULT_RULE_ENGINE.to_string());
+        let end = if engine.eq_ignore_ascii_case(DEFAULT_RULE_ENGINE) {
+            expr_rule_end(rest, chunk.len())
+        } else {
+            chunk.len()
+        };
+        rules.push(RuleSource {
+            name,
+            description,
+            engine,
+            source: rest[..end].to_string(),
+            file: file.map(|x| x.to_path_buf()),
+        });
+        pos += end;
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        split_rules, CompiledRule, Exec, FunctionSet, RuleEngine, RuleFlow, RuleSetLoader,
+        RuleSource, Rush,
+    };
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    struct Tag(String);
+    impl Exec for Tag {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            if let Value::Object(obj) = output {
+                obj.insert(self.0.clone(), Value::Bool(true));
+            }
+            Ok(())
+        }
+    }
+    struct TagEngine;
+    impl RuleEngine for TagEngine {
+        fn compile(&self, rule: &RuleSource) -> anyhow::Result<Vec<CompiledRule>> {
+            Ok(vec![CompiledRule::new(
+                rule.name.clone(),
+                Tag(format!("{}_{}", rule.engine, rule.name)),
+            )])
+        }
+    }
+
+    //cargo test --color=always --lib loader::test::test_split_rules --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_split_rules() {
+        let src = "
+        // comment
+        rule r1 \"first rule\" lua salience=1
+        lua_script:
+            rule = 1
+        rule r2 when a > 1 then b = 1
+        ";
+        let rules = split_rules(src, None).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].name, "r1");
+        assert_eq!(rules[0].description, "first rule");
+        assert_eq!(rules[0].engine, "lua");
+        assert!(rules[0].source.contains("rule = 1"));
+        assert_eq!(rules[1].engine, "expr");
+
+        // 条件中行首的rule是字段，不是新的规则
+        let src = "rule r1\nwhen\nrule > 1;\nthen\na = 1\nrule r2 when true then b = 1";
+        let rules = split_rules(src, None).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].source, "rule r1\nwhen\nrule > 1;\nthen\na = 1\n");
+        assert_eq!(rules[1].name, "r2");
+        // 没有闭合的字符串由引擎编译时报告，拆分时按行找下一条规则
+        let rules = split_rules("rule r1 when a == 'x then b = 1\nrule r2\n", None).unwrap();
+        assert_eq!(rules.len(), 2);
+        // lua的规则体不做词法分析，其中没有闭合的引号不影响拆分
+        let src = "rule r1 \"lua rule\" lua\nlua_script:\n-- it's done\nrule r2 when a > 1 then b = 1; rule r3 when true then c = 1\n";
+        let rules = split_rules(src, None).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            rules[0].source,
+            "rule r1 \"lua rule\" lua\nlua_script:\n-- it's done\n"
+        );
+        assert_eq!(rules[1].source, "rule r2 when a > 1 then b = 1; ");
+        assert_eq!(rules[2].name, "r3");
+
+        assert!(split_rules("a > 1", None).is_err());
+    }
+
+    //cargo test --color=always --lib loader::test::test_loader_dispatch --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_loader_dispatch() {
+        let loader = RuleSetLoader::new()
+            .register_engine("expr", TagEngine)
+            .register_engine("lua", TagEngine);
+        let rh = loader
+            .load_str(Rush::new(), "rule r1 _ lua\nrule r2\n")
+            .unwrap();
+        let res: Value = rh.flow(Value::Null).unwrap();
+        assert_eq!(res.to_string(), r#"{"expr_r2":true,"lua_r1":true}"#);
+
+        assert!(loader.load_str(Rush::new(), "rule r3 _ wasm\n").is_err());
+    }
+}
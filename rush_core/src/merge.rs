@@ -0,0 +1,322 @@
+use crate::{ConflictStrategy, Exec, Rush};
+use anyhow::anyhow;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use wd_tools::{PFErr, PFOk};
+
+/// 多条规则写入相同key时的合并策略
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// 后执行的规则覆盖
+    #[default]
+    Overwrite,
+    /// 保留第一次写入的值
+    KeepFirst,
+    /// 收集到数组中，写入的数组会被展开
+    Collect,
+    /// 数字相加
+    Sum,
+    Max,
+    Min,
+    /// 对象递归合并，其他类型覆盖
+    DeepMerge,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strategy = match s {
+            "overwrite" => MergeStrategy::Overwrite,
+            "keep_first" => MergeStrategy::KeepFirst,
+            "collect" => MergeStrategy::Collect,
+            "sum" => MergeStrategy::Sum,
+            "max" => MergeStrategy::Max,
+            "min" => MergeStrategy::Min,
+            "deep_merge" => MergeStrategy::DeepMerge,
+            _ => return anyhow!("unknown merge strategy[{s}]").err(),
+        };
+        strategy.ok()
+    }
+}
+
+impl MergeStrategy {
+    /// old为None表示之前没有规则写入
+    pub fn merge(&self, path: &str, old: Option<Value>, new: Value) -> anyhow::Result<Value> {
+        let old = match (old, self) {
+            (None, MergeStrategy::Collect) => Value::Array(vec![]),
+            (None, _) => return new.ok(),
+            (Some(old), _) => old,
+        };
+        let value = match self {
+            MergeStrategy::Overwrite => new,
+            MergeStrategy::KeepFirst => old,
+            MergeStrategy::Collect => {
+                let mut list = match old {
+                    Value::Array(list) => list,
+                    v => vec![v],
+                };
+                match new {
+                    Value::Array(n) => list.extend(n),
+                    v => list.push(v),
+                }
+                Value::Array(list)
+            }
+            MergeStrategy::Sum | MergeStrategy::Max | MergeStrategy::Min => {
+                self.number(path, old, new)?
+            }
+            MergeStrategy::DeepMerge => deep_merge(old, new),
+        };
+        value.ok()
+    }
+    // 数字策略的两边都必须是数字或者null
+    fn check(&self, path: &str, old: &Value, new: &Value) -> anyhow::Result<()> {
+        match (self, old, new) {
+            (MergeStrategy::Sum | MergeStrategy::Max | MergeStrategy::Min, a, b) => match (a, b) {
+                (Value::Null, _) | (_, Value::Null) | (Value::Number(_), Value::Number(_)) => {
+                    Ok(())
+                }
+                _ => anyhow!("merge[{path}] {self:?} want number, found {a} and {b}").err(),
+            },
+            _ => Ok(()),
+        }
+    }
+    // null不参与计算
+    fn number(&self, path: &str, old: Value, new: Value) -> anyhow::Result<Value> {
+        self.check(path, &old, &new)?;
+        let (a, b) = match (old, new) {
+            (Value::Null, v) | (v, Value::Null) => return v.ok(),
+            (Value::Number(a), Value::Number(b)) => (a, b),
+            _ => return Value::Null.ok(),
+        };
+        if let MergeStrategy::Sum = self {
+            if let Some(n) = exact_sum(&a, &b) {
+                return Value::Number(n).ok();
+            }
+        }
+        let (x, y) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+        let n = match self {
+            MergeStrategy::Sum => Number::from_f64(x + y).map(Value::Number),
+            MergeStrategy::Max if y > x => Some(Value::Number(b)),
+            MergeStrategy::Min if y < x => Some(Value::Number(b)),
+            _ => Some(Value::Number(a)),
+        };
+        n.unwrap_or(Value::Null).ok()
+    }
+}
+
+// 数字的十进制文本转为整数和小数位数，有指数或者超出范围时返回None
+fn decimal_parts(n: &Number) -> Option<(i128, u32)> {
+    let s = n.to_string();
+    if s.contains(['e', 'E']) {
+        return None;
+    }
+    let (int, frac) = s.split_once('.').unwrap_or((&s, ""));
+    let m = format!("{int}{frac}").parse().ok()?;
+    Some((m, frac.len() as u32))
+}
+
+// 按十进制文本精确相加，避免 0.1 + 0.2 和十进制模式的结果丢失精度
+fn exact_sum(a: &Number, b: &Number) -> Option<Number> {
+    let ((x, sx), (y, sy)) = (decimal_parts(a)?, decimal_parts(b)?);
+    let scale = sx.max(sy);
+    let x = x.checked_mul(10i128.checked_pow(scale - sx)?)?;
+    let y = y.checked_mul(10i128.checked_pow(scale - sy)?)?;
+    let sum = x.checked_add(y)?;
+    let digits = format!(
+        "{:0>width$}",
+        sum.unsigned_abs(),
+        width = scale as usize + 1
+    );
+    let (int, frac) = digits.split_at(digits.len() - scale as usize);
+    let frac = frac.trim_end_matches('0');
+    let sign = if sum < 0 { "-" } else { "" };
+    let text = match frac {
+        "" => format!("{sign}{int}"),
+        _ => format!("{sign}{int}.{frac}"),
+    };
+    Number::from_str(&text).ok()
+}
+
+fn deep_merge(old: Value, new: Value) -> Value {
+    match (old, new) {
+        (Value::Object(mut old), Value::Object(new)) => {
+            for (k, v) in new {
+                let v = match old.remove(&k) {
+                    Some(o) => deep_merge(o, v),
+                    None => v,
+                };
+                old.insert(k, v);
+            }
+            Value::Object(old)
+        }
+        (_, new) => new,
+    }
+}
+
+/// 输出的合并策略，路径用 . 分隔对象的key，没有配置的路径使用默认策略
+/// 除了全部为Overwrite的情况，每条规则的普通赋值写入自己的输出后再合并
+/// $out能读到已经合并的输出，复合赋值已经包含原来的值，直接更新合并后的输出
+#[derive(Debug, Default, Clone)]
+pub struct MergePolicy {
+    default: MergeStrategy,
+    paths: HashMap<String, MergeStrategy>,
+}
+
+impl MergePolicy {
+    pub fn new(default: MergeStrategy) -> Self {
+        Self {
+            default,
+            paths: HashMap::new(),
+        }
+    }
+    pub fn path<P: Into<String>>(mut self, path: P, strategy: MergeStrategy) -> Self {
+        self.paths.insert(path.into(), strategy);
+        self
+    }
+    pub fn is_overwrite(&self) -> bool {
+        self.default == MergeStrategy::Overwrite
+            && self.paths.values().all(|x| *x == MergeStrategy::Overwrite)
+    }
+    /// 把一条规则的输出合并到output中，出错时output不变
+    pub fn merge(&self, output: &mut Value, rule_output: Value) -> anyhow::Result<()> {
+        // 先检查再合并，不需要备份output
+        self.check_path("", Some(output), &rule_output)?;
+        let old = std::mem::take(output);
+        *output = self.merge_path("", Some(old), rule_output)?;
+        Ok(())
+    }
+    // 下面还有配置的路径时逐个key合并
+    fn nested(&self, path: &str) -> bool {
+        path.is_empty()
+            || self.paths.keys().any(|k| {
+                k.len() > path.len() && k.starts_with(path) && k[path.len()..].starts_with('.')
+            })
+    }
+    fn check_path(&self, path: &str, old: Option<&Value>, new: &Value) -> anyhow::Result<()> {
+        if let Some(s) = self.paths.get(path) {
+            return old.map_or(Ok(()), |o| s.check(path, o, new));
+        }
+        match (old, new) {
+            (None | Some(Value::Object(_)), Value::Object(new)) if self.nested(path) => {
+                for (k, v) in new {
+                    let p = match path {
+                        "" => k.clone(),
+                        _ => format!("{path}.{k}"),
+                    };
+                    self.check_path(&p, old.and_then(|o| o.get(k)), v)?;
+                }
+                Ok(())
+            }
+            (Some(o), _) => self.default.check(path, o, new),
+            (None, _) => Ok(()),
+        }
+    }
+    fn merge_path(&self, path: &str, old: Option<Value>, new: Value) -> anyhow::Result<Value> {
+        if let Some(s) = self.paths.get(path) {
+            return s.merge(path, old, new);
+        }
+        let nested = self.nested(path);
+        let (mut map, new) = match (old, new) {
+            (Some(Value::Object(map)), Value::Object(new)) if nested => (map, new),
+            (None, Value::Object(new)) if nested => (Map::new(), new),
+            (old, new) => return self.default.merge(path, old, new),
+        };
+        for (k, v) in new {
+            let p = match path {
+                "" => k.clone(),
+                _ => format!("{path}.{k}"),
+            };
+            let v = self.merge_path(&p, map.remove(&k), v)?;
+            map.insert(k, v);
+        }
+        Value::Object(map).ok()
+    }
+}
+
+impl Rush {
+    /// 所有路径的默认合并策略
+    pub fn merge_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.merge.default = strategy;
+        self
+    }
+    /// 指定路径的合并策略，例如：merge_path("risk.score", MergeStrategy::Sum)
+    pub fn merge_path<P: Into<String>>(mut self, path: P, strategy: MergeStrategy) -> Self {
+        self.merge = self.merge.path(path, strategy);
+        self
+    }
+    pub fn merge_policy(mut self, policy: MergePolicy) -> Self {
+        self.merge = policy;
+        self
+    }
+    // 执行规则并按合并策略写入output，返回被跳过的赋值
+    pub(crate) fn merge_execute(
+        &self,
+        exec: &dyn Exec,
+        obj: &Value,
+        output: &mut Value,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        // AllMatches按优先级从高到低执行，没有配置合并策略时相同的key保留先执行的规则写入的值
+        let keep_first;
+        let policy = match (self.merge.is_overwrite(), self.strategy) {
+            (true, ConflictStrategy::AllMatches) => {
+                keep_first = MergePolicy::new(MergeStrategy::KeepFirst);
+                &keep_first
+            }
+            (true, _) => return exec.execute_skip(self.functions.share(), obj, output),
+            (false, _) => &self.merge,
+        };
+        let mut own = Value::Object(Map::new());
+        let skipped = exec.execute_merge(self.functions.share(), obj, output, &mut own)?;
+        policy.merge(output, own)?;
+        Ok(skipped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MergePolicy, MergeStrategy};
+    use serde_json::{json, Value};
+
+    //cargo test --color=always --lib merge::test::test_merge_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_merge_policy() {
+        let policy = MergePolicy::new(MergeStrategy::KeepFirst)
+            .path("tags", MergeStrategy::Collect)
+            .path("risk.score", MergeStrategy::Sum)
+            .path("risk.level", MergeStrategy::Max)
+            .path("detail", MergeStrategy::DeepMerge);
+        let mut out = Value::Object(Default::default());
+        let writes = [
+            json!({"name":"a","tags":"x","risk":{"score":10,"level":1},"detail":{"a":{"b":1}}}),
+            json!({"name":"b","tags":["y","z"],"risk":{"score":2.5,"level":3,"memo":"m"}}),
+            json!({"risk":{"score":1,"level":2,"memo":"n"},"detail":{"a":{"c":2}}}),
+        ];
+        for i in writes {
+            policy.merge(&mut out, i).unwrap();
+        }
+        assert_eq!(
+            out,
+            json!({"name":"a","tags":["x","y","z"],"risk":{"score":13.5,"level":3,"memo":"m"},"detail":{"a":{"b":1,"c":2}}})
+        );
+
+        let before = out.clone();
+        assert!(policy
+            .merge(&mut out, json!({"risk":{"score":"high"}}))
+            .is_err());
+        assert_eq!(out, before);
+        // 按十进制文本精确相加
+        let mut out = json!({"risk":{"score":0.1}});
+        policy
+            .merge(&mut out, json!({"risk":{"score":0.2}}))
+            .unwrap();
+        assert_eq!(out["risk"]["score"], json!(0.3));
+        assert!(MergePolicy::default().is_overwrite());
+        assert_eq!(
+            "keep_first".parse::<MergeStrategy>().unwrap(),
+            MergeStrategy::KeepFirst
+        );
+        assert!("first".parse::<MergeStrategy>().is_err());
+    }
+}
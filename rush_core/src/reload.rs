@@ -0,0 +1,252 @@
+use crate::{AsyncRuleFlow, RuleEngineDiscovery, RuleFlow};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use wd_tools::sync::Acl;
+
+/// 某个版本的规则集
+#[derive(Debug)]
+pub struct RuleSetVersion<F> {
+    pub version: i32,
+    pub flow: F,
+}
+
+/// 可热更新的规则集，包装Rush或MultiRush
+/// 更新时编译好新的规则集后整体替换，正在执行的flow持有旧版本直到结束
+pub struct ReloadableRush<F> {
+    inner: Acl<RuleSetVersion<F>>,
+    // 替换时先比较版本，写操作需要串行
+    writer: Arc<Mutex<()>>,
+}
+
+impl<F> Clone for ReloadableRush<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            writer: self.writer.clone(),
+        }
+    }
+}
+
+impl<F> ReloadableRush<F> {
+    pub fn new(flow: F) -> Self {
+        let inner = Acl::new(RuleSetVersion { version: 1, flow });
+        let writer = Arc::new(Mutex::new(()));
+        Self { inner, writer }
+    }
+    /// 当前生效的版本
+    pub fn version(&self) -> i32 {
+        self.inner.share().version
+    }
+    /// 获取当前版本的快照，多次调用flow时使用同一个版本
+    pub fn current(&self) -> Arc<RuleSetVersion<F>> {
+        self.inner.share()
+    }
+    /// 替换为新的规则集，返回新版本号
+    pub fn swap(&self, flow: F) -> i32 {
+        let _lock = self.writer.lock().expect("ReloadableRush.swap lock error");
+        let version = self.version() + 1;
+        self.inner.set(RuleSetVersion { version, flow });
+        version
+    }
+    /// 替换为指定版本的规则集，版本号不大于当前版本时不替换
+    pub fn swap_version(&self, version: i32, flow: F) -> bool {
+        let _lock = self.writer.lock().expect("ReloadableRush.swap lock error");
+        if version <= self.version() {
+            return false;
+        }
+        self.inner.set(RuleSetVersion { version, flow });
+        true
+    }
+    /// 构建新的规则集并替换，构建失败时保持旧版本
+    pub fn reload<B: FnOnce() -> anyhow::Result<F>>(&self, build: B) -> anyhow::Result<i32> {
+        let flow = build()?;
+        Ok(self.swap(flow))
+    }
+}
+
+impl<F: Send + Sync + 'static> ReloadableRush<F> {
+    /// 从discovery中升级，discovery的版本大于当前版本时才会升级
+    pub async fn upgrade<D: RuleEngineDiscovery<F> + Sync>(&self, discovery: &D) -> bool {
+        let version = discovery.version();
+        if version <= self.version() {
+            return false;
+        }
+        let flow = discovery.upgrade().await;
+        self.swap_version(version, flow)
+    }
+    /// 定时检查discovery的版本并升级
+    pub fn watch_discovery<D: RuleEngineDiscovery<F> + Send + Sync + 'static>(
+        &self,
+        discovery: D,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let rr = self.clone();
+        tokio::spawn(async move {
+            loop {
+                rr.upgrade(&discovery).await;
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+    /// 定时检查目录下文件的修改时间，有变化时用build重新构建，构建失败保持旧版本并调用on_error
+    /// 扫描目录和构建都在阻塞线程池中执行，不占用异步运行时的工作线程
+    /// 例如：rr.watch_dir("rules", interval, move |dir| loader.load_dir(Rush::new(), dir), |_, e| log(e))
+    pub fn watch_dir<P, B, E>(
+        &self,
+        dir: P,
+        interval: Duration,
+        build: B,
+        on_error: E,
+    ) -> JoinHandle<()>
+    where
+        P: Into<PathBuf>,
+        B: Fn(&Path) -> anyhow::Result<F> + Send + Sync + 'static,
+        E: Fn(&Path, anyhow::Error) + Send + 'static,
+    {
+        let rr = self.clone();
+        let dir = dir.into();
+        let build = Arc::new(build);
+        tokio::spawn(async move {
+            let scan = |dir: PathBuf| tokio::task::spawn_blocking(move || dir_fingerprint(&dir));
+            let mut last = scan(dir.clone()).await.unwrap_or_default();
+            loop {
+                tokio::time::sleep(interval).await;
+                let fp = match scan(dir.clone()).await {
+                    Ok(o) => o,
+                    Err(e) => {
+                        on_error(&dir, anyhow!("scan dir failed:{e}"));
+                        continue;
+                    }
+                };
+                if fp == last {
+                    continue;
+                }
+                last = fp;
+                let (b, d) = (build.clone(), dir.clone());
+                let res = match tokio::task::spawn_blocking(move || b(&d)).await {
+                    Ok(o) => o,
+                    Err(e) => Err(anyhow!("build task failed:{e}")),
+                };
+                match res {
+                    Ok(flow) => {
+                        rr.swap(flow);
+                    }
+                    Err(e) => on_error(&dir, e),
+                }
+            }
+        })
+    }
+}
+
+// 目录下全部文件的路径、修改时间和大小
+fn dir_fingerprint(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(o) => o,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let meta = match entry.metadata() {
+                Ok(o) => o,
+                Err(_) => continue,
+            };
+            if meta.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push((path, meta.modified().ok(), meta.len()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+impl<F: RuleFlow> RuleFlow for ReloadableRush<F> {
+    fn version(&self) -> i32 {
+        ReloadableRush::version(self)
+    }
+    fn flow<Obj: Serialize, Out: for<'a> Deserialize<'a>>(&self, obj: Obj) -> anyhow::Result<Out> {
+        self.current().flow.flow(obj)
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: AsyncRuleFlow + 'static> AsyncRuleFlow for ReloadableRush<F> {
+    async fn async_flow<Obj: Serialize + Send, Out: for<'a> Deserialize<'a>>(
+        &self,
+        obj: Obj,
+    ) -> anyhow::Result<Out> {
+        let current = self.current();
+        current.flow.async_flow(obj).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Exec, FunctionSet, ReloadableRush, RuleEngineDiscovery, RuleFlow, Rush};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use wd_tools::PFErr;
+
+    struct Tag(i32);
+    impl Exec for Tag {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            if let Value::Object(obj) = output {
+                obj.insert("version".into(), self.0.into());
+            }
+            Ok(())
+        }
+    }
+
+    struct Discovery(i32);
+    #[async_trait::async_trait]
+    impl RuleEngineDiscovery<Rush> for Discovery {
+        fn version(&self) -> i32 {
+            self.0
+        }
+        async fn upgrade(&self) -> Rush {
+            Rush::new().register_raw_rule("tag", vec![], Box::new(Tag(self.0)))
+        }
+    }
+
+    //cargo test --color=always --lib reload::test::test_reload_swap --no-fail-fast -- --exact unstable-options --show-output
+    #[tokio::test]
+    async fn test_reload_swap() {
+        let rr =
+            ReloadableRush::new(Rush::new().register_raw_rule("tag", vec![], Box::new(Tag(1))));
+        let old = rr.current();
+        assert_eq!(
+            rr.swap(Rush::new().register_raw_rule("tag", vec![], Box::new(Tag(2)))),
+            2
+        );
+        assert_eq!(RuleFlow::version(&rr), 2);
+
+        // 旧版本的快照不受替换影响
+        let res: Value = old.flow.flow(Value::Null).unwrap();
+        assert_eq!(res.to_string(), r#"{"version":1}"#);
+        let res: Value = rr.flow(Value::Null).unwrap();
+        assert_eq!(res.to_string(), r#"{"version":2}"#);
+
+        assert!(rr
+            .reload(|| anyhow::anyhow!("compile failed").err())
+            .is_err());
+        assert_eq!(rr.version(), 2);
+
+        assert!(!rr.upgrade(&Discovery(2)).await);
+        assert!(rr.upgrade(&Discovery(5)).await);
+        let res: Value = rr.flow(Value::Null).unwrap();
+        assert_eq!(res.to_string(), r#"{"version":5}"#);
+    }
+}
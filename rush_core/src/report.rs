@@ -0,0 +1,249 @@
+use crate::Rush;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt;
+
+/// 规则执行失败，只放弃这条规则的输出，其他规则继续执行
+#[derive(Debug)]
+pub struct RuleFailedError(pub String);
+
+impl fmt::Display for RuleFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule failed:{}", self.0.as_str())
+    }
+}
+
+impl Error for RuleFailedError {}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ArithmeticErrorKind {
+    DivisionByZero,
+    Overflow,
+}
+
+/// 算术运算出错，flow中rule为出错的规则
+/// saturated是溢出方向的边界值，除以0时为None
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArithmeticError {
+    pub rule: Option<String>,
+    pub kind: ArithmeticErrorKind,
+    pub expr: String,
+    pub saturated: Option<Value>,
+}
+
+impl ArithmeticError {
+    pub fn division_by_zero<S: Into<String>>(expr: S) -> Self {
+        Self {
+            rule: None,
+            kind: ArithmeticErrorKind::DivisionByZero,
+            expr: expr.into(),
+            saturated: None,
+        }
+    }
+    pub fn overflow<S: Into<String>>(expr: S, saturated: Value) -> Self {
+        Self {
+            rule: None,
+            kind: ArithmeticErrorKind::Overflow,
+            expr: expr.into(),
+            saturated: Some(saturated),
+        }
+    }
+    /// 算术错误没有规则名时填写，其他错误原样返回
+    pub fn attach_rule(e: anyhow::Error, rule: &str) -> anyhow::Error {
+        match e.downcast::<ArithmeticError>() {
+            Ok(mut ae) => {
+                if ae.rule.is_none() {
+                    ae.rule = Some(rule.to_string());
+                }
+                ae.into()
+            }
+            Err(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref rule) = self.rule {
+            write!(f, "rule[{rule}] ")?;
+        }
+        match self.kind {
+            ArithmeticErrorKind::DivisionByZero => write!(f, "division by zero: {}", self.expr),
+            ArithmeticErrorKind::Overflow => write!(f, "arithmetic overflow: {}", self.expr),
+        }
+    }
+}
+
+impl Error for ArithmeticError {}
+
+/// 一条被跳过的赋值
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SkippedAssign {
+    pub rule: String,
+    pub key: String,
+    pub reason: String,
+}
+
+/// 执行失败但没有中断flow的规则
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailedRule {
+    pub rule: String,
+    pub error: String,
+}
+
+/// Rush::flow_report 的执行报告
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FlowReport {
+    pub skipped: Vec<SkippedAssign>,
+    pub failed_rules: Vec<FailedRule>,
+}
+
+impl Rush {
+    /// 和flow相同，同时返回被跳过的赋值和执行失败的规则
+    pub fn flow_report<Obj: Serialize, Out: for<'a> Deserialize<'a>>(
+        &self,
+        obj: Obj,
+    ) -> anyhow::Result<(Out, FlowReport)> {
+        let value = serde_json::to_value(obj)?;
+        let mut report = FlowReport::default();
+        let result = self.report_value(value, &mut report)?;
+        let out = Out::deserialize(result)?;
+        Ok((out, report))
+    }
+    pub fn execute_report(
+        &self,
+        obj: &Value,
+        list: Vec<String>,
+        report: &mut FlowReport,
+    ) -> anyhow::Result<Value> {
+        let mut output = Value::Object(Map::new());
+        for name in list.iter() {
+            self.execute_rule(name, obj, &mut output, report)?;
+        }
+        Ok(output)
+    }
+    // RuleFailedError时回滚这条规则的输出并记录，其他错误中断flow
+    pub(crate) fn execute_rule(
+        &self,
+        name: &str,
+        obj: &Value,
+        output: &mut Value,
+        report: &mut FlowReport,
+    ) -> anyhow::Result<()> {
+        let exec = match self.exec.get(name) {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        // 规则失败时可能留下部分输出才需要备份
+        let before = match exec.keeps_output_on_failure() {
+            true => None,
+            false => Some(output.clone()),
+        };
+        match self.merge_execute(exec.as_ref(), obj, output) {
+            Ok(skipped) => {
+                for (key, reason) in skipped {
+                    report.skipped.push(SkippedAssign {
+                        rule: name.to_string(),
+                        key,
+                        reason,
+                    });
+                }
+                Ok(())
+            }
+            Err(e) if e.downcast_ref::<RuleFailedError>().is_some() => {
+                if let Some(before) = before {
+                    *output = before;
+                }
+                report.failed_rules.push(FailedRule {
+                    rule: name.to_string(),
+                    error: e.to_string(),
+                });
+                Ok(())
+            }
+            Err(e) => Err(ArithmeticError::attach_rule(e, name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ArithmeticError, CalcNode, Exec, FunctionSet, RuleFailedError, RuleFlow, Rush};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    struct Always;
+    impl CalcNode for Always {
+        fn when(&self, _fs: Arc<dyn FunctionSet>, _input: &Value) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+    }
+    // 写入key后按模式返回：0 成功，1 跳过一个key，2 规则失败
+    struct Write(&'static str, u8);
+    impl Exec for Write {
+        fn execute(
+            &self,
+            fs: Arc<dyn FunctionSet>,
+            input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            self.execute_skip(fs, input, output).map(|_| ())
+        }
+        fn execute_skip(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<Vec<(String, String)>> {
+            if let Value::Object(obj) = output {
+                obj.insert(self.0.to_string(), Value::Bool(true));
+            }
+            match self.1 {
+                1 => Ok(vec![("skip".into(), "missing".into())]),
+                2 => Err(RuleFailedError("missing".into()).into()),
+                _ => Ok(vec![]),
+            }
+        }
+    }
+
+    //cargo test --color=always --lib report::test::test_flow_report --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_flow_report() {
+        let rh = Rush::new()
+            .register_rule("r1", vec![Always], Write("a", 0))
+            .register_rule("r2", vec![Always], Write("b", 2))
+            .register_rule("r3", vec![Always], Write("c", 1));
+        let (res, report): (HashMap<String, bool>, _) = rh.flow_report(Value::Null).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res.get("b"), None);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].rule, "r3");
+        assert_eq!(report.skipped[0].key, "skip");
+        assert_eq!(report.failed_rules.len(), 1);
+        assert_eq!(report.failed_rules[0].rule, "r2");
+    }
+
+    struct DivZero;
+    impl CalcNode for DivZero {
+        fn when(&self, _fs: Arc<dyn FunctionSet>, _input: &Value) -> anyhow::Result<bool> {
+            Err(ArithmeticError::division_by_zero("(a / b)").into())
+        }
+    }
+
+    //cargo test --color=always --lib report::test::test_arithmetic_error --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_arithmetic_error() {
+        let rh = Rush::new()
+            .register_rule("r1", vec![Always], Write("a", 0))
+            .register_rule("r2", vec![DivZero], Write("b", 0));
+        let err = rh.flow::<_, Value>(Value::Null).unwrap_err();
+        let ae = err.downcast_ref::<ArithmeticError>().unwrap();
+        assert_eq!(ae.rule.as_deref(), Some("r2"));
+        assert_eq!(err.to_string(), "rule[r2] division by zero: (a / b)");
+        let rh = rh.enable_rete();
+        let err = rh.flow::<_, Value>(Value::Null).unwrap_err();
+        let ae = err.downcast_ref::<ArithmeticError>().unwrap();
+        assert_eq!(ae.rule.as_deref(), Some("r2"));
+    }
+}
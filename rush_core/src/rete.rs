@@ -0,0 +1,205 @@
+use crate::{ArithmeticError, CalcNode, FunctionSet};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+/// alpha节点：一个条件测试，多条规则中相同的条件只会有一个alpha节点
+pub struct AlphaNode {
+    pub id: usize,
+    pub identity: Option<String>,
+    pub rules: Vec<String>,
+    pub(crate) node: Arc<dyn CalcNode>,
+}
+
+/// beta节点：一条规则，持有它依赖的全部alpha节点，全部成立则规则命中
+#[derive(Debug, Clone)]
+pub struct BetaNode {
+    pub rule: String,
+    pub alphas: Vec<usize>,
+}
+
+impl Debug for AlphaNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ id:{},identity:{:?},rules:{:?} }}",
+            self.id, self.identity, self.rules
+        )
+    }
+}
+
+/// ReteNetwork 由规则的条件编译而成
+/// 1. 条件按最外层的 && 拆分，相同标识的测试合并为一个alpha节点
+/// 2. 每条规则对应一个beta节点，按注册顺序排列
+/// 3. 计算时每个alpha节点对一次输入最多计算一次
+#[derive(Debug, Default)]
+pub struct ReteNetwork {
+    alphas: Vec<AlphaNode>,
+    betas: Vec<BetaNode>,
+}
+
+impl ReteNetwork {
+    pub fn build(seq: &[String], nodes: &HashMap<String, Vec<Arc<dyn CalcNode>>>) -> Self {
+        let mut network = ReteNetwork::default();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for rule in seq.iter() {
+            let list = match nodes.get(rule) {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut alphas = vec![];
+            // 按书写顺序展开，计算时仍然从左到右短路
+            let tests = list.iter().flat_map(|node| match node.conjuncts() {
+                parts if parts.is_empty() => vec![node.clone()],
+                parts => parts,
+            });
+            for node in tests {
+                let identity = node.identity();
+                let id = match identity.as_ref().and_then(|k| index.get(k)) {
+                    Some(id) => *id,
+                    None => {
+                        let id = network.alphas.len();
+                        if let Some(ref k) = identity {
+                            index.insert(k.clone(), id);
+                        }
+                        network.alphas.push(AlphaNode {
+                            id,
+                            identity,
+                            rules: vec![],
+                            node: node.clone(),
+                        });
+                        id
+                    }
+                };
+                if !network.alphas[id].rules.contains(rule) {
+                    network.alphas[id].rules.push(rule.clone());
+                }
+                if !alphas.contains(&id) {
+                    alphas.push(id);
+                }
+            }
+            network.betas.push(BetaNode {
+                rule: rule.clone(),
+                alphas,
+            });
+        }
+        network
+    }
+
+    pub fn alpha_nodes(&self) -> &[AlphaNode] {
+        self.alphas.as_slice()
+    }
+    pub fn beta_nodes(&self) -> &[BetaNode] {
+        self.betas.as_slice()
+    }
+    /// 被多条规则共享的alpha节点
+    pub fn shared_alpha_nodes(&self) -> Vec<&AlphaNode> {
+        self.alphas.iter().filter(|x| x.rules.len() > 1).collect()
+    }
+
+    /// 计算命中的规则，按注册顺序返回
+    pub fn matched(&self, fs: Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<Vec<String>> {
+        let mut memory: Vec<Option<bool>> = vec![None; self.alphas.len()];
+        let mut rules = vec![];
+        'lp: for beta in self.betas.iter() {
+            for id in beta.alphas.iter() {
+                let b = match memory[*id] {
+                    Some(b) => b,
+                    None => {
+                        let b = self.alphas[*id]
+                            .node
+                            .when(fs.clone(), input)
+                            .map_err(|e| ArithmeticError::attach_rule(e, &beta.rule))?;
+                        memory[*id] = Some(b);
+                        b
+                    }
+                };
+                if !b {
+                    continue 'lp;
+                }
+            }
+            rules.push(beta.rule.clone());
+        }
+        Ok(rules)
+    }
+}
+
+impl Display for ReteNetwork {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "alpha nodes:")?;
+        for i in self.alphas.iter() {
+            writeln!(
+                f,
+                "    [{}] {} <- {:?}",
+                i.id,
+                i.identity.as_deref().unwrap_or("_"),
+                i.rules
+            )?;
+        }
+        writeln!(f, "beta nodes:")?;
+        for i in self.betas.iter() {
+            writeln!(f, "    {} -> {:?}", i.rule, i.alphas)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CalcNode, Exec, FunctionSet, RuleFlow, Rush};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct KeyNode(&'static str, Arc<AtomicUsize>);
+    impl CalcNode for KeyNode {
+        fn when(&self, _fs: Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<bool> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            Ok(input.get(self.0).is_some())
+        }
+        fn identity(&self) -> Option<String> {
+            Some(self.0.to_string())
+        }
+    }
+    struct Tag(&'static str);
+    impl Exec for Tag {
+        fn execute(
+            &self,
+            _fs: Arc<dyn FunctionSet>,
+            _input: &Value,
+            output: &mut Value,
+        ) -> anyhow::Result<()> {
+            if let Value::Object(obj) = output {
+                obj.insert(self.0.to_string(), Value::Bool(true));
+            }
+            Ok(())
+        }
+    }
+
+    //cargo test --color=always --lib rete::test::test_rete_share --no-fail-fast -- --exact unstable-options --show-output
+    #[test]
+    fn test_rete_share() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let node = |k| KeyNode(k, count.clone());
+        let rh = Rush::new()
+            .register_rule("r1", vec![node("a"), node("b")], Tag("r1"))
+            .register_rule("r2", vec![node("a"), node("c")], Tag("r2"))
+            .register_rule("r3", vec![node("a")], Tag("r3"))
+            .enable_rete();
+
+        let network = rh.network().unwrap();
+        assert_eq!(network.alpha_nodes().len(), 3);
+        assert_eq!(network.shared_alpha_nodes().len(), 1);
+
+        let res: HashMap<String, bool> = rh
+            .flow(r#"{"a":1,"c":1}"#.parse::<Value>().unwrap())
+            .unwrap();
+        assert_eq!(res.get("r1"), None);
+        assert_eq!(res.get("r2"), Some(&true));
+        assert_eq!(res.get("r3"), Some(&true));
+        // a,b,c each evaluated once
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+}
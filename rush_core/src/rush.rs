@@ -1,19 +1,29 @@
 use crate::std_tool::{ArrayContain, ArraySub, Env};
 use crate::{
-    AsyncRuleFlow, CalcNode, Exec, Function, FunctionImpl, FunctionSet, HostFunction, RuleFlow,
+    ArithmeticError, AsyncRuleFlow, CalcNode, ConflictStrategy, Exec, FlowReport, Function,
+    FunctionImpl, FunctionSet, HostFunction, MergePolicy, ReteNetwork, RuleFlow,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use wd_tools::sync::Acl;
 
 pub struct Rush {
     pub(crate) functions: Acl<HashMap<String, Arc<dyn Function>>>,
-    pub(crate) nodes: HashMap<String, Vec<Box<dyn CalcNode>>>,
+    pub(crate) nodes: HashMap<String, Vec<Arc<dyn CalcNode>>>,
     pub(crate) nodes_seq: Vec<String>,
     pub(crate) exec: HashMap<String, Box<dyn Exec>>,
+    // 开启后使用rete网络匹配规则，否则按顺序逐条计算
+    pub(crate) rete: bool,
+    // 第一次匹配时构建，注册或删除规则后重新构建
+    pub(crate) network: OnceLock<ReteNetwork>,
+    pub(crate) strategy: ConflictStrategy,
+    // 多条规则写入相同key时的合并策略
+    pub(crate) merge: MergePolicy,
+    // 正向推理的最大轮次，None表示单次匹配
+    pub(crate) chain: Option<usize>,
 }
 
 impl Debug for Rush {
@@ -54,26 +64,44 @@ impl Rush {
             nodes,
             nodes_seq,
             exec: rules,
+            rete: false,
+            network: OnceLock::new(),
+            strategy: ConflictStrategy::default(),
+            merge: MergePolicy::default(),
+            chain: None,
         };
-        rh.raw_register_function("contain", ArrayContain {})
+        let rh = rh
+            .raw_register_function("contain", ArrayContain {})
             .raw_register_function("sub", ArraySub {})
-            .raw_register_function("env", Env::default())
+            .raw_register_function("env", Env::default());
+        #[cfg(feature = "std-lib")]
+        let rh = rh.std_groups(&crate::StdGroup::ALL);
+        rh
     }
     pub fn register_rule<
         C: CalcNode + Send + Sync + 'static,
         E: Exec + Send + Sync + 'static,
         T: Into<String>,
     >(
-        mut self,
+        self,
         name: T,
         nodes: Vec<C>,
         exec: E,
     ) -> Self {
-        let name = name.into();
-        let mut ns: Vec<Box<dyn CalcNode>> = vec![];
+        let mut ns: Vec<Arc<dyn CalcNode>> = vec![];
         for i in nodes {
-            ns.push(Box::new(i));
+            ns.push(Arc::new(i));
         }
+        self.register_raw_rule(name, ns, Box::new(exec))
+    }
+    /// 注册已经装箱的条件和执行，用于规则集加载等动态场景
+    pub fn register_raw_rule<T: Into<String>>(
+        mut self,
+        name: T,
+        ns: Vec<Arc<dyn CalcNode>>,
+        exec: Box<dyn Exec>,
+    ) -> Self {
+        let name = name.into();
         let mut index = usize::MAX;
         if self.nodes.contains_key(&name) {
             for (i,n) in self.nodes_seq.iter().enumerate(){
@@ -88,7 +116,8 @@ impl Rush {
         }
         self.nodes_seq.push(name.clone());
         self.nodes.insert(name.clone(), ns);
-        self.exec.insert(name, Box::new(exec));
+        self.exec.insert(name, exec);
+        self.reset_network();
         self
     }
     pub fn delete_rule<T: AsRef<str>>(&mut self, name: T) {
@@ -106,6 +135,31 @@ impl Rush {
         }
         self.nodes.remove(name.as_ref());
         self.exec.remove(name.as_ref());
+        self.reset_network();
+    }
+    /// 把所有规则的条件编译为rete网络，相同的条件在多条规则间只计算一次
+    /// 网络在第一次匹配时构建，批量注册规则时不会重复构建
+    pub fn enable_rete(mut self) -> Self {
+        self.rete = true;
+        self
+    }
+    pub fn disable_rete(mut self) -> Self {
+        self.rete = false;
+        self.reset_network();
+        self
+    }
+    pub fn network(&self) -> Option<&ReteNetwork> {
+        if !self.rete {
+            return None;
+        }
+        Some(
+            self.network
+                .get_or_init(|| ReteNetwork::build(&self.nodes_seq, &self.nodes)),
+        )
+    }
+    // 规则变化后在下次匹配时重新构建
+    fn reset_network(&mut self) {
+        self.network = OnceLock::new();
     }
     pub fn raw_register_function<S: Into<String>, F: Function>(self, name: S, function: F) -> Self {
         self.functions.update(|x| {
@@ -133,29 +187,46 @@ impl Rush {
     }
 
     pub fn execute(&self, obj: &Value, list: Vec<String>) -> anyhow::Result<Value> {
-        let mut output = Value::Object(Map::new());
-        for name in list.iter() {
-            if let Some(r) = self.exec.get(name) {
-                r.execute(self.functions.share(), obj, &mut output)?;
-            }
-        }
-        Ok(output)
+        self.execute_report(obj, list, &mut FlowReport::default())
     }
     /// input_value
     /// 1. 计算匹配到的规则
-    /// 2. 找出规则进行结果生成
-    fn flow_value(&self, obj: Value) -> anyhow::Result<Value> {
+    /// 2. 按冲突消解策略排序
+    /// 3. 找出规则进行结果生成
+    pub(crate) fn flow_value(&self, obj: Value) -> anyhow::Result<Value> {
+        self.report_value(obj, &mut FlowReport::default())
+    }
+    pub(crate) fn report_value(
+        &self,
+        obj: Value,
+        report: &mut FlowReport,
+    ) -> anyhow::Result<Value> {
+        if self.chain.is_some() {
+            return self.chain_value(obj, report).map(|x| x.0);
+        }
+        let rules = self.matched_rules(&obj)?;
+        let rules = self.agenda(rules);
+        self.execute_report(&obj, rules, report)
+    }
+    /// 按注册顺序返回命中的规则
+    pub fn matched_rules(&self, obj: &Value) -> anyhow::Result<Vec<String>> {
+        if let Some(network) = self.network() {
+            return network.matched(self.functions.share(), obj);
+        }
         let mut rules = vec![];
         'lp: for k in self.nodes_seq.iter() {
             let v = self.nodes.get(k).unwrap();
             for i in v.iter() {
-                if !i.when(self.functions.share(), &obj)? {
+                let b = i
+                    .when(self.functions.share(), obj)
+                    .map_err(|e| ArithmeticError::attach_rule(e, k))?;
+                if !b {
                     continue 'lp;
                 }
             }
             rules.push(k.to_string());
         }
-        self.execute(&obj, rules)
+        Ok(rules)
     }
 }
 
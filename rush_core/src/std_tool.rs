@@ -1,3 +1,5 @@
+#[cfg(feature = "std-lib")]
+use crate::{Clock, SystemClock, Temporal, Zone};
 use crate::{Function, FunctionSet, Rush};
 use anyhow::anyhow;
 use serde_json::Value;
@@ -163,3 +165,814 @@ impl Function for ArraySub {
         return Ok(Value::Bool(false));
     }
 }
+
+/// 标准库函数的分组，Rush::new 默认注册全部分组，len 同时属于String和Collection
+#[cfg(feature = "std-lib")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StdGroup {
+    /// len, lower, upper, trim, substr, split, replace, pad
+    String,
+    /// abs, min, max, round, floor, ceil, pow
+    Math,
+    /// len, distinct, union, intersect, sort
+    Collection,
+    /// type_of, to_number, to_string
+    Type,
+    /// now, today, parse_date, to_timezone, days_between, duration_between, weekday, hour, add
+    Time,
+}
+
+#[cfg(feature = "std-lib")]
+type StdCall = fn(&str, Vec<Value>) -> anyhow::Result<Value>;
+
+#[cfg(feature = "std-lib")]
+impl StdGroup {
+    pub const ALL: [StdGroup; 5] = [
+        StdGroup::String,
+        StdGroup::Math,
+        StdGroup::Collection,
+        StdGroup::Type,
+        StdGroup::Time,
+    ];
+    fn functions(&self) -> Vec<(&'static str, Arc<dyn Function>)> {
+        let mut list: Vec<(&'static str, Arc<dyn Function>)> = self
+            .calls()
+            .into_iter()
+            .map(|(name, call)| {
+                (
+                    name,
+                    Arc::new(StdFunction { name, call }) as Arc<dyn Function>,
+                )
+            })
+            .collect();
+        if let StdGroup::Time = self {
+            list.extend(clock_functions(Arc::new(SystemClock)));
+        }
+        list
+    }
+    fn calls(&self) -> Vec<(&'static str, StdCall)> {
+        match self {
+            StdGroup::String => vec![
+                ("len", std_lib::len),
+                ("lower", std_lib::lower),
+                ("upper", std_lib::upper),
+                ("trim", std_lib::trim),
+                ("substr", std_lib::substr),
+                ("split", std_lib::split),
+                ("replace", std_lib::replace),
+                ("pad", std_lib::pad),
+            ],
+            StdGroup::Math => vec![
+                ("abs", std_lib::abs),
+                ("min", std_lib::min),
+                ("max", std_lib::max),
+                ("round", std_lib::round),
+                ("floor", std_lib::floor),
+                ("ceil", std_lib::ceil),
+                ("pow", std_lib::pow),
+            ],
+            StdGroup::Collection => vec![
+                ("len", std_lib::len),
+                ("distinct", std_lib::distinct),
+                ("union", std_lib::union),
+                ("intersect", std_lib::intersect),
+                ("sort", std_lib::sort),
+            ],
+            StdGroup::Type => vec![
+                ("type_of", std_lib::type_of),
+                ("to_number", std_lib::to_number),
+                ("to_string", std_lib::to_string),
+            ],
+            StdGroup::Time => vec![
+                ("parse_date", std_lib::parse_date),
+                ("to_timezone", std_lib::to_timezone),
+                ("days_between", std_lib::days_between),
+                ("duration_between", std_lib::duration_between),
+                ("weekday", std_lib::weekday),
+                ("hour", std_lib::hour),
+                ("add", std_lib::add),
+            ],
+        }
+    }
+}
+
+// 依赖时钟的函数
+#[cfg(feature = "std-lib")]
+fn clock_functions(clock: Arc<dyn Clock>) -> Vec<(&'static str, Arc<dyn Function>)> {
+    vec![
+        ("now", Arc::new(Now(clock.clone()))),
+        ("today", Arc::new(Today(clock))),
+    ]
+}
+
+/// now()，当前的UTC时间
+#[cfg(feature = "std-lib")]
+pub struct Now(Arc<dyn Clock>);
+
+#[cfg(feature = "std-lib")]
+impl Function for Now {
+    fn call(&self, _fs: Arc<dyn FunctionSet>, args: Vec<Value>) -> anyhow::Result<Value> {
+        if !args.is_empty() {
+            return anyhow!("function[now] want no args").err();
+        }
+        let t = Temporal::datetime(self.0.now_millis(), 0)?;
+        Value::String(t.to_string()).ok()
+    }
+}
+
+/// today([tz])，指定时区的当前日期，默认UTC
+#[cfg(feature = "std-lib")]
+pub struct Today(Arc<dyn Clock>);
+
+#[cfg(feature = "std-lib")]
+impl Function for Today {
+    fn call(&self, _fs: Arc<dyn FunctionSet>, args: Vec<Value>) -> anyhow::Result<Value> {
+        let zone = match args.as_slice() {
+            [] => Zone::UTC,
+            [Value::String(tz)] => Zone::parse(tz)?,
+            _ => return anyhow!("function[today] want args: ([time zone])").err(),
+        };
+        let now = self.0.now_millis();
+        let t = Temporal::datetime(now, zone.offset_at(now))?;
+        let days = t.local_days().unwrap_or(0);
+        Value::String(Temporal::Date(days).to_string()).ok()
+    }
+}
+
+/// 标准库函数，name用于错误信息
+#[cfg(feature = "std-lib")]
+pub struct StdFunction {
+    name: &'static str,
+    call: StdCall,
+}
+
+#[cfg(feature = "std-lib")]
+impl Function for StdFunction {
+    fn call(&self, _fs: Arc<dyn FunctionSet>, args: Vec<Value>) -> anyhow::Result<Value> {
+        (self.call)(self.name, args)
+    }
+}
+
+#[cfg(feature = "std-lib")]
+impl Rush {
+    /// 只保留指定分组的标准库函数，例如去掉Math：std_groups(&[StdGroup::String, StdGroup::Collection, StdGroup::Type])
+    /// 会替换同名的函数，需要在注册自定义函数之前调用
+    pub fn std_groups(self, groups: &[StdGroup]) -> Self {
+        self.functions.update(|x| {
+            let mut map = (*x).clone();
+            for g in StdGroup::ALL.iter() {
+                for (name, _) in g.functions() {
+                    map.remove(name);
+                }
+            }
+            for g in groups.iter() {
+                for (name, f) in g.functions() {
+                    map.insert(name.to_string(), f);
+                }
+            }
+            map
+        });
+        self
+    }
+    /// 替换now和today使用的时钟，需要在std_groups之后调用
+    pub fn set_clock<C: Clock + 'static>(self, clock: C) -> Self {
+        let list = clock_functions(Arc::new(clock));
+        self.functions.update(|x| {
+            let mut map = (*x).clone();
+            for (name, f) in list.iter() {
+                map.insert(name.to_string(), f.clone());
+            }
+            map
+        });
+        self
+    }
+}
+
+#[cfg(feature = "std-lib")]
+mod std_lib {
+    use crate::{time, Temporal, Zone};
+    use anyhow::anyhow;
+    use serde_json::{Number, Value};
+    use std::cmp::Ordering;
+    use wd_tools::{PFErr, PFOk};
+
+    fn want_args(name: &str, args: &[Value], min: usize, max: usize) -> anyhow::Result<()> {
+        if args.len() < min || args.len() > max {
+            return anyhow!(
+                "function[{name}] want {min}..={max} args, found {}",
+                args.len()
+            )
+            .err();
+        }
+        Ok(())
+    }
+    fn string<'a>(name: &str, args: &'a [Value], i: usize) -> anyhow::Result<&'a str> {
+        match args.get(i) {
+            Some(Value::String(s)) => s.as_str().ok(),
+            v => anyhow!("function[{name}] arg[{i}] want string, found {v:?}").err(),
+        }
+    }
+    fn float(name: &str, args: &[Value], i: usize) -> anyhow::Result<f64> {
+        match args.get(i) {
+            Some(Value::Number(n)) => n.as_f64().unwrap_or(0.0).ok(),
+            v => anyhow!("function[{name}] arg[{i}] want number, found {v:?}").err(),
+        }
+    }
+    fn int(name: &str, args: &[Value], i: usize) -> anyhow::Result<i64> {
+        match args.get(i) {
+            Some(Value::Number(n)) if n.is_i64() => n.as_i64().unwrap_or(0).ok(),
+            v => anyhow!("function[{name}] arg[{i}] want integer, found {v:?}").err(),
+        }
+    }
+    fn array(name: &str, args: &mut [Value], i: usize) -> anyhow::Result<Vec<Value>> {
+        match args.get_mut(i).map(std::mem::take) {
+            Some(Value::Array(list)) => list.ok(),
+            Some(Value::Null) => vec![].ok(),
+            v => anyhow!("function[{name}] arg[{i}] want array, found {v:?}").err(),
+        }
+    }
+    fn number(name: &str, f: f64) -> anyhow::Result<Value> {
+        match Number::from_f64(f) {
+            Some(n) => Value::Number(n).ok(),
+            None => anyhow!("function[{name}] result is NAN").err(),
+        }
+    }
+    // 没有小数部分并且在i64范围内时返回整数
+    fn integral(name: &str, f: f64) -> anyhow::Result<Value> {
+        if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+            return Value::from(f as i64).ok();
+        }
+        number(name, f)
+    }
+    fn compare(name: &str, a: &Value, b: &Value) -> anyhow::Result<Ordering> {
+        match (a, b) {
+            (Value::Number(x), Value::Number(y)) => {
+                let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+                x.partial_cmp(&y).unwrap_or(Ordering::Equal).ok()
+            }
+            (Value::String(x), Value::String(y)) => x.cmp(y).ok(),
+            _ => anyhow!("function[{name}] can not compare [{a}] and [{b}]").err(),
+        }
+    }
+
+    // 字符串的字符数，数组的长度，对象的key数量
+    pub fn len(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        let n = match &args[0] {
+            Value::Null => 0,
+            Value::String(s) => s.chars().count(),
+            Value::Array(list) => list.len(),
+            Value::Object(map) => map.len(),
+            v => return anyhow!("function[{name}] can not get len of {v}").err(),
+        };
+        Value::from(n).ok()
+    }
+    pub fn lower(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        Value::String(string(name, &args, 0)?.to_lowercase()).ok()
+    }
+    pub fn upper(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        Value::String(string(name, &args, 0)?.to_uppercase()).ok()
+    }
+    pub fn trim(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        Value::String(string(name, &args, 0)?.trim().to_string()).ok()
+    }
+    // substr(s, start[, len])，按字符计算，start为负数时从末尾开始，越界时截断
+    pub fn substr(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 3)?;
+        let chars: Vec<char> = string(name, &args, 0)?.chars().collect();
+        let count = chars.len() as i64;
+        let start = match int(name, &args, 1)? {
+            i if i < 0 => (count + i).max(0),
+            i => i.min(count),
+        };
+        let end = match args.len() {
+            3 => start.saturating_add(int(name, &args, 2)?.max(0)).min(count),
+            _ => count,
+        };
+        let s: String = chars[start as usize..end as usize].iter().collect();
+        Value::String(s).ok()
+    }
+    pub fn split(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let (s, sep) = (string(name, &args, 0)?, string(name, &args, 1)?);
+        let list = match sep {
+            "" => s.chars().map(|c| Value::String(c.to_string())).collect(),
+            _ => s.split(sep).map(|x| Value::String(x.to_string())).collect(),
+        };
+        Value::Array(list).ok()
+    }
+    pub fn replace(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 3, 3)?;
+        let s = string(name, &args, 0)?;
+        let (from, to) = (string(name, &args, 1)?, string(name, &args, 2)?);
+        if from.is_empty() {
+            return Value::String(s.to_string()).ok();
+        }
+        Value::String(s.replace(from, to)).ok()
+    }
+    // pad的最大宽度，避免分配过大的字符串
+    const MAX_PAD_WIDTH: u64 = 1 << 20;
+    // pad(s, width[, fill])，width为正数时在左边填充，负数时在右边填充，默认填充空格
+    pub fn pad(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 3)?;
+        let s = string(name, &args, 0)?;
+        let width = int(name, &args, 1)?;
+        if width.unsigned_abs() > MAX_PAD_WIDTH {
+            return anyhow!("function[{name}] width[{width}] exceeds {MAX_PAD_WIDTH}").err();
+        }
+        let fill = match args.len() {
+            3 => string(name, &args, 2)?,
+            _ => " ",
+        };
+        let n = (width.unsigned_abs() as usize).saturating_sub(s.chars().count());
+        if n == 0 || fill.is_empty() {
+            return Value::String(s.to_string()).ok();
+        }
+        let padding: String = fill.chars().cycle().take(n).collect();
+        let s = match width < 0 {
+            true => format!("{s}{padding}"),
+            false => format!("{padding}{s}"),
+        };
+        Value::String(s).ok()
+    }
+
+    pub fn abs(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        if let Ok(i) = int(name, &args, 0) {
+            if let Some(i) = i.checked_abs() {
+                return Value::from(i).ok();
+            }
+        }
+        number(name, float(name, &args, 0)?.abs())
+    }
+    // min(a, b, ...) 或者 min(array)，忽略null，空时返回null
+    fn best(name: &str, mut args: Vec<Value>, want: Ordering) -> anyhow::Result<Value> {
+        if args.len() == 1 && args[0].is_array() {
+            args = array(name, &mut args, 0)?;
+        }
+        let mut best: Option<Value> = None;
+        for i in args.into_iter().filter(|x| !x.is_null()) {
+            best = match best {
+                Some(b) if compare(name, &i, &b)? != want => Some(b),
+                _ => Some(i),
+            };
+        }
+        best.unwrap_or(Value::Null).ok()
+    }
+    pub fn min(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        best(name, args, Ordering::Less)
+    }
+    pub fn max(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        best(name, args, Ordering::Greater)
+    }
+    // round(n[, digits])，四舍五入到指定的小数位数
+    pub fn round(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 2)?;
+        let f = float(name, &args, 0)?;
+        match args.len() {
+            2 => {
+                let scale = 10f64.powi(int(name, &args, 1)?.clamp(-15, 15) as i32);
+                number(name, (f * scale).round() / scale)
+            }
+            _ => integral(name, f.round()),
+        }
+    }
+    pub fn floor(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        integral(name, float(name, &args, 0)?.floor())
+    }
+    pub fn ceil(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        integral(name, float(name, &args, 0)?.ceil())
+    }
+    // 整数的非负整数次幂返回整数，溢出时返回浮点数
+    pub fn pow(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        if let (Ok(x), Ok(y)) = (int(name, &args, 0), int(name, &args, 1)) {
+            if let Some(n) = u32::try_from(y).ok().and_then(|y| x.checked_pow(y)) {
+                return Value::from(n).ok();
+            }
+        }
+        number(name, float(name, &args, 0)?.powf(float(name, &args, 1)?))
+    }
+
+    // 去重，保留第一次出现的顺序
+    pub fn distinct(name: &str, mut args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        let mut result: Vec<Value> = vec![];
+        for i in array(name, &mut args, 0)? {
+            if !result.contains(&i) {
+                result.push(i);
+            }
+        }
+        Value::Array(result).ok()
+    }
+    pub fn union(name: &str, mut args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let mut list = array(name, &mut args, 0)?;
+        list.extend(array(name, &mut args, 1)?);
+        distinct(name, vec![Value::Array(list)])
+    }
+    pub fn intersect(name: &str, mut args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let (a, b) = (array(name, &mut args, 0)?, array(name, &mut args, 1)?);
+        let list = a.into_iter().filter(|x| b.contains(x)).collect();
+        distinct(name, vec![Value::Array(list)])
+    }
+    // sort(array[, 'desc'])，只能排序数字或者字符串
+    pub fn sort(name: &str, mut args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 2)?;
+        let desc = match args.len() {
+            2 => match string(name, &args, 1)? {
+                "asc" => false,
+                "desc" => true,
+                s => return anyhow!("function[{name}] order want asc or desc, found {s}").err(),
+            },
+            _ => false,
+        };
+        let mut list = array(name, &mut args, 0)?;
+        let mut error = None;
+        list.sort_by(|a, b| match compare(name, a, b) {
+            Ok(o) => o,
+            Err(e) => {
+                error = Some(e);
+                Ordering::Equal
+            }
+        });
+        if let Some(e) = error {
+            return e.err();
+        }
+        if desc {
+            list.reverse();
+        }
+        Value::Array(list).ok()
+    }
+
+    pub fn type_of(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        let ty = match args[0] {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        Value::String(ty.to_string()).ok()
+    }
+    // 字符串按整数或者浮点数解析，bool转为1和0，null转为0
+    pub fn to_number(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        match &args[0] {
+            Value::Number(n) => Value::Number(n.clone()).ok(),
+            Value::Null => Value::from(0).ok(),
+            Value::Bool(b) => Value::from(*b as i64).ok(),
+            Value::String(s) => match (s.trim().parse::<i64>(), s.trim().parse::<f64>()) {
+                (Ok(i), _) => Value::from(i).ok(),
+                (_, Ok(f)) => number(name, f),
+                _ => anyhow!("function[{name}] can not convert [{s}] to number").err(),
+            },
+            v => anyhow!("function[{name}] can not convert [{v}] to number").err(),
+        }
+    }
+    // 字符串原样返回，其它类型转为json文本
+    pub fn to_string(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 1)?;
+        match &args[0] {
+            Value::String(s) => Value::String(s.clone()).ok(),
+            v => Value::String(v.to_string()).ok(),
+        }
+    }
+
+    // 字符串为日期或者时间，数字为秒级时间戳，超出0000-9999年时报错
+    fn temporal(name: &str, args: &[Value], i: usize) -> anyhow::Result<Temporal> {
+        let t = match args.get(i) {
+            Some(Value::String(s)) => Temporal::parse(s),
+            Some(Value::Number(n)) => match n.as_f64().map(|f| f * 1000.0) {
+                // 先检查范围，as i64 会静默饱和
+                Some(ms) if ms.abs() < i64::MAX as f64 => Some(
+                    Temporal::datetime(ms as i64, 0)
+                        .map_err(|e| anyhow!("function[{name}] arg[{i}] {e}"))?,
+                ),
+                _ => {
+                    return anyhow!("function[{name}] arg[{i}] timestamp [{n}] out of range").err()
+                }
+            },
+            _ => None,
+        };
+        match t {
+            Some(Temporal::Duration(_)) | None => anyhow!(
+                "function[{name}] arg[{i}] want date or datetime, found {:?}",
+                args.get(i)
+            )
+            .err(),
+            Some(t) => t.ok(),
+        }
+    }
+    // 可选的时区参数，没有时为None
+    fn zone(name: &str, args: &[Value], i: usize) -> anyhow::Result<Option<Zone>> {
+        match args.get(i) {
+            None => None.ok(),
+            Some(Value::String(tz)) => Some(Zone::parse(tz)?).ok(),
+            Some(v) => anyhow!("function[{name}] arg[{i}] want time zone, found {v}").err(),
+        }
+    }
+    fn text(t: Temporal) -> anyhow::Result<Value> {
+        Value::String(t.to_string()).ok()
+    }
+    // parse_date(value[, tz])，没有时区的时间按tz解析，结果显示为tz的时间
+    pub fn parse_date(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 1, 2)?;
+        let zone = zone(name, &args, 1)?;
+        let t = match &args[0] {
+            Value::String(s) => match Temporal::parse_in(s, &zone.unwrap_or(Zone::UTC)) {
+                Some(Temporal::Duration(_)) | None => {
+                    return anyhow!("function[{name}] can not parse [{s}] as date").err()
+                }
+                Some(t) => t,
+            },
+            _ => temporal(name, &args, 0)?,
+        };
+        text(zone.map(|z| t.in_zone(&z)).unwrap_or(t))
+    }
+    pub fn to_timezone(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let t = temporal(name, &args, 0)?;
+        text(t.in_zone(&zone(name, &args, 1)?.unwrap_or(Zone::UTC)))
+    }
+    // days_between(a, b)，b的日期减去a的日期，各自按自己的时区取日期
+    pub fn days_between(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let a = temporal(name, &args, 0)?.local_days().unwrap_or(0);
+        let b = temporal(name, &args, 1)?.local_days().unwrap_or(0);
+        match b.checked_sub(a) {
+            Some(d) => Value::from(d).ok(),
+            None => anyhow!("function[{name}] days between [{a}] and [{b}] overflow").err(),
+        }
+    }
+    pub fn duration_between(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let a = temporal(name, &args, 0)?.instant().unwrap_or(0);
+        let b = temporal(name, &args, 1)?.instant().unwrap_or(0);
+        match b.checked_sub(a) {
+            Some(d) => text(Temporal::Duration(d)),
+            None => anyhow!("function[{name}] duration between [{a}] and [{b}] overflow").err(),
+        }
+    }
+    // 按时区转换后的本地时间，没有时区参数时使用值自己的时区
+    fn local(name: &str, args: &[Value]) -> anyhow::Result<Temporal> {
+        want_args(name, args, 1, 2)?;
+        let t = temporal(name, args, 0)?;
+        match zone(name, args, 1)? {
+            Some(z) => t.in_zone(&z).ok(),
+            None => t.ok(),
+        }
+    }
+    // ISO星期，1是星期一，7是星期日
+    pub fn weekday(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        let t = local(name, &args)?;
+        Value::from(time::weekday(t.local_days().unwrap_or(0))).ok()
+    }
+    pub fn hour(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        let h = match local(name, &args)? {
+            Temporal::DateTime { millis, offset } => match millis.checked_add(offset as i64 * 1000)
+            {
+                Some(local) => local.rem_euclid(86_400_000) / 3_600_000,
+                None => return anyhow!("function[{name}] time [{millis}ms] overflow").err(),
+            },
+            _ => 0,
+        };
+        Value::from(h).ok()
+    }
+    // add(time, duration)
+    pub fn add(name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        want_args(name, &args, 2, 2)?;
+        let t = temporal(name, &args, 0)?;
+        let d = match &args[1] {
+            Value::String(s) => Temporal::parse(s),
+            _ => None,
+        };
+        match d {
+            Some(Temporal::Duration(ms)) => text(t.add(ms)?),
+            _ => anyhow!(
+                "function[{name}] arg[1] want duration like '5d', found {}",
+                args[1]
+            )
+            .err(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-lib"))]
+mod test {
+    use crate::{FixedClock, FunctionSet, Rush, StdGroup};
+    use anyhow::anyhow;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use wd_tools::PFErr;
+
+    fn call(rh: &Rush, name: &str, args: Value) -> anyhow::Result<Value> {
+        let fs: Arc<dyn FunctionSet> = rh.functions.share();
+        let args = match args {
+            Value::Array(list) => list,
+            _ => vec![],
+        };
+        match fs.get(name) {
+            Some(f) => f.call(fs.clone(), args),
+            None => anyhow!("function[{name}] not found").err(),
+        }
+    }
+
+    //cargo test --color=always --lib std_tool::test::test_std_lib --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_std_lib() {
+        let rh = Rush::new();
+        let cases = [
+            ("len", json!(["héllo"]), json!(5)),
+            ("len", json!([[1, 2, 3]]), json!(3)),
+            ("len", json!([{"a": 1}]), json!(1)),
+            ("len", json!([null]), json!(0)),
+            ("lower", json!(["AbC"]), json!("abc")),
+            ("upper", json!(["AbC"]), json!("ABC")),
+            ("trim", json!(["  a b "]), json!("a b")),
+            ("substr", json!(["hello", 1, 3]), json!("ell")),
+            ("substr", json!(["hello", -3]), json!("llo")),
+            ("substr", json!(["hello", 3, 10]), json!("lo")),
+            ("substr", json!(["加拿大", 1, 1]), json!("拿")),
+            ("substr", json!(["hello", 1, i64::MAX]), json!("ello")),
+            ("split", json!(["a,b,,c", ","]), json!(["a", "b", "", "c"])),
+            ("split", json!(["ab", ""]), json!(["a", "b"])),
+            ("replace", json!(["a-b-c", "-", "+"]), json!("a+b+c")),
+            ("pad", json!(["7", 3, "0"]), json!("007")),
+            ("pad", json!(["ab", -4]), json!("ab  ")),
+            ("pad", json!(["abcd", 2]), json!("abcd")),
+            ("abs", json!([-3]), json!(3)),
+            ("abs", json!([-1.5]), json!(1.5)),
+            ("min", json!([3, 1.5, 2]), json!(1.5)),
+            ("min", json!([[3, null, 2]]), json!(2)),
+            ("max", json!([["b", "c", "a"]]), json!("c")),
+            ("max", json!([[]]), json!(null)),
+            ("round", json!([2.5]), json!(3)),
+            ("round", json!([1.23456, 2]), json!(1.23)),
+            ("floor", json!([-1.5]), json!(-2)),
+            ("ceil", json!([1.2]), json!(2)),
+            ("pow", json!([2, 10]), json!(1024)),
+            ("pow", json!([2, -1]), json!(0.5)),
+            ("pow", json!([4, 0.5]), json!(2.0)),
+            ("distinct", json!([[1, 2, 1, "a", "a"]]), json!([1, 2, "a"])),
+            ("union", json!([[1, 2], [2, 3]]), json!([1, 2, 3])),
+            ("intersect", json!([[1, 2, 3, 2], [2, 3, 4]]), json!([2, 3])),
+            ("sort", json!([[3, 1, 2]]), json!([1, 2, 3])),
+            (
+                "sort",
+                json!([["b", "a", "c"], "desc"]),
+                json!(["c", "b", "a"]),
+            ),
+            ("type_of", json!([[1]]), json!("array")),
+            ("type_of", json!([null]), json!("null")),
+            ("to_number", json!([" 42 "]), json!(42)),
+            ("to_number", json!(["1.5"]), json!(1.5)),
+            ("to_number", json!([true]), json!(1)),
+            ("to_string", json!([12]), json!("12")),
+            ("to_string", json!([{"a": 1}]), json!(r#"{"a":1}"#)),
+        ];
+        for (name, args, want) in cases {
+            let res = call(&rh, name, args.clone());
+            assert_eq!(res.ok(), Some(want), "{name}{args}");
+        }
+
+        let errors = [
+            ("upper", json!([1])),
+            ("substr", json!(["a"])),
+            ("pad", json!(["a", i64::MAX])),
+            ("pad", json!(["a", i64::MIN, "0"])),
+            ("sort", json!([[1, "a"]])),
+            ("sort", json!([[1], "up"])),
+            ("to_number", json!(["abc"])),
+            ("min", json!([1, "a"])),
+        ];
+        for (name, args) in errors {
+            assert!(call(&rh, name, args.clone()).is_err(), "{name}{args}");
+        }
+
+        // 去掉Math分组，len依然由String分组提供
+        let rh = Rush::new().std_groups(&[StdGroup::String, StdGroup::Type]);
+        assert!(call(&rh, "abs", json!([-1])).is_err());
+        assert!(call(&rh, "sort", json!([[1]])).is_err());
+        assert_eq!(call(&rh, "len", json!(["ab"])).unwrap(), json!(2));
+        assert_eq!(call(&rh, "contain", json!([[1], 1])).unwrap(), json!(true));
+        let rh = Rush::new().std_groups(&[]);
+        assert!(call(&rh, "len", json!(["ab"])).is_err());
+    }
+
+    //cargo test --color=always --lib std_tool::test::test_time_functions --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_time_functions() {
+        // 2024-01-06 是星期六
+        let clock = FixedClock::parse("2024-01-06T20:30:00Z").unwrap();
+        let rh = Rush::new().set_clock(clock);
+        let cases = [
+            ("now", json!([]), json!("2024-01-06T20:30:00Z")),
+            ("today", json!([]), json!("2024-01-06")),
+            ("today", json!(["Asia/Shanghai"]), json!("2024-01-07")),
+            (
+                "parse_date",
+                json!(["2024-01-05 08:00", "+08:00"]),
+                json!("2024-01-05T08:00:00+08:00"),
+            ),
+            (
+                "parse_date",
+                json!(["2024-01-05T00:00:00Z", "Asia/Tokyo"]),
+                json!("2024-01-05T09:00:00+09:00"),
+            ),
+            (
+                "parse_date",
+                json!([1704412800]),
+                json!("2024-01-05T00:00:00Z"),
+            ),
+            (
+                "to_timezone",
+                json!(["2024-01-05T23:00:00Z", "-05:00"]),
+                json!("2024-01-05T18:00:00-05:00"),
+            ),
+            // 时区名按时区数据库计算夏令时
+            (
+                "to_timezone",
+                json!(["2024-07-05T23:00:00Z", "America/New_York"]),
+                json!("2024-07-05T19:00:00-04:00"),
+            ),
+            (
+                "parse_date",
+                json!(["2024-07-05 10:00", "Europe/Berlin"]),
+                json!("2024-07-05T10:00:00+02:00"),
+            ),
+            (
+                "hour",
+                json!(["2024-01-05T12:00:00Z", "Europe/Berlin"]),
+                json!(13),
+            ),
+            (
+                "days_between",
+                json!(["2023-12-01", "2024-01-06T20:30:00Z"]),
+                json!(36),
+            ),
+            (
+                "days_between",
+                json!(["2024-01-06", "2024-01-01"]),
+                json!(-5),
+            ),
+            (
+                "duration_between",
+                json!(["2024-01-06T08:00:00Z", "2024-01-06T20:30:00Z"]),
+                json!("12h30m"),
+            ),
+            ("weekday", json!(["2024-01-06"]), json!(6)),
+            (
+                "weekday",
+                json!(["2024-01-06T20:30:00Z", "Asia/Shanghai"]),
+                json!(7),
+            ),
+            (
+                "hour",
+                json!(["2024-01-06T20:30:00Z", "Asia/Shanghai"]),
+                json!(4),
+            ),
+            ("add", json!(["2024-01-31", "1d"]), json!("2024-02-01")),
+            (
+                "add",
+                json!(["2024-01-06T20:30:00+08:00", "-1h30m"]),
+                json!("2024-01-06T19:00:00+08:00"),
+            ),
+        ];
+        for (name, args, want) in cases {
+            let res = call(&rh, name, args.clone());
+            assert_eq!(res.ok(), Some(want), "{name}{args}");
+        }
+        let errors = [
+            ("parse_date", json!(["5d"])),
+            ("parse_date", json!(["2024-01-05", "Mars/Olympus"])),
+            ("add", json!(["2024-01-05", 1])),
+            ("weekday", json!(["tomorrow"])),
+            ("now", json!([1])),
+            // 超出范围的时间戳报错，不会溢出
+            ("hour", json!([9.3e18, "+08:00"])),
+            ("duration_between", json!([-9.3e18, 9.3e18])),
+            ("to_timezone", json!([9.3e18, "+08:00"])),
+            ("parse_date", json!([1e300])),
+            ("days_between", json!([-9.3e18, 0])),
+            ("weekday", json!([9.3e18])),
+            ("add", json!([9.3e18, "1d"])),
+            ("add", json!(["9999-12-31", "1d"])),
+        ];
+        for (name, args) in errors {
+            assert!(call(&rh, name, args.clone()).is_err(), "{name}{args}");
+        }
+        // 时钟超出范围时同样报错
+        let rh = Rush::new().set_clock(FixedClock::new(i64::MAX));
+        assert!(call(&rh, "now", json!([])).is_err());
+        assert!(call(&rh, "today", json!(["+08:00"])).is_err());
+    }
+}
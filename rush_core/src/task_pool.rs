@@ -1,4 +1,4 @@
-use crate::Rush;
+use crate::{ArithmeticError, AsyncRuleFlow, RuleFlow, Rush};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
@@ -16,7 +16,7 @@ impl MulMsg {
 
 #[derive(Debug)]
 pub struct MultiRush {
-    rush: Arc<Rush>,
+    pub(crate) rush: Arc<Rush>,
 }
 impl MultiRush {
     pub async fn multi_flow<Obj: Serialize, Out: for<'de> Deserialize<'de>>(
@@ -24,6 +24,11 @@ impl MultiRush {
         obj: Obj,
     ) -> anyhow::Result<Out> {
         let obj: Value = serde_json::to_value(obj)?;
+        if self.rush.chain.is_some() {
+            //正向推理每轮依赖上一轮的结果，无法并发
+            let val = self.rush.flow_value(obj)?;
+            return Ok(serde_json::from_value(val)?);
+        }
         let (send, mut recv) = mpsc::channel(self.rush.nodes.len());
         let obj = Arc::new(obj);
         for (k, _) in self.rush.nodes.iter() {
@@ -66,7 +71,9 @@ impl MultiRush {
         let mut rules = vec![];
         for _ in 0..self.rush.nodes.len() {
             if let Some(i) = recv.recv().await {
-                if i.result? {
+                if i.result
+                    .map_err(|e| ArithmeticError::attach_rule(e, &i.rule_name))?
+                {
                     rules.push(i.rule_name);
                 }
             } else {
@@ -75,6 +82,8 @@ impl MultiRush {
             }
         }
         drop(send);
+        self.rush.sort_by_seq(&mut rules);
+        let rules = self.rush.agenda(rules);
         let val = self.rush.execute(&obj, rules)?;
         let val = serde_json::from_value(val)?;
         Ok(val)
@@ -88,3 +97,20 @@ impl From<Rush> for MultiRush {
         }
     }
 }
+
+impl RuleFlow for MultiRush {
+    fn flow<Obj: Serialize, Out: for<'a> Deserialize<'a>>(&self, obj: Obj) -> anyhow::Result<Out> {
+        self.rush.flow(obj)
+    }
+}
+
+// 异步执行时并发计算条件
+#[async_trait::async_trait]
+impl AsyncRuleFlow for MultiRush {
+    async fn async_flow<Obj: Serialize + Send, Out: for<'a> Deserialize<'a>>(
+        &self,
+        obj: Obj,
+    ) -> anyhow::Result<Out> {
+        self.multi_flow(obj).await
+    }
+}
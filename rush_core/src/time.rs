@@ -0,0 +1,553 @@
+use anyhow::anyhow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wd_tools::{PFErr, PFOk};
+
+const DAY_MILLIS: i64 = 86_400_000;
+// 支持的时间范围 0000-01-01T00:00:00Z 到 9999-12-31T23:59:59.999Z，范围内加上时区偏移不会溢出
+const MIN_MILLIS: i64 = -62_167_219_200_000;
+const MAX_MILLIS: i64 = 253_402_300_799_999;
+
+/// 当前时间，毫秒时间戳。测试时用FixedClock替换，保证结果确定
+pub trait Clock: Send + Sync {
+    fn now_millis(&self) -> i64;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_millis(&self) -> i64 {
+        self.as_ref().now_millis()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        }
+    }
+}
+
+/// 固定的时间，可以用set修改
+#[derive(Debug, Default)]
+pub struct FixedClock(AtomicI64);
+
+impl FixedClock {
+    pub fn new(millis: i64) -> Self {
+        Self(AtomicI64::new(millis))
+    }
+    /// 从日期或者时间文本创建，例如：2024-01-05T10:00:00+08:00
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match Temporal::parse(s).and_then(|t| t.instant()) {
+            Some(millis) => Self::new(millis).ok(),
+            None => anyhow!("FixedClock: [{s}] is not date or datetime").err(),
+        }
+    }
+    pub fn set(&self, millis: i64) {
+        self.0.store(millis, AtomicOrdering::Relaxed)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now_millis(&self) -> i64 {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// 日期、时间和时长，运行时以ISO格式的字符串表示
+/// 日期：2024-01-05
+/// 时间：2024-01-05T10:30:00+08:00，没有时区时为UTC
+/// 时长：1w2d3h4m5s6ms，可以带负号
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Temporal {
+    // 1970-01-01以来的天数
+    Date(i64),
+    // UTC毫秒时间戳，offset是显示用的时区偏移，单位秒
+    DateTime { millis: i64, offset: i32 },
+    // 毫秒
+    Duration(i64),
+}
+
+impl Temporal {
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::parse_in(s, &Zone::UTC)
+    }
+    /// 没有时区的时间按zone解析
+    pub fn parse_in(s: &str, zone: &Zone) -> Option<Self> {
+        let s = s.trim();
+        if s.len() >= 10 && s.as_bytes()[4] == b'-' {
+            let days = parse_date(s.get(..10)?)?;
+            if s.len() == 10 {
+                return Some(Temporal::Date(days));
+            }
+            let rest = s.get(10..)?.strip_prefix(['T', ' '])?;
+            return parse_time(days, rest, zone);
+        }
+        parse_duration(s).map(Temporal::Duration)
+    }
+    /// UTC毫秒时间戳创建时间，超出0000-9999年时报错
+    pub fn datetime(millis: i64, offset: i32) -> anyhow::Result<Self> {
+        Temporal::DateTime { millis, offset }.checked()
+    }
+    // 日期和时间必须在支持的范围内
+    fn checked(self) -> anyhow::Result<Self> {
+        match self.instant() {
+            _ if matches!(self, Temporal::Duration(_)) => self.ok(),
+            Some(m) if (MIN_MILLIS..=MAX_MILLIS).contains(&m) => self.ok(),
+            _ => anyhow!("time[{self:?}] out of range, supports 0000-01-01 to 9999-12-31").err(),
+        }
+    }
+    /// 日期为UTC零点，时长为None
+    pub fn instant(&self) -> Option<i64> {
+        match self {
+            Temporal::Date(d) => d.checked_mul(DAY_MILLIS),
+            Temporal::DateTime { millis, .. } => Some(*millis),
+            Temporal::Duration(_) => None,
+        }
+    }
+    /// 在自己的时区下的日期
+    pub fn local_days(&self) -> Option<i64> {
+        match self {
+            Temporal::Date(d) => Some(*d),
+            Temporal::DateTime { millis, offset } => Some(
+                millis
+                    .checked_add(*offset as i64 * 1000)?
+                    .div_euclid(DAY_MILLIS),
+            ),
+            Temporal::Duration(_) => None,
+        }
+    }
+    /// 转换到指定时区，日期不变
+    pub fn with_offset(self, offset: i32) -> Self {
+        match self {
+            Temporal::DateTime { millis, .. } => Temporal::DateTime { millis, offset },
+            t => t,
+        }
+    }
+    /// 转换到指定时区，偏移按这个时刻计算，日期不变
+    pub fn in_zone(self, zone: &Zone) -> Self {
+        match self {
+            Temporal::DateTime { millis, .. } => self.with_offset(zone.offset_at(millis)),
+            t => t,
+        }
+    }
+    /// 日期和时间比较时，日期取时间所在时区的零点；时长只能和时长比较
+    pub fn compare(&self, other: &Temporal) -> Option<Ordering> {
+        let midnight = |d: &i64, offset: &i32| d * DAY_MILLIS - *offset as i64 * 1000;
+        let ord = match (self, other) {
+            (Temporal::Duration(a), Temporal::Duration(b)) => a.cmp(b),
+            (Temporal::Date(a), Temporal::Date(b)) => a.cmp(b),
+            (Temporal::Date(d), Temporal::DateTime { millis, offset }) => {
+                midnight(d, offset).cmp(millis)
+            }
+            (Temporal::DateTime { millis, offset }, Temporal::Date(d)) => {
+                millis.cmp(&midnight(d, offset))
+            }
+            (Temporal::DateTime { millis: a, .. }, Temporal::DateTime { millis: b, .. }) => {
+                a.cmp(b)
+            }
+            _ => return None,
+        };
+        Some(ord)
+    }
+    /// 两个字符串都是日期、时间或者时长并且可以比较时返回顺序
+    pub fn compare_str(a: &str, b: &str) -> Option<Ordering> {
+        Self::parse(a)?.compare(&Self::parse(b)?)
+    }
+    /// 加上时长，日期加整天时还是日期
+    pub fn add(&self, millis: i64) -> anyhow::Result<Self> {
+        let overflow = || anyhow!("time[{self}] add [{millis}ms] overflow");
+        let t = match self {
+            Temporal::Date(d) if millis % DAY_MILLIS == 0 => {
+                Temporal::Date(d.checked_add(millis / DAY_MILLIS).ok_or_else(overflow)?)
+            }
+            Temporal::Date(d) => Temporal::DateTime {
+                millis: d
+                    .checked_mul(DAY_MILLIS)
+                    .and_then(|x| x.checked_add(millis))
+                    .ok_or_else(overflow)?,
+                offset: 0,
+            },
+            Temporal::DateTime { millis: m, offset } => Temporal::DateTime {
+                millis: m.checked_add(millis).ok_or_else(overflow)?,
+                offset: *offset,
+            },
+            Temporal::Duration(d) => {
+                Temporal::Duration(d.checked_add(millis).ok_or_else(overflow)?)
+            }
+        };
+        t.checked()
+    }
+}
+
+impl fmt::Display for Temporal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Temporal::Date(d) => {
+                let (y, m, day) = civil_from_days(*d);
+                write!(f, "{y:04}-{m:02}-{day:02}")
+            }
+            Temporal::DateTime { millis, offset } => {
+                // 构造时已经限制了范围，这里不会饱和
+                let local = millis.saturating_add(*offset as i64 * 1000);
+                let (y, m, d) = civil_from_days(local.div_euclid(DAY_MILLIS));
+                let ms = local.rem_euclid(DAY_MILLIS);
+                let (h, min, s) = (ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60);
+                write!(f, "{y:04}-{m:02}-{d:02}T{h:02}:{min:02}:{s:02}")?;
+                if ms % 1000 != 0 {
+                    write!(f, ".{:03}", ms % 1000)?;
+                }
+                match offset {
+                    0 => write!(f, "Z"),
+                    o => {
+                        let sign = if *o < 0 { '-' } else { '+' };
+                        write!(f, "{sign}{:02}:{:02}", o.abs() / 3600, o.abs() / 60 % 60)
+                    }
+                }
+            }
+            Temporal::Duration(ms) => {
+                if *ms == 0 {
+                    return write!(f, "0s");
+                }
+                if *ms < 0 {
+                    write!(f, "-")?;
+                }
+                let mut rest = ms.unsigned_abs();
+                for (unit, n) in [
+                    ("d", DAY_MILLIS as u64),
+                    ("h", 3_600_000),
+                    ("m", 60_000),
+                    ("s", 1000),
+                    ("ms", 1),
+                ] {
+                    if rest >= n {
+                        write!(f, "{}{unit}", rest / n)?;
+                        rest %= n;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 时区：UTC、Z、+08:00、-0530 等固定偏移，或者 Asia/Shanghai、Europe/Berlin 等IANA时区名
+/// 时区名按时区数据库计算每个时刻的偏移，包括夏令时，需要开启std-lib
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    // 偏移秒数
+    Fixed(i32),
+    #[cfg(feature = "std-lib")]
+    Named(chrono_tz::Tz),
+}
+
+impl Zone {
+    pub const UTC: Zone = Zone::Fixed(0);
+
+    pub fn parse(tz: &str) -> anyhow::Result<Self> {
+        match tz {
+            "UTC" | "Z" | "GMT" => return Zone::UTC.ok(),
+            _ => {}
+        }
+        if let Some(o) = parse_offset(tz) {
+            return Zone::Fixed(o).ok();
+        }
+        #[cfg(feature = "std-lib")]
+        if let Ok(tz) = tz.parse::<chrono_tz::Tz>() {
+            return Zone::Named(tz).ok();
+        }
+        anyhow!("time zone[{tz}] not supported, use an IANA name like Europe/Berlin or an offset like +02:00").err()
+    }
+    /// UTC毫秒时间戳在这个时区的偏移秒数
+    #[cfg_attr(not(feature = "std-lib"), allow(unused_variables))]
+    pub fn offset_at(&self, millis: i64) -> i32 {
+        match self {
+            Zone::Fixed(o) => *o,
+            #[cfg(feature = "std-lib")]
+            Zone::Named(tz) => {
+                use chrono::{Offset, TimeZone};
+                match chrono::DateTime::from_timestamp_millis(millis) {
+                    Some(t) => tz
+                        .offset_from_utc_datetime(&t.naive_utc())
+                        .fix()
+                        .local_minus_utc(),
+                    None => 0,
+                }
+            }
+        }
+    }
+    /// 本地时间的偏移秒数，local是把本地时间当作UTC得到的毫秒数
+    /// 夏令时结束时重复的时间取较早的一个，开始时跳过的时间按切换前的偏移
+    #[cfg_attr(not(feature = "std-lib"), allow(unused_variables))]
+    pub fn local_offset(&self, local: i64) -> i32 {
+        match self {
+            Zone::Fixed(o) => *o,
+            #[cfg(feature = "std-lib")]
+            Zone::Named(tz) => {
+                use chrono::{LocalResult, Offset, TimeZone};
+                let naive = match chrono::DateTime::from_timestamp_millis(local) {
+                    Some(t) => t.naive_utc(),
+                    None => return 0,
+                };
+                match tz.offset_from_local_datetime(&naive) {
+                    LocalResult::Single(o) | LocalResult::Ambiguous(o, _) => {
+                        o.fix().local_minus_utc()
+                    }
+                    // 跳过的时间前一天一定是切换前的偏移
+                    LocalResult::None => self.offset_at(local.saturating_sub(DAY_MILLIS)),
+                }
+            }
+        }
+    }
+}
+
+// ±HH:MM 或者 ±HHMM
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits = rest.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m): (i32, i32) = (digits[..2].parse().ok()?, digits[2..].parse().ok()?);
+    if h > 23 || m > 59 {
+        return None;
+    }
+    Some(sign * (h * 3600 + m * 60))
+}
+
+fn number<T: std::str::FromStr>(s: &str) -> Option<T> {
+    if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+// YYYY-MM-DD
+fn parse_date(s: &str) -> Option<i64> {
+    let mut parts = s.splitn(3, '-');
+    let y: i64 = number(parts.next()?)?;
+    let m: u32 = number(parts.next()?)?;
+    let d: u32 = number(parts.next()?)?;
+    if !(1..=12).contains(&m) || d == 0 || d > days_in_month(y, m) {
+        return None;
+    }
+    Some(days_from_civil(y, m, d))
+}
+
+// HH:MM[:SS[.fff]][Z|±HH:MM]
+fn parse_time(days: i64, s: &str, zone: &Zone) -> Option<Temporal> {
+    let (time, offset) = match s.find(['Z', 'z', '+', '-']) {
+        Some(i) if s[i..].eq_ignore_ascii_case("z") => (&s[..i], Some(0)),
+        Some(i) => (&s[..i], Some(parse_offset(&s[i..])?)),
+        None => (s, None),
+    };
+    let (time, frac) = match time.split_once('.') {
+        Some((t, f)) if (1..=9).contains(&f.len()) && f.bytes().all(|c| c.is_ascii_digit()) => {
+            (t, f)
+        }
+        Some(_) => return None,
+        None => (time, ""),
+    };
+    let mut parts = time.split(':');
+    let h: i64 = number(parts.next()?)?;
+    let m: i64 = number(parts.next()?)?;
+    let sec: i64 = match parts.next() {
+        Some(p) => number(p)?,
+        None => 0,
+    };
+    if parts.next().is_some() || h > 23 || m > 59 || sec > 59 {
+        return None;
+    }
+    let ms: i64 = match frac {
+        "" => 0,
+        f => number::<i64>(&format!("{f:0<3}")[..3])?,
+    };
+    let local = days * DAY_MILLIS + ((h * 60 + m) * 60 + sec) * 1000 + ms;
+    let offset = offset.unwrap_or_else(|| zone.local_offset(local));
+    Some(Temporal::DateTime {
+        millis: local - offset as i64 * 1000,
+        offset,
+    })
+}
+
+// 1w2d3h4m5s6ms
+fn parse_duration(s: &str) -> Option<i64> {
+    let (sign, mut rest) = match s.strip_prefix('-') {
+        Some(r) => (-1, r),
+        None => (1, s),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total: i64 = 0;
+    while !rest.is_empty() {
+        let n = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: i64 = number(&rest[..n])?;
+        rest = &rest[n..];
+        let (unit, len) = match rest.as_bytes() {
+            [b'm', b's', ..] => (1, 2),
+            [b'w', ..] => (7 * DAY_MILLIS, 1),
+            [b'd', ..] => (DAY_MILLIS, 1),
+            [b'h', ..] => (3_600_000, 1),
+            [b'm', ..] => (60_000, 1),
+            [b's', ..] => (1000, 1),
+            _ => return None,
+        };
+        rest = &rest[len..];
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    Some(sign * total)
+}
+
+fn is_leap(y: i64) -> bool {
+    y % 4 == 0 && (y % 100 != 0 || y % 400 == 0)
+}
+
+fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        2 if is_leap(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// 公历日期转为1970-01-01以来的天数
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = m as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+/// ISO星期，1是星期一，7是星期日
+pub fn weekday(days: i64) -> i64 {
+    (days + 3).rem_euclid(7) + 1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{weekday, Clock, FixedClock, Temporal, Zone};
+    use std::cmp::Ordering;
+
+    //cargo test --color=always --lib time::test::test_temporal --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_temporal() {
+        let cases = [
+            ("2024-02-29", "2024-02-29"),
+            ("1969-12-31", "1969-12-31"),
+            ("2024-01-05T10:30:00+08:00", "2024-01-05T10:30:00+08:00"),
+            ("2024-01-05 02:30", "2024-01-05T02:30:00Z"),
+            ("2024-01-05T02:30:00.5z", "2024-01-05T02:30:00.500Z"),
+            ("90m", "1h30m"),
+            ("1w", "7d"),
+            ("-1500ms", "-1s500ms"),
+        ];
+        for (s, want) in cases {
+            assert_eq!(Temporal::parse(s).unwrap().to_string(), want, "{s}");
+        }
+        for s in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-01-05T25:00",
+            "5x",
+            "abc",
+            "",
+            "12",
+            "abcd-中文字",
+            "2024-01-05中",
+            "2024-01-05T10:00:00.éé",
+        ] {
+            assert_eq!(Temporal::parse(s), None, "{s}");
+        }
+
+        let ord = |a: &str, b: &str| Temporal::compare_str(a, b);
+        assert_eq!(
+            ord("2024-01-05T10:00:00+08:00", "2024-01-05T02:00:00Z"),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            ord("2024-01-05", "2024-01-04T23:00:00-02:00"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            ord("2024-01-05T00:00:00+08:00", "2024-01-05"),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(ord("1d", "23h"), Some(Ordering::Greater));
+        assert_eq!(ord("1d", "2024-01-05"), None);
+
+        let date = Temporal::parse("2024-02-28").unwrap();
+        assert_eq!(date.add(86_400_000).unwrap().to_string(), "2024-02-29");
+        assert_eq!(
+            date.add(3_600_000).unwrap().to_string(),
+            "2024-02-28T01:00:00Z"
+        );
+        let t = Temporal::parse("2024-01-06T20:00:00Z").unwrap();
+        let t = t.in_zone(&Zone::parse("+08:00").unwrap());
+        assert_eq!(t.to_string(), "2024-01-07T04:00:00+08:00");
+        assert_eq!(weekday(t.local_days().unwrap()), 7);
+        assert_eq!(Zone::parse("-05:30").unwrap(), Zone::Fixed(-19800));
+        assert!(Zone::parse("Mars/Olympus").is_err());
+
+        // 超出范围时报错，不会溢出
+        assert!(Temporal::datetime(i64::MAX, 0).is_err());
+        assert!(Temporal::datetime(253_402_300_799_999, 3600).is_ok());
+        let max = Temporal::parse("9999-12-31T23:59:59Z").unwrap();
+        assert!(max.add(1000).is_err());
+        assert!(date.add(i64::MAX).is_err());
+        assert!(date.add(i64::MIN / 2).is_err());
+
+        let clock = FixedClock::parse("2024-01-05").unwrap();
+        assert_eq!(clock.now_millis(), 1704412800000);
+        clock.set(0);
+        assert_eq!(clock.now_millis(), 0);
+    }
+
+    //cargo test --color=always --lib time::test::test_zone --no-fail-fast -- --exact unstable-options --nocapture
+    #[cfg(feature = "std-lib")]
+    #[test]
+    fn test_zone() {
+        let berlin = Zone::parse("Europe/Berlin").unwrap();
+        let at = |s: &str| Temporal::parse(s).unwrap().in_zone(&berlin).to_string();
+        assert_eq!(at("2024-01-15T12:00:00Z"), "2024-01-15T13:00:00+01:00");
+        assert_eq!(at("2024-07-15T12:00:00Z"), "2024-07-15T14:00:00+02:00");
+
+        // 没有偏移的本地时间按当时是否夏令时解析
+        let local = |s: &str| Temporal::parse_in(s, &berlin).unwrap().to_string();
+        assert_eq!(local("2024-07-15T14:00:00"), "2024-07-15T14:00:00+02:00");
+        assert_eq!(local("2024-01-15T14:00:00"), "2024-01-15T14:00:00+01:00");
+        // 2024-10-27 03:00 回拨到 02:00，重复的时间取较早的一个
+        assert_eq!(local("2024-10-27T02:30:00"), "2024-10-27T02:30:00+02:00");
+        // 2024-03-31 02:00 跳到 03:00，跳过的时间按切换前的偏移
+        assert_eq!(local("2024-03-31T02:30:00"), "2024-03-31T02:30:00+01:00");
+    }
+}
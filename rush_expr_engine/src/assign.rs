@@ -1,52 +1,395 @@
-use crate::Calc;
+use crate::rule_parse::{parse_assignment, parse_calc, split_statements, tokenize};
+use crate::Precedence;
+use crate::{
+    get_path, remove_path, set_path, ArithmeticPolicy, Calc, NotFoundFieldError, NullMode,
+    NumberMode, Opt, PathSegment,
+};
 use anyhow::anyhow;
-use rush_core::{Exec, FunctionSet};
+use rush_core::{merge_value, Exec, FunctionSet, RuleFailedError};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
-use wd_tools::PFErr;
+use wd_tools::{PFErr, PFOk};
 
+/// 赋值表达式中读取已有输出的根字段：$out.total
+pub const OUTPUT_ROOT: &str = "$out";
+
+/// 赋值表达式引用了不存在的字段时的处理策略
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum MissingPolicy {
+    /// 返回错误，中断整个flow
+    #[default]
+    Fail,
+    /// 跳过这个key，记录在执行报告中
+    Skip,
+    /// 写入null
+    Null,
+    /// 写入声明的默认值，没有声明时跳过
+    Default,
+    /// 放弃这条规则的全部赋值，其他规则继续执行
+    FailRule,
+}
+
+impl FromStr for MissingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policy = match s.to_lowercase().as_str() {
+            "fail" => MissingPolicy::Fail,
+            "skip" => MissingPolicy::Skip,
+            "null" => MissingPolicy::Null,
+            "default" => MissingPolicy::Default,
+            "fail_rule" => MissingPolicy::FailRule,
+            _ => {
+                return anyhow!(
+                    "unknown missing policy[{s}], want: fail, skip, null, default, fail_rule"
+                )
+                .err()
+            }
+        };
+        Ok(policy)
+    }
+}
+
+/// then中的赋值操作，目标不存在或者为null时：
+/// += -= 从0开始（值为字符串时从''开始），*= /= 从1开始，??= 写入，push 创建数组，delete 不做任何事
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum AssignOp {
+    #[default]
+    Set, // =
+    Add,      // +=，数字相加，有字符串时拼接，数组时追加另一个数组的全部元素
+    Sub,      // -=
+    Mul,      // *=
+    Div,      // /=
+    Coalesce, // ??= 目标不存在或者为null时才计算并写入
+    Push,     // key.push(expr) 追加到数组
+    Delete,   // delete key
+}
+
+impl AsRef<str> for AssignOp {
+    fn as_ref(&self) -> &str {
+        match self {
+            AssignOp::Set => "=",
+            AssignOp::Add => "+=",
+            AssignOp::Sub => "-=",
+            AssignOp::Mul => "*=",
+            AssignOp::Div => "/=",
+            AssignOp::Coalesce => "??=",
+            AssignOp::Push => "push",
+            AssignOp::Delete => "delete",
+        }
+    }
+}
+
+impl AssignOp {
+    /// 复合赋值 = 前面的运算符
+    pub fn from_symbol(s: &str) -> Option<Self> {
+        let op = match s {
+            "+" => AssignOp::Add,
+            "-" => AssignOp::Sub,
+            "*" => AssignOp::Mul,
+            "/" => AssignOp::Div,
+            "??" => AssignOp::Coalesce,
+            _ => return None,
+        };
+        Some(op)
+    }
+    // 用目标的当前值和表达式的值计算写入的值，溢出和除以0按算术策略处理
+    fn apply(
+        &self,
+        key: &str,
+        current: Option<Value>,
+        val: Value,
+        fs: &Arc<dyn FunctionSet>,
+        policy: ArithmeticPolicy,
+    ) -> anyhow::Result<Value> {
+        let opt = match self {
+            AssignOp::Set | AssignOp::Coalesce | AssignOp::Delete => return val.ok(),
+            AssignOp::Push => {
+                return match current {
+                    None | Some(Value::Null) => Value::Array(vec![val]).ok(),
+                    Some(Value::Array(mut list)) => {
+                        list.push(val);
+                        Value::Array(list).ok()
+                    }
+                    Some(v) => anyhow!("assign[{key}.push] target is not array:{v}").err(),
+                }
+            }
+            AssignOp::Add => Opt::ADD,
+            AssignOp::Sub => Opt::SUB,
+            AssignOp::Mul => Opt::MUL,
+            AssignOp::Div => Opt::DIV,
+        };
+        let current = match current {
+            Some(Value::Null) | None => match (self, &val) {
+                (AssignOp::Add, Value::String(_)) => Value::String(String::new()),
+                (AssignOp::Add | AssignOp::Sub, _) => Value::from(0),
+                _ => Value::from(1),
+            },
+            Some(v) => v,
+        };
+        let args = match (current, val) {
+            (Value::Array(mut a), Value::Array(b)) if opt == Opt::ADD => {
+                a.extend(b);
+                return Value::Array(a).ok();
+            }
+            (a, b) => match (Calc::constant(a.clone()), Calc::constant(b)) {
+                (Some(x), Some(y)) if !a.is_array() => vec![x, y],
+                _ => {
+                    return anyhow!(
+                        "assign[{key} {}] can not apply to target:{a}",
+                        self.as_ref()
+                    )
+                    .err()
+                }
+            },
+        };
+        Calc::Operator(opt, args)
+            .arithmetic_policy(policy)
+            .value(fs, &Value::Null)
+            .map_err(|e| anyhow!("assign[{key} {}] {e}", self.as_ref()))
+    }
+}
+
+/// 按书写顺序依次执行的赋值，后面的表达式可以通过$out读取之前写入的结果
 #[derive(Debug, Default)]
 pub struct Assign {
-    execs: HashMap<String, Calc>,
+    // key、解析好的路径、操作和表达式
+    execs: Vec<(String, Vec<PathSegment>, AssignOp, Calc)>,
+    salience: i32,
+    // 为true时结果追加到数组中，而不是覆盖
+    collect: bool,
+    missing: MissingPolicy,
+    // MissingPolicy::Default 使用的默认值
+    defaults: HashMap<String, Value>,
+    // 有表达式读取$out时才构造包含输出的作用域
+    read_output: bool,
+    // 复合赋值的算术策略
+    arithmetic: ArithmeticPolicy,
 }
 impl Assign {
     pub fn new() -> Self {
         Assign {
-            execs: HashMap::new(),
+            execs: Vec::new(),
+            salience: 0,
+            collect: false,
+            missing: MissingPolicy::Fail,
+            defaults: HashMap::new(),
+            read_output: false,
+            arithmetic: ArithmeticPolicy::Error,
         }
     }
-    pub fn add_exec<K: Into<String>, C: Into<Calc>>(mut self, key: K, expr: C) -> Self {
-        self.execs.insert(key.into(), expr.into());
+    pub fn set_salience(mut self, salience: i32) -> Self {
+        self.salience = salience;
         self
     }
-    #[allow(unused_assignments)]
-    fn insert_value(k: &str, input: Value, mut out: &mut Value) -> anyhow::Result<()> {
-        let ks: Vec<_> = k.split(".").collect();
-        let last = ks.len() - 1;
-
-        for (i, e) in ks.into_iter().enumerate() {
-            out = if let Value::Object(map) = out {
-                if i == last {
-                    map.insert(e.to_string(), input);
-                    return Ok(());
+    /// 多条规则写同一个字段时收集为数组，决策表的collect策略使用
+    pub fn set_collect(mut self, collect: bool) -> Self {
+        self.collect = collect;
+        self
+    }
+    pub fn set_missing_policy(mut self, missing: MissingPolicy) -> Self {
+        self.missing = missing;
+        self
+    }
+    /// 声明key的默认值，字段不存在且策略为Default时使用
+    pub fn add_default<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        self.defaults.insert(key.into(), value);
+        self
+    }
+    /// 追加一条赋值，同一个key可以多次赋值，按顺序执行
+    pub fn add_exec<K: Into<String>, C: Into<Calc>>(self, key: K, expr: C) -> anyhow::Result<Self> {
+        self.add_action(key, AssignOp::Set, expr)
+    }
+    /// 追加一条赋值操作，delete的表达式不会被计算，key不是合法的路径时返回错误
+    pub fn add_action<K: Into<String>, C: Into<Calc>>(
+        mut self,
+        key: K,
+        op: AssignOp,
+        expr: C,
+    ) -> anyhow::Result<Self> {
+        let key = key.into();
+        let segs = PathSegment::parse(&key).map_err(|e| anyhow!("assign key[{key}] {e}"))?;
+        let expr = expr.into();
+        self.read_output |= read_output(&expr);
+        self.execs.push((key, segs, op, expr));
+        Ok(self)
+    }
+    /// 按null模式转换全部赋值表达式
+    pub fn null_mode(mut self, mode: NullMode) -> Self {
+        self.execs = self
+            .execs
+            .into_iter()
+            .map(|(k, segs, op, c)| (k, segs, op, c.null_mode(mode)))
+            .collect();
+        self
+    }
+    /// 按数字模式转换全部赋值表达式
+    pub fn number_mode(mut self, mode: NumberMode) -> Self {
+        self.execs = self
+            .execs
+            .into_iter()
+            .map(|(k, segs, op, c)| (k, segs, op, c.number_mode(mode)))
+            .collect();
+        self
+    }
+    /// 按算术策略转换全部赋值表达式，+= 等复合赋值同样使用该策略
+    pub fn arithmetic_policy(mut self, policy: ArithmeticPolicy) -> Self {
+        self.arithmetic = policy;
+        self.execs = self
+            .execs
+            .into_iter()
+            .map(|(k, segs, op, c)| (k, segs, op, c.arithmetic_policy(policy)))
+            .collect();
+        self
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Calc)> {
+        self.execs.iter().map(|(k, _, _, c)| (k, c))
+    }
+    pub fn actions(&self) -> impl Iterator<Item = (&String, AssignOp, &Calc)> {
+        self.execs.iter().map(|(k, _, op, c)| (k, *op, c))
+    }
+    // 目标的当前值，不存在时为None
+    fn current(k: &str, segs: &[PathSegment], out: &Value) -> anyhow::Result<Option<Value>> {
+        match get_path(k, segs, out) {
+            Ok(v) => Some(v).ok(),
+            Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => None.ok(),
+            Err(e) => e.err(),
+        }
+    }
+    // 按顺序计算并写入，每次写入后$out同步更新
+    // base是合并策略下已经合并的输出，$out包含它，本规则没有写过的复合赋值目标直接在它上面更新
+    fn assign(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        output: &mut Value,
+        mut base: Option<&mut Value>,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut scope = match (self.read_output, base.as_deref()) {
+            (false, _) => None,
+            (true, Some(b)) => {
+                let mut view = b.clone();
+                merge_value(&mut view, output);
+                Some(output_scope(input, view))
+            }
+            (true, None) => Some(output_scope(input, output.clone())),
+        };
+        let mut skipped = vec![];
+        for (k, segs, op, c) in self.execs.iter() {
+            if let AssignOp::Delete = op {
+                if let Some(out) = scope.as_mut().and_then(|s| s.get_mut(OUTPUT_ROOT)) {
+                    remove_path(k, segs, out)?;
                 }
-                if map.get(e).is_none() {
-                    map.insert(e.to_string(), Value::Object(Map::new()));
+                if let Some(b) = base.as_deref_mut() {
+                    remove_path(k, segs, b)?;
                 }
-                if let Some(s) = map.get_mut(e) {
-                    s
-                } else {
-                    panic!("Out of the question to here");
+                remove_path(k, segs, output)?;
+                continue;
+            }
+            let target = match base.as_deref_mut() {
+                Some(b) if *op != AssignOp::Set && Self::current(k, segs, output)?.is_none() => b,
+                _ => &mut *output,
+            };
+            let current = match op {
+                AssignOp::Set => None,
+                _ => Self::current(k, segs, target)?,
+            };
+            if *op == AssignOp::Coalesce && !matches!(current, None | Some(Value::Null)) {
+                continue;
+            }
+            let res = match scope {
+                Some(ref s) => c.value(fs, s),
+                None => c.value(fs, input),
+            };
+            let val = match res {
+                Ok(val) => val,
+                Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => {
+                    match (self.missing, self.defaults.get(k)) {
+                        (MissingPolicy::Fail, _) => return e.err(),
+                        (MissingPolicy::FailRule, _) => {
+                            return Err(RuleFailedError(format!("assign[{k}] {e}")).into())
+                        }
+                        (MissingPolicy::Null, _) => Value::Null,
+                        (MissingPolicy::Default, Some(val)) => val.clone(),
+                        (MissingPolicy::Skip | MissingPolicy::Default, _) => {
+                            skipped.push((k.clone(), e.to_string()));
+                            continue;
+                        }
+                    }
                 }
-            } else {
-                return anyhow!("want insert at,but the path is not obj").err();
+                Err(e) => return e.err(),
+            };
+            let val = op.apply(k, current, val, fs, self.arithmetic)?;
+            // collect只用于普通赋值，复合赋值已经包含了原来的值
+            let collect = self.collect && *op == AssignOp::Set;
+            if let Some(out) = scope.as_mut().and_then(|s| s.get_mut(OUTPUT_ROOT)) {
+                set_path(k, segs, out, val.clone(), collect)?;
             }
+            set_path(k, segs, target, val, collect)?;
         }
-        return Ok(());
+        Ok(skipped)
     }
 }
+
+// 输入加上$out，同名字段以$out为准
+fn output_scope(input: &Value, output: Value) -> Value {
+    let mut map = match input {
+        Value::Object(map) => map.clone(),
+        _ => Map::new(),
+    };
+    map.insert(OUTPUT_ROOT.to_string(), output);
+    Value::Object(map)
+}
+
+// 表达式中是否有 $out 开头的字段
+fn read_output(calc: &Calc) -> bool {
+    match calc {
+        Calc::Field(f) => output_path(f).is_some(),
+        Calc::Array(list) | Calc::Function(_, list) | Calc::Operator(_, list) => {
+            list.iter().any(read_output)
+        }
+        Calc::Lambda(_, c) | Calc::NullSafe(c) | Calc::Decimal(c) | Calc::Checked(_, c) => {
+            read_output(c)
+        }
+        Calc::If(cond, a, b) => read_output(cond) || read_output(a) || read_output(b),
+        Calc::Case(branches, default) => {
+            read_output(default)
+                || branches
+                    .iter()
+                    .any(|(c, v)| read_output(c) || read_output(v))
+        }
+        _ => false,
+    }
+}
+
+/// $out 开头的字段返回输出中的路径，$out本身返回空字符串
+pub fn output_path(field: &str) -> Option<&str> {
+    let rest = field.strip_prefix(OUTPUT_ROOT)?;
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix('.').or_else(|| rest.strip_prefix("?."))
+}
+impl Assign {
+    /// 解析 key = expression; key += expression; key.push(expression); delete key; ...
+    /// 按指定的运算符优先级
+    pub fn parse(s: &str, prec: Precedence) -> anyhow::Result<Self> {
+        let mut assign = Assign::new();
+        let tokens = tokenize(s).map_err(|e| e.locate(s))?;
+        for stmt in split_statements(&tokens) {
+            let (key, op, span) = parse_assignment(s, stmt).map_err(|e| e.locate(s))?;
+            let calc = match span {
+                Some(span) => parse_calc(s, span, prec)?,
+                None => Calc::NULL,
+            };
+            assign = assign.add_action(key, op, calc)?;
+        }
+        Ok(assign)
+    }
+}
+
 impl Exec for Assign {
     fn execute(
         &self,
@@ -54,11 +397,44 @@ impl Exec for Assign {
         input: &Value,
         output: &mut Value,
     ) -> anyhow::Result<()> {
-        for (k, c) in self.execs.iter() {
-            let val = c.value(&fs, input)?;
-            Self::insert_value(k, val, output)?;
+        self.execute_skip(fs, input, output).map(|_| ())
+    }
+    // 放弃规则时不能留下部分输出，先写到副本上
+    fn execute_skip(
+        &self,
+        fs: Arc<dyn FunctionSet>,
+        input: &Value,
+        output: &mut Value,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        if self.missing == MissingPolicy::FailRule {
+            let mut staged = output.clone();
+            let skipped = self.assign(&fs, input, &mut staged, None)?;
+            *output = staged;
+            return Ok(skipped);
         }
-        Ok(())
+        self.assign(&fs, input, output, None)
+    }
+    fn execute_merge(
+        &self,
+        fs: Arc<dyn FunctionSet>,
+        input: &Value,
+        output: &mut Value,
+        own: &mut Value,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        if self.missing == MissingPolicy::FailRule {
+            let (mut base, mut staged) = (output.clone(), own.clone());
+            let skipped = self.assign(&fs, input, &mut staged, Some(&mut base))?;
+            (*output, *own) = (base, staged);
+            return Ok(skipped);
+        }
+        self.assign(&fs, input, own, Some(output))
+    }
+    // FailRule时先写到副本上，失败不会留下部分输出
+    fn keeps_output_on_failure(&self) -> bool {
+        true
+    }
+    fn salience(&self) -> i32 {
+        self.salience
     }
 }
 
@@ -66,31 +442,17 @@ impl FromStr for Assign {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.trim_start_matches(" \r\n\t");
-        let ss: Vec<_> = s.split(";").collect();
-        let mut assign = Assign::new();
-        for i in ss {
-            let expr = i.trim_matches(|x| " \r\n\t".contains(x));
-            if expr.is_empty() {
-                continue;
-            }
-            if let Some((k, e)) = expr.split_once("=") {
-                assign = assign.add_exec(k.trim_matches(|x| " \r\n\t".contains(x)), e);
-            } else {
-                return anyhow!(
-                    "parse[{}] failed, expr must format:[argument = expression]",
-                    i
-                )
-                .err();
-            }
-        }
-        Ok(assign)
+        Assign::parse(s, Precedence::Standard)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::Assign;
+    use crate::{ArithmeticPolicy, Assign, AssignOp, MissingPolicy};
+    use rush_core::{Exec, Function, FunctionSet, RuleFailedError};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
 
     #[test]
     fn test_assign_new() {
@@ -108,4 +470,152 @@ mod test {
             .expect("new Assign failed");
         println!("--->{:?}", a);
     }
+
+    //cargo test --color=always --lib assign::test::test_missing_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_missing_policy() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let input = json!({"name":"ann"});
+        let run = |missing: MissingPolicy| {
+            let assign = "out.name = name; out.age = age + 1"
+                .parse::<Assign>()
+                .unwrap()
+                .set_missing_policy(missing)
+                .add_default("out.age", json!(18));
+            let mut out = json!({});
+            let res = assign.execute_skip(fs.clone(), &input, &mut out);
+            (res, out)
+        };
+
+        // 按顺序执行，出错前的赋值已经写入，flow会被中断
+        let (res, out) = run(MissingPolicy::Fail);
+        assert!(res.is_err());
+        assert_eq!(out, json!({"out":{"name":"ann"}}));
+        let (res, out) = run(MissingPolicy::Skip);
+        assert_eq!(res.unwrap()[0].0, "out.age");
+        assert_eq!(out, json!({"out":{"name":"ann"}}));
+        let (res, out) = run(MissingPolicy::Null);
+        assert!(res.unwrap().is_empty());
+        assert_eq!(out, json!({"out":{"name":"ann","age":null}}));
+        let (res, out) = run(MissingPolicy::Default);
+        assert!(res.unwrap().is_empty());
+        assert_eq!(out, json!({"out":{"name":"ann","age":18}}));
+        let (res, out) = run(MissingPolicy::FailRule);
+        assert!(res.unwrap_err().downcast_ref::<RuleFailedError>().is_some());
+        assert_eq!(out, Value::Object(Default::default()));
+        assert_eq!(
+            "fail_rule".parse::<MissingPolicy>().unwrap(),
+            MissingPolicy::FailRule
+        );
+        assert!("ignore".parse::<MissingPolicy>().is_err());
+    }
+
+    //cargo test --color=always --lib assign::test::test_assign_sequential --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_assign_sequential() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let assign = "total = a + b; tag = $out.total > 100 ? 'big' : 'small'; total = $out.total * 2; all = $out"
+            .parse::<Assign>()
+            .unwrap();
+        let keys: Vec<_> = assign.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["total", "tag", "total", "all"]);
+
+        let mut out = json!({"prev": 1});
+        assign
+            .execute(fs.clone(), &json!({"a": 60, "b": 50}), &mut out)
+            .unwrap();
+        assert_eq!(
+            out,
+            json!({"prev":1,"total":220,"tag":"big","all":{"prev":1,"total":220,"tag":"big"}})
+        );
+
+        assert!("$out.total = 1".parse::<Assign>().is_err());
+    }
+
+    //cargo test --color=always --lib assign::test::test_assign_mutation --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_assign_mutation() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let assign = r#"
+        score += 10; score *= 2; score -= a; msg += 'hi'; msg += '!';
+        level ??= 1; name ??= 'x';
+        tags.push(a); tags.push('b'); list += [1, 2];
+        delete tmp; delete items[0]; delete missing.key
+        "#
+        .parse::<Assign>()
+        .unwrap();
+        let ops: Vec<_> = assign.actions().map(|(_, op, _)| op).collect();
+        assert_eq!(ops[..3], [AssignOp::Add, AssignOp::Mul, AssignOp::Sub]);
+        assert_eq!(ops[5], AssignOp::Coalesce);
+        assert_eq!(ops[7], AssignOp::Push);
+        assert_eq!(ops[10], AssignOp::Delete);
+
+        let mut out = json!({"name":"ann","list":[0],"tmp":1,"items":[1,2]});
+        assign
+            .execute(fs.clone(), &json!({"a": 5}), &mut out)
+            .unwrap();
+        assert_eq!(
+            out,
+            json!({"score":15,"msg":"hi!","level":1,"name":"ann","tags":[5,"b"],"list":[0,1,2],"items":[2]})
+        );
+
+        let run = |s: &str, mut out: Value| {
+            let assign = s.parse::<Assign>()?;
+            assign
+                .execute(fs.clone(), &json!({}), &mut out)
+                .map(|_| out)
+        };
+        assert_eq!(run("n /= 2", json!({"n":9})).unwrap(), json!({"n":4}));
+        assert_eq!(
+            run("n.push(1)", json!({"n":null})).unwrap(),
+            json!({"n":[1]})
+        );
+        assert!(run("n.push(1)", json!({"n":1})).is_err());
+        assert!(run("n -= 1", json!({"n":"a"})).is_err());
+        assert!(run("n += 1", json!({"n":{}})).is_err());
+        assert!(run("n[*].a += 1", json!({})).is_err());
+        assert!(run("+= 1", json!({})).is_err());
+        assert!(run("n.push()", json!({})).is_err());
+        // 计数和 $out 一起使用
+        assert_eq!(
+            run("n += 1; m = $out.n * 10", json!({"n":1})).unwrap(),
+            json!({"n":2,"m":20})
+        );
+        // 路径在构建时解析，不合法时不用等到执行
+        assert!("items[x] = 1".parse::<Assign>().is_err());
+        assert!(Assign::new().add_exec("a[1", "1").is_err());
+        // delete 作为普通字段名
+        assert_eq!(run("delete = 1", json!({})).unwrap(), json!({"delete":1}));
+    }
+
+    //cargo test --color=always --lib assign::test::test_assign_arithmetic_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_assign_arithmetic_policy() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let run = |s: &str, policy: ArithmeticPolicy, mut out: Value| {
+            let assign = s.parse::<Assign>()?.arithmetic_policy(policy);
+            assign
+                .execute(fs.clone(), &json!({"y":0}), &mut out)
+                .map(|_| out)
+        };
+        let max = json!({"n": i64::MAX});
+        assert!(run("n += 10", ArithmeticPolicy::Error, max.clone()).is_err());
+        assert_eq!(
+            run("n += 10", ArithmeticPolicy::Saturate, max.clone()).unwrap(),
+            json!({"n": i64::MAX})
+        );
+        assert_eq!(
+            run("n += 10", ArithmeticPolicy::Null, max.clone()).unwrap(),
+            json!({"n": null})
+        );
+        assert_eq!(
+            run("n /= y", ArithmeticPolicy::Null, json!({"n": 1})).unwrap(),
+            json!({"n": null})
+        );
+        assert!(run("n /= y", ArithmeticPolicy::Saturate, json!({"n": 1})).is_err());
+        assert_eq!(
+            run("n += 1", ArithmeticPolicy::Saturate, json!({"n": 1})).unwrap(),
+            json!({"n": 2})
+        );
+    }
 }
@@ -1,15 +1,21 @@
-use crate::{CalcBuilder, NotFoundFieldError};
+use crate::{
+    checked_f64, checked_i64, checked_neg, checked_shift, concat, contains, string_arg,
+    ArithmeticPolicy, CalcBuilder, FieldPath, HigherOrder, NotFoundFieldError, NullPredicate,
+    Pattern, RoundingMode,
+};
 use anyhow::anyhow;
-use rush_core::FunctionSet;
+use rush_core::{ConditionTrace, ExprTrace, FunctionSet, Temporal};
 use serde_json::{Number, Value};
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::str::FromStr;
 use std::sync::Arc;
 use wd_tools::{PFErr, PFOk};
 
+// 运算符的优先级由Precedence::level决定，与声明顺序无关
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Opt {
-    //最低
+    // 算术和位运算
     ADD, // +
     SUB, // -
     MUL, // *
@@ -22,16 +28,21 @@ pub enum Opt {
     REV, // ~
     SHL, // <<
     SHR, // >>
-    //次高
-    GT, // >
-    GE, // >=
-    LT, // <
-    LE, // <=
-    EQ, // ==
-    NQ, // !=
-    // 优先级最高
+    // 比较
+    GT,    // >
+    GE,    // >=
+    LT,    // <
+    LE,    // <=
+    EQ,    // ==
+    NQ,    // !=
+    IN,    // in
+    LIKE,  // like
+    MATCH, // =~
+    SW,    // starts_with
+    // 逻辑
     AT, // &&
     OT, // ||
+    NC, // ?? 左边不存在或者为null时取右边
 }
 
 impl AsRef<str> for Opt {
@@ -55,8 +66,13 @@ impl AsRef<str> for Opt {
             Opt::LE => "<=",
             Opt::EQ => "==",
             Opt::NQ => "!=",
+            Opt::IN => "in",
+            Opt::LIKE => "like",
+            Opt::MATCH => "=~",
+            Opt::SW => "starts_with",
             Opt::AT => "&&",
             Opt::OT => "||",
+            Opt::NC => "??",
         }
     }
 }
@@ -64,7 +80,7 @@ impl AsRef<str> for Opt {
 #[derive(Debug, PartialEq, Clone)]
 pub enum Calc {
     NULL,
-    Field(String),
+    Field(FieldPath),
     String(String),
     Number(i64),
     Float(f64),
@@ -73,58 +89,46 @@ pub enum Calc {
     Function(String, Vec<Calc>),
 
     Operator(Opt, Vec<Calc>),
+    // like 和 =~ 右边的字符串常量，解析时编译
+    Pattern(Pattern),
+    // x -> expression，只能作为高阶函数的参数
+    Lambda(String, Box<Calc>),
+    // cond ? a : b 或者 if(cond, a, b)，只计算选中的分支
+    If(Box<Calc>, Box<Calc>, Box<Calc>),
+    // case when cond then value ... else value end，没有else时为null
+    Case(Vec<(Calc, Calc)>, Box<Calc>),
+    // 三值逻辑模式下的字段和运算符，字段不存在为null，运算遇到null为null
+    NullSafe(Box<Calc>),
+    // 十进制模式下的算术和比较运算符，参数都是数字时按十进制计算
+    Decimal(Box<Calc>),
+    // 按算术策略处理除以0和溢出的算术运算符
+    Checked(ArithmeticPolicy, Box<Calc>),
+    // @日期、时间和时长字面量，规范的文本，比较时按时间先后
+    Time(String),
 }
 
-macro_rules! operator_number_float {
-    ($operator:tt,$args:tt,$fs:tt,$input:tt,$($enum_ty:path=>$opt:tt),*) => {
-        $(
-        if let $enum_ty = $operator{
-            let nb1 = $args[0].number($fs, $input)?;
-            let nb2 = $args[1].number($fs,$input)?;
-            if let Some(i1) = nb1.as_i64() {
-                if let Some(i2) = nb2.as_i64(){
-                    return  Value::Number(Number::from(i1 $opt i2)).ok()
-                }else if let Some(i2) = nb2.as_f64(){
-                    if let Some(s) = Number::from_f64(i1 as f64 $opt i2){
-                        return Value::Number(s).ok()
-                    }
-                }
-            }else if let Some(f1) = nb1.as_f64(){
-                if let Some(f2) = nb2.as_i64(){
-                    if let Some(s) = Number::from_f64(f1 $opt f2 as f64){
-                        return Value::Number(s).ok()
-                    }
-                }else if let Some(f2) = nb2.as_f64(){
-                    if let Some(s) = Number::from_f64(f1 $opt f2){
-                        return Value::Number(s).ok()
-                    }
-                }
+// 两边都是整数时按整数计算，否则按浮点数计算，除以0和溢出时返回ArithmeticError
+macro_rules! number_float {
+    ($nb1:tt,$nb2:tt,$operator:tt,$args:tt) => {
+        {
+            let (nb1, nb2): (Number, Number) = ($nb1, $nb2);
+            if let (Some(i1), Some(i2)) = (nb1.as_i64(), nb2.as_i64()) {
+                return checked_i64($operator, i1, i2, $args);
+            }
+            if let (Some(f1), Some(f2)) = (nb1.as_f64(), nb2.as_f64()) {
+                return checked_f64($operator, f1, f2, $args);
             }
             return anyhow!("operator[{:?}] can not support args:[{:?}]",$operator,$args).err()
         }
-        )*
     };
 }
-macro_rules! operator_number_bool {
-    ($operator:tt,$args:tt,$fs:tt,$input:tt,$($enum_ty:path=>$opt:tt),*) => {
+macro_rules! operator_number_float {
+    ($operator:tt,$args:tt,$fs:tt,$input:tt,$($enum_ty:path),*) => {
         $(
         if let $enum_ty = $operator{
             let nb1 = $args[0].number($fs, $input)?;
             let nb2 = $args[1].number($fs,$input)?;
-            if let Some(i1) = nb1.as_i64() {
-                if let Some(i2) = nb2.as_i64(){
-                    return  Value::Bool(i1 $opt i2).ok()
-                }else if let Some(i2) = nb2.as_f64(){
-                    return Value::Bool((i1 as f64) $opt i2).ok()
-                }
-            }else if let Some(f1) = nb1.as_f64(){
-                if let Some(f2) = nb2.as_i64(){
-                    return Value::Bool(f1 $opt (f2 as f64)).ok()
-                }else if let Some(f2) = nb2.as_f64(){
-                    return Value::Bool(f1 $opt f2).ok()
-                }
-            }
-            return anyhow!("operator[{:?}] can not support args:[{:?}]",$operator,$args).err()
+            number_float!(nb1,nb2,$operator,$args);
         }
         )*
     };
@@ -147,34 +151,31 @@ macro_rules! operator_number_bit_option {
 }
 
 impl Calc {
-    pub fn field(&self, mut input: &Value) -> anyhow::Result<Value> {
+    pub fn field(&self, input: &Value) -> anyhow::Result<Value> {
         match self {
-            Calc::Field(field) => {
-                let ks: Vec<&str> = field.split('.').collect();
-                for i in ks {
-                    match input {
-                        Value::Object(obj) => {
-                            match obj.get(i) {
-                                None => {
-                                    // return <NotFoundFieldError as Into<anyhow::Error>>::into(NotFoundFieldError(i.to_string())).err()
-                                    return Err(NotFoundFieldError(i.to_string()).into());
-                                }
-                                Some(s) => {
-                                    input = s;
-                                }
-                            }
-                        }
-                        _ => return anyhow!("not found object at field[{field}]").err(),
-                    }
-                }
-                input.clone().ok()
-            }
+            Calc::Field(field) => field.get(input),
             _ => anyhow!("calc[{:?}],is not field", self).err(),
         }
     }
     pub fn function(&self, fs: &Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<Value> {
         return match self {
             Calc::Function(name, args) => {
+                if let Some(np) = NullPredicate::from_name(name) {
+                    if fs.get(name).is_none() {
+                        return np.call(args, fs, input);
+                    }
+                }
+                if let Some(rm) = RoundingMode::from_name(name) {
+                    if fs.get(name).is_none() {
+                        return rm.call(args, fs, input);
+                    }
+                }
+                if let Some(hf) = HigherOrder::from_name(name) {
+                    let lambda = args.iter().any(|x| matches!(x, Calc::Lambda(_, _)));
+                    if lambda || fs.get(name).is_none() {
+                        return hf.call(args, fs, input);
+                    }
+                }
                 let mut val_args = vec![];
                 for i in args {
                     val_args.push(i.value(fs, input)?);
@@ -223,6 +224,18 @@ impl Calc {
                     _ => return anyhow!("type[{val}] can not to number").err(),
                 }
             }
+            Calc::If(_, _, _)
+            | Calc::Case(_, _)
+            | Calc::NullSafe(_)
+            | Calc::Decimal(_)
+            | Calc::Checked(_, _) => {
+                let val = self.value(fs, input)?;
+                match val {
+                    Value::Null => Number::from(0i64),
+                    Value::Number(n) => n,
+                    _ => return anyhow!("type[{val}] can not to number").err(),
+                }
+            }
             _ => return anyhow!("type[{:?}] can not to number", self).err(),
         };
         return n.ok();
@@ -231,13 +244,14 @@ impl Calc {
         let b = match self {
             Calc::NULL => Value::Null,
             Calc::Field(_) => self.field(input)?,
-            Calc::String(s) => Value::String(s.clone()),
+            Calc::String(s) | Calc::Time(s) => Value::String(s.clone()),
             Calc::Number(n) => Value::Number(Number::from(*n)),
             Calc::Float(f) => match Number::from_f64(*f) {
                 None => return anyhow!("need f64, found a NAN").err(),
                 Some(n) => Value::Number(n),
             },
             Calc::Bool(b) => Value::Bool(*b),
+            Calc::Pattern(p) => Value::String(p.source().to_string()),
             Calc::Array(a) => {
                 let mut array = vec![];
                 for i in a {
@@ -247,6 +261,27 @@ impl Calc {
             }
             Calc::Function(_, _) => self.function(fs, input)?,
             Calc::Operator(opt, args) => Self::operator(opt, args, fs, input)?,
+            Calc::Lambda(_, _) => {
+                return anyhow!("lambda[{}] must be a function argument", self.to_string()).err()
+            }
+            Calc::If(cond, a, b) => {
+                if cond.bool(fs, input)? {
+                    a.value(fs, input)?
+                } else {
+                    b.value(fs, input)?
+                }
+            }
+            Calc::Case(branches, default) => {
+                for (cond, val) in branches {
+                    if cond.bool(fs, input)? {
+                        return val.value(fs, input);
+                    }
+                }
+                default.value(fs, input)?
+            }
+            Calc::NullSafe(calc) => calc.null_safe_value(fs, input)?,
+            Calc::Decimal(calc) => calc.decimal_value(fs, input)?,
+            Calc::Checked(policy, calc) => calc.checked_value(*policy, fs, input)?,
         };
         Ok(b)
     }
@@ -263,17 +298,7 @@ impl Calc {
             match opt {
                 Opt::SUB => {
                     let n = args[0].number(fs, input)?;
-                    if let Some(i) = n.as_i64() {
-                        return Value::Number(Number::from(-i)).ok();
-                    } else if let Some(i) = n.as_u64() {
-                        return Value::Number(Number::from(-(i as i64))).ok();
-                    }
-                    if let Some(i) = n.as_f64() {
-                        if let Some(s) = Number::from_f64(-i) {
-                            return Value::Number(s).ok();
-                        }
-                    }
-                    return anyhow!("operator '-' parse number[{n}] failed").err();
+                    return checked_neg(&n, args);
                 }
                 Opt::NOT => {
                     let b = args[0].bool(fs, input)?;
@@ -302,30 +327,84 @@ impl Calc {
             _ => {}
         }
 
+        // 有一边是字符串时拼接，另一边只能是字符串或者null
+        if let Opt::ADD = opt {
+            let v1 = args[0].value(fs, input)?;
+            let v2 = args[1].value(fs, input)?;
+            if v1.is_string() || v2.is_string() {
+                return concat(&v1, &v2);
+            }
+            let nb1 = Self::value_number(v1)?;
+            let nb2 = Self::value_number(v2)?;
+            number_float!(nb1, nb2, opt, args);
+        }
+
         operator_number_float!(opt,args,fs,input,
-            Opt::ADD=>+,
-            Opt::SUB=>-,
-            Opt::MUL=>*,
-            Opt::DIV=>/,
-            Opt::REM=>%);
+            Opt::SUB,
+            Opt::MUL,
+            Opt::DIV,
+            Opt::REM);
 
         operator_number_bit_option!(opt,args,fs,input,
             Opt::AND=> &,
             Opt::OR=> |,
-            Opt::XOR=> ^,
-            Opt::SHL=> <<,
-            Opt::SHR=> >>);
+            Opt::XOR=> ^);
+
+        if let Opt::SHL | Opt::SHR = opt {
+            let nb1 = args[0].number(fs, input)?;
+            let nb2 = args[1].number(fs, input)?;
+            if let (Some(i1), Some(i2)) = (nb1.as_i64(), nb2.as_i64()) {
+                return checked_shift(opt, i1, i2, args);
+            }
+            return anyhow!("operator[{:?}] can not support args:[{:?}]", opt, args).err();
+        }
 
-        operator_number_bool!(opt,args,fs,input,
-            Opt::GT=> >,
-            Opt::GE=> >=,
-            Opt::LT=> <,
-            Opt::LE=> <=);
+        // 日期、时间和时长按时间先后比较，其它值按数字比较
+        if let Opt::GT | Opt::GE | Opt::LT | Opt::LE = opt {
+            let (v1, v2) = (args[0].value(fs, input)?, args[1].value(fs, input)?);
+            if let Some(ord) = temporal_order(args, &v1, &v2) {
+                let b = match opt {
+                    Opt::GT => ord.is_gt(),
+                    Opt::GE => ord.is_ge(),
+                    Opt::LT => ord.is_lt(),
+                    _ => ord.is_le(),
+                };
+                return Value::Bool(b).ok();
+            }
+            let nb1 = Self::value_number(v1)?;
+            let nb2 = Self::value_number(v2)?;
+            let ord = match (nb1.as_i64(), nb2.as_i64()) {
+                (Some(i1), Some(i2)) => Some(i1.cmp(&i2)),
+                _ => match (nb1.as_f64(), nb2.as_f64()) {
+                    (Some(f1), Some(f2)) => f1.partial_cmp(&f2),
+                    _ => None,
+                },
+            };
+            let b = match (opt, ord) {
+                (_, None) => false,
+                (Opt::GT, Some(ord)) => ord.is_gt(),
+                (Opt::GE, Some(ord)) => ord.is_ge(),
+                (Opt::LT, Some(ord)) => ord.is_lt(),
+                (_, Some(ord)) => ord.is_le(),
+            };
+            return Value::Bool(b).ok();
+        }
 
+        // && 和 || 短路，右边只在需要时计算
         if let Opt::AT = opt {
-            let b1 = args[0].bool(fs, input)?;
-            let b2 = args[1].bool(fs, input)?;
-            return Value::Bool(b1 && b2).ok();
+            if !args[0].bool(fs, input)? {
+                return Value::Bool(false).ok();
+            }
+            return Value::Bool(args[1].bool(fs, input)?).ok();
+        }
+        // 左边不存在或者为null时才计算右边
+        if let Opt::NC = opt {
+            return match args[0].value(fs, input) {
+                Ok(Value::Null) => args[1].value(fs, input),
+                Err(e) if e.downcast_ref::<NotFoundFieldError>().is_none() => e.err(),
+                Ok(v) => v.ok(),
+                Err(_) => args[1].value(fs, input),
+            };
         }
         if let Opt::OT = opt {
             if args[0].bool(fs, input)? {
@@ -338,16 +417,62 @@ impl Calc {
         }
 
         let v1 = args[0].value(fs, input)?;
+        if let Opt::LIKE | Opt::MATCH = opt {
+            return Self::pattern_match(opt, &v1, &args[1], fs, input);
+        }
         let v2 = args[1].value(fs, input)?;
-        if let Opt::EQ = opt {
-            return Value::Bool(v1 == v2).ok();
-        } else if let Opt::NQ = opt {
-            return Value::Bool(v1 != v2).ok();
+        if let Opt::IN = opt {
+            return Value::Bool(contains(&v1, &v2)?).ok();
+        }
+        if let Opt::SW = opt {
+            let b = match (
+                string_arg("starts_with", &v1)?,
+                string_arg("starts_with", &v2)?,
+            ) {
+                (Some(s), Some(prefix)) => s.starts_with(prefix),
+                _ => false,
+            };
+            return Value::Bool(b).ok();
+        }
+        if let Opt::EQ | Opt::NQ = opt {
+            let eq = v1 == v2 || temporal_order(args, &v1, &v2) == Some(Ordering::Equal);
+            return Value::Bool(eq == (*opt == Opt::EQ)).ok();
         }
 
         return anyhow!("unknown operator[{:?}]", opt).err();
     }
 
+    // 右边是常量时使用解析时编译好的模式
+    fn pattern_match(
+        opt: &Opt,
+        v1: &Value,
+        pattern: &Calc,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        let s = match string_arg(opt.as_ref(), v1)? {
+            Some(s) => s,
+            None => return Value::Bool(false).ok(),
+        };
+        if let Calc::Pattern(p) = pattern {
+            return Value::Bool(p.is_match(s)).ok();
+        }
+        let v2 = pattern.value(fs, input)?;
+        let p = match (opt, string_arg(opt.as_ref(), &v2)?) {
+            (_, None) => return Value::Bool(false).ok(),
+            (Opt::LIKE, Some(p)) => Pattern::like(p)?,
+            (_, Some(p)) => Pattern::regex(p)?,
+        };
+        Value::Bool(p.is_match(s)).ok()
+    }
+    fn value_number(val: Value) -> anyhow::Result<Number> {
+        match val {
+            Value::Null => Number::from(0i64).ok(),
+            Value::Number(n) => n.ok(),
+            _ => anyhow!("type[{val}] can not to number").err(),
+        }
+    }
+
     pub fn bool(&self, fs: &Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<bool> {
         let b = match self {
             Calc::NULL => false,
@@ -359,7 +484,8 @@ impl Calc {
                     _ => true,
                 }
             }
-            Calc::String(s) => !s.is_empty(),
+            Calc::String(s) | Calc::Time(s) => !s.is_empty(),
+            Calc::Pattern(p) => !p.source().is_empty(),
             Calc::Number(n) => *n != 0,
             Calc::Float(_) => true,
             Calc::Bool(b) => *b,
@@ -380,17 +506,186 @@ impl Calc {
                     _ => true,
                 }
             }
+            Calc::Lambda(_, _) => return self.value(fs, input).map(|_| false),
+            Calc::If(_, _, _)
+            | Calc::Case(_, _)
+            | Calc::NullSafe(_)
+            | Calc::Decimal(_)
+            | Calc::Checked(_, _) => {
+                let val = self.value(fs, input)?;
+                match val {
+                    Value::Null => false,
+                    Value::Bool(b) => b,
+                    _ => true,
+                }
+            }
         };
         Ok(b)
     }
 }
 
+impl Calc {
+    // 后序遍历记录每个非字面量子表达式的值，短路和没有选中的分支不计算
+    pub fn explain_values(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        trace: &mut ConditionTrace,
+    ) {
+        self.explain_node(fs, input, trace);
+    }
+    // 每个节点只计算一次：子表达式的值替换为常量后再计算当前节点，字面量返回None
+    fn explain_node(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        trace: &mut ConditionTrace,
+    ) -> Option<Value> {
+        let node = self.explain_children(fs, input, trace)?;
+        // 三值逻辑下字段不存在不报错，但仍然记录
+        if let Calc::NullSafe(field) = self {
+            if let Calc::Field(f) = field.as_ref() {
+                if field.field(input).is_err()
+                    && !trace.missing_fields.iter().any(|x| x == f.path())
+                {
+                    trace.missing_fields.push(f.to_string());
+                }
+            }
+        }
+        let expr = self.to_string();
+        match node.value(fs, input) {
+            Ok(value) => {
+                trace.values.push(ExprTrace {
+                    expr,
+                    value: value.clone(),
+                    error: None,
+                });
+                Some(value)
+            }
+            Err(e) => {
+                if let Calc::Field(field) = self {
+                    if e.downcast_ref::<NotFoundFieldError>().is_some()
+                        && !trace.missing_fields.iter().any(|x| x == field.path())
+                    {
+                        trace.missing_fields.push(field.to_string());
+                    }
+                }
+                trace.values.push(ExprTrace {
+                    expr,
+                    value: Value::Null,
+                    error: Some(e.to_string()),
+                });
+                None
+            }
+        }
+    }
+    // 记录子表达式并替换为计算结果，null和出错时保留原表达式，null模式和is_missing需要区分它们
+    // cond为true时用在条件位置，只保留真假，避免 '' 和 0 作为常量时真假不同
+    fn explain_arg(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        trace: &mut ConditionTrace,
+        cond: bool,
+    ) -> Calc {
+        match self.explain_node(fs, input, trace) {
+            None | Some(Value::Null) => self.clone(),
+            Some(v) if cond => Calc::Bool(v != Value::Bool(false)),
+            Some(v) => self.evaluated(v),
+        }
+    }
+    fn explain_args(
+        list: &[Calc],
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        trace: &mut ConditionTrace,
+        cond: bool,
+    ) -> Vec<Calc> {
+        list.iter()
+            .map(|x| x.explain_arg(fs, input, trace, cond))
+            .collect()
+    }
+    // 返回子表达式替换为常量后的当前节点，字面量返回None
+    fn explain_children(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+        trace: &mut ConditionTrace,
+    ) -> Option<Calc> {
+        let node = match self {
+            Calc::NULL
+            | Calc::String(_)
+            | Calc::Time(_)
+            | Calc::Number(_)
+            | Calc::Float(_)
+            | Calc::Bool(_)
+            | Calc::Pattern(_)
+            | Calc::Lambda(_, _) => return None,
+            Calc::Field(_) => self.clone(),
+            Calc::NullSafe(calc) => {
+                Calc::NullSafe(Box::new(calc.explain_children(fs, input, trace)?))
+            }
+            Calc::Decimal(calc) => Calc::Decimal(Box::new(calc.explain_children(fs, input, trace)?)),
+            Calc::Checked(policy, calc) => {
+                Calc::Checked(*policy, Box::new(calc.explain_children(fs, input, trace)?))
+            }
+            Calc::Operator(opt @ (Opt::AT | Opt::OT), list) if list.len() == 2 => {
+                let left = list[0].explain_arg(fs, input, trace, true);
+                let right = match left.bool(fs, input).ok() == Some(matches!(opt, Opt::AT)) {
+                    true => list[1].explain_arg(fs, input, trace, true),
+                    false => list[1].clone(),
+                };
+                Calc::Operator(opt.clone(), vec![left, right])
+            }
+            Calc::Operator(Opt::NOT, list) => {
+                Calc::Operator(Opt::NOT, Self::explain_args(list, fs, input, trace, true))
+            }
+            // is_null、is_missing需要区分字段不存在，参数保留原表达式
+            Calc::Function(name, list) if NullPredicate::from_name(name).is_some() => {
+                for i in list {
+                    i.explain_node(fs, input, trace);
+                }
+                self.clone()
+            }
+            Calc::Array(list) => Calc::Array(Self::explain_args(list, fs, input, trace, false)),
+            Calc::Function(name, list) => {
+                Calc::Function(name.clone(), Self::explain_args(list, fs, input, trace, false))
+            }
+            Calc::Operator(opt, list) => {
+                Calc::Operator(opt.clone(), Self::explain_args(list, fs, input, trace, false))
+            }
+            // 分支是惰性的，只记录条件
+            Calc::If(cond, a, b) => Calc::If(
+                Box::new(cond.explain_arg(fs, input, trace, true)),
+                a.clone(),
+                b.clone(),
+            ),
+            Calc::Case(branches, default) => {
+                let mut list = vec![];
+                let mut hit = false;
+                for (cond, val) in branches {
+                    let cond = match hit {
+                        true => cond.clone(),
+                        false => cond.explain_arg(fs, input, trace, true),
+                    };
+                    hit = hit || matches!(cond.bool(fs, input), Ok(true));
+                    list.push((cond, val.clone()));
+                }
+                Calc::Case(list, default.clone())
+            }
+        };
+        Some(node)
+    }
+}
+
 impl ToString for Calc {
     fn to_string(&self) -> String {
         match self {
             Calc::NULL => "null".into(),
-            Calc::Field(s) => s.clone(),
+            Calc::Field(s) => s.to_string(),
             Calc::String(s) => format!("\"{}\"", s),
+            Calc::Time(s) => format!("@{}", s),
+            Calc::Pattern(p) => format!("\"{}\"", p.source()),
             Calc::Number(n) => n.to_string(),
             Calc::Float(f) => format!("{:.2}", f),
             Calc::Bool(b) => b.to_string(),
@@ -431,6 +726,25 @@ impl ToString for Calc {
                     panic!("Calc.to_string length[{}]", arg.len());
                 }
             }
+            Calc::Lambda(param, body) => format!("{} -> {}", param, body.to_string()),
+            Calc::NullSafe(calc) | Calc::Decimal(calc) | Calc::Checked(_, calc) => calc.to_string(),
+            Calc::If(cond, a, b) => format!(
+                "({} ? {} : {})",
+                cond.to_string(),
+                a.to_string(),
+                b.to_string()
+            ),
+            Calc::Case(branches, default) => {
+                let mut case: String = "case".into();
+                for (cond, val) in branches {
+                    case.push_str(&format!(
+                        " when {} then {}",
+                        cond.to_string(),
+                        val.to_string()
+                    ));
+                }
+                format!("{} else {} end", case, default.to_string())
+            }
         }
     }
 }
@@ -463,16 +777,109 @@ impl rush_core::CalcNode for Calc {
             }
         }
     }
+    fn identity(&self) -> Option<String> {
+        Some(format!("{:?}", self))
+    }
+    // a && b && c 拆分为a、b、c，各自的标识相同时在规则间共享
+    fn conjuncts(&self) -> Vec<Arc<dyn rush_core::CalcNode>> {
+        let mut list = vec![];
+        self.split_and(&mut list);
+        if list.len() < 2 {
+            return vec![];
+        }
+        list.into_iter()
+            .map(|x| Arc::new(x) as Arc<dyn rush_core::CalcNode>)
+            .collect()
+    }
+    fn explain(&self, fs: Arc<dyn FunctionSet>, input: &Value) -> ConditionTrace {
+        let mut trace = ConditionTrace {
+            expr: self.to_string(),
+            ..Default::default()
+        };
+        // 根节点的值已经算出时不再重复计算条件
+        let result = match self.explain_node(&fs, input, &mut trace) {
+            Some(Value::Null) => Ok(false),
+            Some(Value::Bool(b)) => Ok(b),
+            Some(_) => Ok(true),
+            None => self.when(fs, input),
+        };
+        match result {
+            Ok(b) => trace.result = Some(b),
+            Err(e) => trace.error = Some(e.to_string()),
+        }
+        trace
+    }
+}
+
+impl Calc {
+    // 三值逻辑下 && 包装在NullSafe中，条件成立要求每一项都为true，拆分后结果相同
+    fn split_and(&self, list: &mut Vec<Calc>) {
+        match self {
+            Calc::Operator(Opt::AT, args) if args.len() == 2 => {
+                args[0].split_and(list);
+                args[1].split_and(list);
+            }
+            Calc::NullSafe(calc) if matches!(calc.as_ref(), Calc::Operator(Opt::AT, _)) => {
+                calc.split_and(list)
+            }
+            _ => list.push(self.clone()),
+        }
+    }
+}
+
+// 返回日期、时间或者时长的标准库函数
+const TEMPORAL_FUNCTIONS: [&str; 6] = [
+    "now",
+    "today",
+    "parse_date",
+    "to_timezone",
+    "duration_between",
+    "add",
+];
+
+impl Calc {
+    // @字面量和时间函数的结果是时间类型，只有它们参与比较时才按时间先后比较
+    pub(crate) fn is_temporal(&self) -> bool {
+        match self {
+            Calc::Time(_) => true,
+            Calc::Function(name, _) => TEMPORAL_FUNCTIONS.contains(&name.as_str()),
+            Calc::NullSafe(calc) | Calc::Decimal(calc) | Calc::Checked(_, calc) => {
+                calc.is_temporal()
+            }
+            _ => false,
+        }
+    }
+    // 已经计算过的参数转为常量，保留时间类型
+    pub(crate) fn evaluated(&self, val: Value) -> Calc {
+        match (self, val) {
+            (Calc::Pattern(_), _) => self.clone(),
+            (_, Value::String(s)) if self.is_temporal() => Calc::Time(s),
+            (_, val) => Self::constant(val).unwrap_or_else(|| self.clone()),
+        }
+    }
+}
+
+// 至少一边是时间类型，并且两边都可以解析为日期、时间或者时长时返回顺序
+fn temporal_order(args: &[Calc], v1: &Value, v2: &Value) -> Option<Ordering> {
+    if !args.iter().any(Calc::is_temporal) {
+        return None;
+    }
+    match (v1, v2) {
+        (Value::String(a), Value::String(b)) => Temporal::compare_str(a, b),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::Calc;
     use crate::Opt;
-    use rush_core::{CalcNode, Function, FunctionSet};
+    use rush_core::{CalcNode, Function, FunctionImpl, FunctionSet};
     use serde::Serialize;
     use serde_json::Value;
+    use std::collections::HashMap;
     use std::fmt::Debug;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
     //cargo test --color=always --lib calc_impl::test::test_calc_show -- --exact unstable-options --nocapture
@@ -524,6 +931,126 @@ mod test {
         println!("result ---> {result}");
     }
 
+    //cargo test --color=always --lib calc::test::test_calc_condition --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_calc_condition() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(FunctionSetImpl {});
+        let input = serde_json::json!({"x": 7, "end": 1, "vip": true});
+        let value = |expr: &str| -> Value {
+            let calc: Calc = expr.parse().unwrap();
+            calc.value(&fs, &input).unwrap()
+        };
+        assert_eq!(value("x > 5 ? 'big' : 'small'"), "big");
+        assert_eq!(value("x > 10 ? 'big' : x > 5 ? 'mid' : 'small'"), "mid");
+        assert_eq!(value("vip && x > 8 ? 1 : 2"), 2);
+        assert_eq!(value("(x > 5 ? 10 : 0) + 1"), 11);
+        // 没有选中的分支不计算
+        assert_eq!(value("if(x > 5, x * 2, missing.field)"), 14);
+        assert_eq!(value("if(x < 5, missing.field, 0)"), 0);
+        let case = "case when x > 10 then 'high' when x > 5 then 'mid' else 'low' end";
+        assert_eq!(value(case), "mid");
+        assert_eq!(value("case when x > 10 then 1 end"), Value::Null);
+        assert_eq!(
+            value("case when x > 5 then case when vip then 'a' else 'b' end end"),
+            "a"
+        );
+        assert_eq!(value("case when vip then 1 else 2 end * 3 + 1"), 4);
+        // case外的end依然是字段
+        assert_eq!(value("end + 1"), 2);
+
+        let calc: Calc = case.parse().unwrap();
+        assert_eq!(
+            calc.to_string(),
+            r#"case when (x > 10) then "high" when (x > 5) then "mid" else "low" end"#
+        );
+        assert!("x > 1 ? 2".parse::<Calc>().is_err());
+        assert!("if(x, 1)".parse::<Calc>().is_err());
+        assert!("case when x then 1 else 2 when y then 3 end"
+            .parse::<Calc>()
+            .is_err());
+    }
+
+    //cargo test --color=always --lib calc::test::test_calc_string_operator --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_calc_string_operator() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(FunctionSetImpl {});
+        let input = serde_json::json!({"country":"中国","name":"VIP_tom","phone":"13800000000","in":1,"like":2});
+        let value = |expr: &str| -> Value {
+            let calc: Calc = expr.parse().unwrap();
+            calc.value(&fs, &input).unwrap()
+        };
+        assert_eq!(value("'a' + 'b' + phone"), "ab13800000000");
+        // 只有两边都是字符串时拼接，'5' + 1 报错
+        for expr in ["'5' + 1", "1 + 2 + 'c'"] {
+            let calc: Calc = expr.parse().unwrap();
+            assert!(calc.value(&fs, &input).is_err());
+        }
+        assert_eq!(value("country in ['中国','美国']"), true);
+        assert_eq!(value("'tom' in name && !('jack' in name)"), true);
+        assert_eq!(value("name like 'VIP\\_%'"), true);
+        assert_eq!(value("name like 'vip%'"), false);
+        assert_eq!(value("phone =~ '^1[3-9]\\d{9}$'"), true);
+        assert_eq!(value("name starts_with 'VIP' && name =~ 'tom$'"), true);
+        assert_eq!(value("null like '%'"), Value::Bool(false));
+        // 不在两个算子之间时是字段
+        assert_eq!(value("in + like"), 3);
+
+        // 常量模式在解析时编译，非法的正则是解析错误
+        let calc: Calc = "name like 'VIP%'".parse().unwrap();
+        assert!(matches!(calc, Calc::Operator(_, ref args) if matches!(args[1], Calc::Pattern(_))));
+        assert_eq!(calc.to_string(), r#"(name like "VIP%")"#);
+        assert!("name =~ '('".parse::<Calc>().is_err());
+        let calc: Calc = "name =~ country".parse().unwrap();
+        assert_eq!(calc.value(&fs, &input).unwrap(), false);
+    }
+
+    //cargo test --color=always --lib calc::test::test_calc_lazy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_calc_lazy() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let lookup = FunctionImpl::new(move |id: i64| -> anyhow::Result<bool> {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(id > 0)
+        });
+        let mut functions: HashMap<String, Arc<dyn Function>> = HashMap::new();
+        functions.insert("slow_lookup".into(), Arc::new(lookup));
+        let fs: Arc<dyn FunctionSet> = Arc::new(functions);
+        let input = serde_json::json!({"vip": false, "user": {"id": 1}});
+        let when = |expr: &str| -> bool {
+            let calc: Calc = expr.parse().unwrap();
+            calc.when(fs.clone(), &input).unwrap()
+        };
+
+        // 左边为false时右边的函数和缺少的字段都不会计算
+        assert!(!when("vip && slow_lookup(user.id)"));
+        assert!(!when("vip && missing.field > 1"));
+        assert!(when("!vip || slow_lookup(user.id)"));
+        assert!(when("vip ? slow_lookup(user.id) : true"));
+        assert!(when("if(vip, slow_lookup(user.id), true)"));
+        assert!(when(
+            "case when vip then slow_lookup(user.id) else true end"
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert!(when("!vip && slow_lookup(user.id)"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // explain同样不计算短路的部分
+        let calc: Calc = "vip && slow_lookup(user.id)".parse().unwrap();
+        let trace = calc.explain(fs.clone(), &input);
+        assert_eq!(trace.result, Some(false));
+        assert_eq!(trace.values.len(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // explain时每个节点只计算一次
+        let calc: Calc = "!vip && (slow_lookup(user.id) == true)".parse().unwrap();
+        let trace = calc.explain(fs.clone(), &input);
+        assert_eq!(trace.result, Some(true));
+        assert_eq!(trace.values.len(), 6);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
     #[test]
     fn test_calc_bit_operation() {
         let expr = "1 ^ 2";
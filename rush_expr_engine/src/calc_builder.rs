@@ -1,4 +1,4 @@
-use crate::{Calc, Element};
+use crate::{ArithmeticPolicy, Calc, NullMode, NumberMode, Precedence, Token};
 use std::collections::VecDeque;
 
 pub trait CalcBuilderEvent {
@@ -9,7 +9,7 @@ pub trait CalcBuilderEvent {
     fn remove_annotation_after(&self, _expr: &mut String) -> anyhow::Result<()> {
         Ok(())
     }
-    fn expression_split_check(&self, _deq: &mut VecDeque<Element>) -> anyhow::Result<()> {
+    fn expression_split_check(&self, _deq: &mut VecDeque<Token>) -> anyhow::Result<()> {
         Ok(())
     }
     fn calc_check(&self, _calc: &mut Calc) -> anyhow::Result<()> {
@@ -28,6 +28,18 @@ pub struct CalcBuilder {
     // 在快速解析中 例如："a > b &/*注释*/& d < c" 这样的注解会解析失败
     disable_fast_parse_annotation: bool,
 
+    //运算符优先级，默认与C语言一致
+    precedence: Precedence,
+
+    //字段不存在和null的处理方式
+    null_mode: NullMode,
+
+    //数字按浮点数还是十进制计算
+    number_mode: NumberMode,
+
+    //除以0和溢出的处理方式
+    arithmetic_policy: ArithmeticPolicy,
+
     //需要解析的表达式
     expr: String,
 }
@@ -44,6 +56,31 @@ impl CalcBuilder {
         self.disable_fast_parse_annotation = true;
         self
     }
+
+    /// 使用旧版本的运算符优先级：计算和位运算同级，&& || 同级，用于迁移
+    pub fn legacy_precedence(mut self) -> Self {
+        self.precedence = Precedence::Legacy;
+        self
+    }
+    pub fn precedence(mut self, precedence: Precedence) -> Self {
+        self.precedence = precedence;
+        self
+    }
+    /// 使用三值逻辑时字段不存在视为null，而不是报错
+    pub fn null_mode(mut self, null_mode: NullMode) -> Self {
+        self.null_mode = null_mode;
+        self
+    }
+    /// 使用十进制模式时算术和比较运算按十进制精确计算
+    pub fn number_mode(mut self, number_mode: NumberMode) -> Self {
+        self.number_mode = number_mode;
+        self
+    }
+    /// 除以0和溢出时返回null或者取边界值，而不是报错
+    pub fn arithmetic_policy(mut self, policy: ArithmeticPolicy) -> Self {
+        self.arithmetic_policy = policy;
+        self
+    }
 }
 
 impl CalcBuilder {
@@ -53,6 +90,10 @@ impl CalcBuilder {
     pub fn build_event<E: CalcBuilderEvent>(self, event: Option<E>) -> anyhow::Result<Calc> {
         let Self {
             disable_fast_parse_annotation,
+            precedence,
+            null_mode,
+            number_mode,
+            arithmetic_policy,
             mut expr,
         } = self;
 
@@ -70,14 +111,19 @@ impl CalcBuilder {
             }
         }
 
-        let mut deq = Calc::expression_split(expr)?;
+        // 解析错误按处理后的表达式计算行列号
+        let mut deq = Calc::expression_split(expr.clone()).map_err(|e| e.locate(&expr))?;
 
         if let Some(ref e) = event {
             //<<---------- 拆解检查
             e.expression_split_check(&mut deq)?;
         }
 
-        let mut calc = Calc::convert_one_group_calc(None, &mut deq)?;
+        let mut calc = Calc::convert_one_group_calc(None, &mut deq, precedence)
+            .map_err(|e| e.locate(&expr))?
+            .null_mode(null_mode)
+            .number_mode(number_mode)
+            .arithmetic_policy(arithmetic_policy);
 
         if let Some(ref e) = event {
             //<<---------- 算子检查
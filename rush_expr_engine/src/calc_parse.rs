@@ -1,5 +1,6 @@
-use crate::{Calc, Opt};
-use anyhow::anyhow;
+use rush_core::{scan_string, skip_comment};
+use crate::{Calc, FieldPath, Opt, ParseError, Pattern, Position, Span};
+use rush_core::Temporal;
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::str::FromStr;
@@ -16,6 +17,27 @@ pub enum Element {
     LeftBig,    // {
     RightBig,   // }
     Comma,      // ,
+    Arrow,      // ->
+    Question,   // ?
+    Colon,      // :
+    Case,       // case
+    When,       // when
+    Then,       // then
+    Else,       // else
+    End,        // end
+}
+
+/// 带位置的词法单元
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub ele: Element,
+    pub span: Span,
+}
+
+impl PartialEq<Element> for Token {
+    fn eq(&self, other: &Element) -> bool {
+        self.ele == *other
+    }
 }
 
 impl ToString for Element {
@@ -30,6 +52,14 @@ impl ToString for Element {
             Element::LeftBig => "{".into(),
             Element::RightBig => "}".into(),
             Element::Comma => ",".into(),
+            Element::Arrow => "->".into(),
+            Element::Question => "?".into(),
+            Element::Colon => ":".into(),
+            Element::Case => "case".into(),
+            Element::When => "when".into(),
+            Element::Then => "then".into(),
+            Element::Else => "else".into(),
+            Element::End => "end".into(),
         }
     }
 }
@@ -68,6 +98,7 @@ impl Opt {
             "<="=>Element::OPT(Opt::LE),
             "=="=>Element::OPT(Opt::EQ),
             "!="=>Element::OPT(Opt::NQ),
+            "=~"=>Element::OPT(Opt::MATCH),
             "<<"=>Element::OPT(Opt::SHL),
             ">>"=>Element::OPT(Opt::SHR),
 
@@ -88,27 +119,72 @@ impl Opt {
     }
 }
 
-impl PartialOrd for Opt {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        return match self {
-            Opt::GT | Opt::GE | Opt::LT | Opt::LE | Opt::EQ | Opt::NQ => match other {
-                Opt::GT | Opt::GE | Opt::LT | Opt::LE | Opt::EQ | Opt::NQ => Some(Ordering::Equal),
-                Opt::OT | Opt::AT => Some(Ordering::Less),
-                _ => Some(Ordering::Greater),
-            },
-            Opt::OT | Opt::AT => match other {
-                Opt::OT | Opt::AT => Some(Ordering::Equal),
-                Opt::GT | Opt::GE | Opt::LT | Opt::LE | Opt::EQ | Opt::NQ => {
-                    Some(Ordering::Greater)
-                }
-                _ => Some(Ordering::Greater),
+impl Opt {
+    // 用单词表示的运算符，只在两个算子之间时才是运算符
+    fn from_word(word: &str) -> Option<Opt> {
+        match word {
+            "in" => Some(Opt::IN),
+            "like" => Some(Opt::LIKE),
+            "starts_with" => Some(Opt::SW),
+            _ => None,
+        }
+    }
+}
+
+/// 运算符优先级，数字越大结合越松，同级左结合
+/// Standard：与C语言一致，前缀 > * / % > + - > << >> > < <= > >= > == != in like =~ starts_with > & > ^ > | > && > || > ??
+/// Legacy：旧版本的规则，计算和位运算同级，比较同级，&& || 同级
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Precedence {
+    #[default]
+    Standard,
+    Legacy,
+}
+
+impl Precedence {
+    pub fn level(&self, opt: &Opt, unary: bool) -> u8 {
+        if unary {
+            return 0;
+        }
+        match self {
+            Precedence::Standard => match opt {
+                Opt::MUL | Opt::DIV | Opt::REM => 1,
+                Opt::ADD | Opt::SUB => 2,
+                Opt::SHL | Opt::SHR => 3,
+                Opt::GT | Opt::GE | Opt::LT | Opt::LE => 4,
+                Opt::EQ | Opt::NQ | Opt::IN | Opt::LIKE | Opt::MATCH | Opt::SW => 5,
+                Opt::AND => 6,
+                Opt::XOR => 7,
+                Opt::OR => 8,
+                Opt::AT => 9,
+                Opt::OT => 10,
+                Opt::NC => 11,
+                Opt::NOT | Opt::REV => 0,
             },
-            _ => match other {
-                Opt::GT | Opt::GE | Opt::LT | Opt::LE | Opt::EQ | Opt::NQ => Some(Ordering::Less),
-                Opt::OT | Opt::AT => Some(Ordering::Less),
-                _ => Some(Ordering::Equal),
+            Precedence::Legacy => match opt {
+                Opt::GT
+                | Opt::GE
+                | Opt::LT
+                | Opt::LE
+                | Opt::EQ
+                | Opt::NQ
+                | Opt::IN
+                | Opt::LIKE
+                | Opt::MATCH
+                | Opt::SW => 1,
+                Opt::OT | Opt::AT => 2,
+                Opt::NC => 3,
+                _ => 0,
             },
-        };
+        }
+    }
+}
+
+impl PartialOrd for Opt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let prec = Precedence::Standard;
+        prec.level(self, false)
+            .partial_cmp(&prec.level(other, false))
     }
 }
 
@@ -150,9 +226,15 @@ impl Calc {
         return Ok(s);
     }
     //拆分
-    pub(crate) fn expression_split(mut expr: String) -> anyhow::Result<VecDeque<Element>> {
+    pub(crate) fn expression_split(mut expr: String) -> Result<VecDeque<Token>, ParseError> {
+        let total = expr.len();
         let mut deq = VecDeque::new();
+        let mut tokens = VecDeque::new();
+        // case ... end 的嵌套层数，只有在case中 when then else end 才是关键字
+        let mut case_depth = 0;
         'lp: while !expr.is_empty() {
+            // 每一轮开始时剩余文本的偏移，用于计算单元的位置
+            let start = total - expr.len();
             if expr.starts_with("\n") {
                 expr = expr.split_off(1);
             } else if expr.starts_with("\r") {
@@ -182,39 +264,64 @@ impl Calc {
             } else if expr.starts_with("}") {
                 deq.push_back(Element::RightBig);
                 expr = expr.split_off(1);
-            } else if expr.starts_with("'") {
+            } else if expr.starts_with(['\'', '"']) {
                 //字符串
-                let mut index = 0;
-                for (i, e) in expr.as_bytes().into_iter().enumerate() {
-                    if i != 0 && *e == '\'' as u8 {
-                        index = i;
-                        break;
+                let (value, len) = match scan_string(&expr) {
+                    Some(s) => s,
+                    None => {
+                        return Err(ParseError::UnterminatedString {
+                            at: Position::new(Span::new(start, total)),
+                        })
                     }
-                }
-                let mut e = expr.split_off(index + 1);
-                unsafe {
-                    std::ptr::swap(&mut e, &mut expr);
-                }
-                let e = e.trim_matches(|c| c == '\'');
-                deq.push_back(Element::CALC(Calc::String(e.to_string())));
-            } else if expr.starts_with("/*") {
+                };
+                expr = expr.split_off(len);
+                deq.push_back(Element::CALC(Calc::String(value)));
+            } else if expr.starts_with("/*") || expr.starts_with("//") {
                 //注释
-                let mut index = 0;
-                let bs = expr.as_bytes();
-                while index < bs.len() - 1 {
-                    if bs[index] == b'*' && bs[index + 1] == b'/' {
-                        expr = expr.split_off(index + 2);
+                match skip_comment(&expr) {
+                    Some(n) => {
+                        expr = expr.split_off(n);
                         continue 'lp;
                     }
-                    index += 1;
+                    None => {
+                        return Err(ParseError::UnterminatedComment {
+                            at: Position::new(Span::new(start, start + 2)),
+                        })
+                    }
                 }
-                return anyhow!("/* and */ mismatch").err();
+            } else if expr.starts_with("->") {
+                deq.push_back(Element::Arrow);
+                expr = expr.split_off(2);
+            } else if expr.starts_with("??") {
+                deq.push_back(Element::OPT(Opt::NC));
+                expr = expr.split_off(2);
+            } else if expr.starts_with("?") {
+                deq.push_back(Element::Question);
+                expr = expr.split_off(1);
+            } else if expr.starts_with(":") {
+                deq.push_back(Element::Colon);
+                expr = expr.split_off(1);
+            } else if expr.starts_with('@') {
+                //日期、时间和时长，转为规范的字符串
+                let (value, len) = match temporal_literal(&expr) {
+                    Some(s) => s,
+                    None => return Err(ParseError::syntax(
+                        "invalid time literal, want @2024-01-05, @2024-01-05T10:00:00+08:00 or @5d",
+                        Span::new(start, start + 1),
+                    )),
+                };
+                expr = expr.split_off(len);
+                deq.push_back(Element::CALC(Calc::Time(value)));
             } else if Opt::parse_one(&mut expr, &mut deq) {
                 //运算符
             } else {
                 let mut chars = expr.chars();
                 let char = match chars.next() {
-                    None => return anyhow!("parse error over").err(),
+                    None => {
+                        return Err(ParseError::EmptyExpression {
+                            at: Position::new(Span::new(start, start)),
+                        })
+                    }
                     Some(s) => s,
                 };
                 if char.is_ascii_digit() {
@@ -233,232 +340,442 @@ impl Calc {
                     unsafe {
                         std::ptr::swap(&mut e, &mut expr);
                     }
+                    let invalid = || ParseError::InvalidNumber {
+                        token: e.clone(),
+                        at: Position::new(Span::new(start, start + i)),
+                    };
                     if not_is_float {
-                        let i = i64::from_str(e.as_str())?;
+                        let i = i64::from_str(e.as_str()).map_err(|_| invalid())?;
                         deq.push_back(Element::CALC(Calc::Number(i)));
                     } else {
-                        let i = f64::from_str(e.as_str())?;
+                        let i = f64::from_str(e.as_str()).map_err(|_| invalid())?;
                         deq.push_back(Element::CALC(Calc::Float(i)));
                     }
                 } else if char == '_' {
                     //保留的部分
-                    return anyhow!("Content beginning with the character _ is reserved").err();
-                } else if char.is_alphabetic() {
-                    //变量 或者函数 或者数组
-                    let mut i = 1;
+                    return Err(ParseError::Reserved {
+                        token: char.to_string(),
+                        at: Position::new(Span::new(start, start + 1)),
+                    });
+                } else if char.is_alphabetic()
+                    || char == '$' && expr[1..].starts_with(char::is_alphabetic)
+                {
+                    //变量 或者函数 或者数组，$开头的是保留的根字段，例如：$out
+                    let mut i = char.len_utf8();
                     let mut ty = 1; //1:变量 2:函数
-                    for e in chars.into_iter() {
+                    while let Some(e) = expr[i..].chars().next() {
                         if e == '(' {
                             //函数
                             ty = 2;
                             break;
-                        } else if e.is_ascii_digit()
-                            || e.is_alphabetic()
-                            || e == '_'
-                            || e == '_'
-                            || e == '.'
-                        {
+                        } else if e.is_ascii_digit() || e.is_alphabetic() || e == '_' || e == '.' {
+                            i += e.len_utf8()
+                        } else if let Some(n) = field_index_len(&expr[i..]) {
+                            //紧跟在字段后的下标 [0] [-1] [*]
+                            i += n
+                        } else if e == '?' && optional_chain(&expr[i + 1..]) {
+                            //可选链 a?.b a?[0]
+                            i += 1
                         } else {
                             break;
                         }
-                        i += 1
                     }
                     let mut e = expr.split_off(i);
                     unsafe {
                         std::ptr::swap(&mut e, &mut expr);
                     }
+                    // 字段路径在这里解析一次，执行时直接使用
+                    let span = Span::new(start, total - expr.len());
+                    let field = |e: String| match FieldPath::parse(e) {
+                        Ok(f) => Ok(Element::CALC(Calc::Field(f))),
+                        Err(err) => Err(ParseError::syntax(err.to_string(), span)),
+                    };
                     if e == "true" || e == "false" {
                         deq.push_back(Element::CALC(Calc::Bool(
                             bool::from_str(e.as_str()).unwrap(),
                         )));
                     } else if e.to_lowercase() == "null" || e.to_lowercase() == "nil" {
                         deq.push_back(Element::CALC(Calc::NULL));
+                    } else if let (1, Some(opt), Some(Token { ele, .. })) =
+                        (ty, Opt::from_word(&e), tokens.back())
+                    {
+                        // 前一个单元是算子时是运算符，否则是字段
+                        match ele {
+                            Element::CALC(_)
+                            | Element::RightSmall
+                            | Element::RightMed
+                            | Element::End => deq.push_back(Element::OPT(opt)),
+                            _ => deq.push_back(field(e)?),
+                        }
+                    } else if let Some(kw) = case_keyword(&e, &expr, case_depth) {
+                        match kw {
+                            Element::Case => case_depth += 1,
+                            Element::End => case_depth -= 1,
+                            _ => {}
+                        }
+                        deq.push_back(kw);
                     } else {
                         match ty {
-                            1 => deq.push_back(Element::CALC(Calc::Field(e))),
-                            2 => deq.push_back(Element::CALC(Calc::Function(e, vec![]))),
-                            _ => return anyhow!("unknown string({e})").err(),
+                            1 => deq.push_back(field(e)?),
+                            _ => deq.push_back(Element::CALC(Calc::Function(e, vec![]))),
                         }
                     }
                 } else {
-                    return anyhow!("unknown char({char})").err();
+                    return Err(ParseError::UnknownChar {
+                        ch: char,
+                        at: Position::new(Span::new(start, start + char.len_utf8())),
+                    });
                 }
             }
+            // 本轮产生的单元记录位置
+            let span = Span::new(start, total - expr.len());
+            while let Some(ele) = deq.pop_front() {
+                tokens.push_back(Token { ele, span });
+            }
         }
-        return Ok(deq);
+        Ok(tokens)
     }
     //组合
 
     // 取一个单元算子
     pub(crate) fn convert_one_group_calc(
         calc: Option<Calc>,
-        deq: &mut VecDeque<Element>,
-    ) -> anyhow::Result<Calc> {
+        deq: &mut VecDeque<Token>,
+        prec: Precedence,
+    ) -> Result<Calc, ParseError> {
         if deq.is_empty() {
             return if let Some(s) = calc {
                 Ok(s)
             } else {
-                anyhow!("expression is null").err()
+                Err(ParseError::EmptyExpression {
+                    at: Position::default(),
+                })
                 // Calc::NULL.ok()
             };
         };
-        let ele = deq.pop_front().unwrap();
+        let Token { ele, span } = deq.pop_front().unwrap();
         match ele {
             Element::OPT(opt) => {
-                match opt {
-                    Opt::ADD
-                    | Opt::MUL
-                    | Opt::DIV
-                    | Opt::REM
-                    | Opt::AND
-                    | Opt::OR
-                    | Opt::XOR
-                    | Opt::SHL
-                    | Opt::SHR => {
-                        //向右边取一个算子单元
-                        let lc = if let Some(s) = calc {
-                            s
-                        } else {
-                            return anyhow!(
-                                "parsing the left side of the operation[{opt:?}] failed"
-                            )
-                            .err();
-                        };
-                        let mut r_deq = Self::split_deque_by_opt(deq, &opt)?;
-                        let rc = Self::convert_one_group_calc(None, &mut r_deq)?;
-                        return Self::convert_one_group_calc(
-                            Some(Calc::Operator(opt, vec![lc, rc])),
-                            deq,
-                        );
-                    }
-                    Opt::SUB | Opt::NOT => {
-                        // -
-                        //向右边取一个算子单元
-                        let mut r_deq = Self::split_deque_by_opt(deq, &opt)?;
-                        let rc = Self::convert_one_group_calc(None, &mut r_deq)?;
-                        return match calc {
-                            None => Self::convert_one_group_calc(
-                                Some(Calc::Operator(opt, vec![rc])),
-                                deq,
-                            ),
-                            Some(lc) => Self::convert_one_group_calc(
-                                Some(Calc::Operator(opt, vec![lc, rc])),
-                                deq,
-                            ),
-                        };
-                    }
-                    Opt::REV => {
-                        // ~
-                        if let Some(_) = calc {
-                            return anyhow!(
-                                "operation[{opt:?}] cannot perform multi-value calculations"
-                            )
-                            .err();
-                        };
-                        let mut r_deq = Self::split_deque_by_opt(deq, &opt)?;
-                        let rc = Self::convert_one_group_calc(None, &mut r_deq)?;
+                // 前面没有算子时是前缀运算符：- ! ~
+                let lc = match (calc, &opt) {
+                    (None, Opt::SUB | Opt::NOT | Opt::REV) => {
+                        let rc = Self::convert_right_calc(deq, &opt, true, span, prec)?;
                         return Self::convert_one_group_calc(
                             Some(Calc::Operator(opt, vec![rc])),
                             deq,
+                            prec,
                         );
                     }
-                    Opt::GT
-                    | Opt::GE
-                    | Opt::LT
-                    | Opt::LE
-                    | Opt::EQ
-                    | Opt::NQ
-                    | Opt::AT
-                    | Opt::OT => {
-                        let lc = if let Some(s) = calc {
-                            s
-                        } else {
-                            return anyhow!(
-                                "parsing the left side of the operation[{opt:?}] failed"
-                            )
-                            .err();
-                        };
-                        let mut r_deq = Self::split_deque_by_opt(deq, &opt)?;
-                        let rc = Self::convert_one_group_calc(None, &mut r_deq)?;
-                        return Self::convert_one_group_calc(
-                            Some(Calc::Operator(opt, vec![lc, rc])),
-                            deq,
-                        );
+                    (Some(_), Opt::NOT | Opt::REV) => {
+                        return Err(ParseError::syntax(
+                            format!("operation[{opt:?}] cannot perform multi-value calculations"),
+                            span,
+                        ));
                     }
+                    (Some(s), _) => s,
+                    (None, _) => return Err(Self::missing_operand(&opt, span)),
+                };
+                //向右边取一个算子单元，比当前运算符结合更紧的都属于右边
+                let mut rc = Self::convert_right_calc(deq, &opt, false, span, prec)?;
+                // 常量模式在解析时编译
+                if let (Opt::LIKE | Opt::MATCH, Calc::String(p)) = (&opt, &rc) {
+                    let pattern = match opt {
+                        Opt::LIKE => Pattern::like(p),
+                        _ => Pattern::regex(p),
+                    };
+                    rc = match pattern {
+                        Ok(o) => Calc::Pattern(o),
+                        Err(e) => return Err(ParseError::syntax(e.to_string(), span)),
+                    };
                 }
+                Self::convert_one_group_calc(Some(Calc::Operator(opt, vec![lc, rc])), deq, prec)
             }
             Element::CALC(ec) => {
+                if calc.is_some() {
+                    return Err(ParseError::UnexpectedToken {
+                        token: ec.to_string(),
+                        at: Position::new(span),
+                    });
+                }
                 return match ec {
                     Calc::NULL
                     | Calc::Field(_)
                     | Calc::String(_)
+                    | Calc::Time(_)
                     | Calc::Number(_)
                     | Calc::Float(_)
                     | Calc::Bool(_)
+                    | Calc::Pattern(_)
                     | Calc::Operator(_, _)
-                    | Calc::Array(_) => Self::convert_one_group_calc(Some(ec), deq),
+                    | Calc::Lambda(_, _)
+                    | Calc::If(_, _, _)
+                    | Calc::Case(_, _)
+                    | Calc::NullSafe(_)
+                    | Calc::Decimal(_)
+                    | Calc::Checked(_, _)
+                    | Calc::Array(_) => Self::convert_one_group_calc(Some(ec), deq, prec),
                     Calc::Function(name, mut args) => {
-                        if calc.is_some() {
-                            return anyhow!("function[{name}] parse failed").err();
-                        }
-                        if let Some(Element::LeftSmall) = deq.pop_front() {
-                        } else {
-                            return anyhow!("function[{name}] right must is '('").err();
-                        }
-                        let list = Self::split_deque_by_comma(deq, Element::RightSmall)?;
+                        let open = match deq.pop_front() {
+                            Some(Token {
+                                ele: Element::LeftSmall,
+                                span,
+                            }) => span,
+                            _ => {
+                                return Err(ParseError::syntax(
+                                    format!("function[{name}] right must is '('"),
+                                    span,
+                                ))
+                            }
+                        };
+                        let list = Self::split_deque_by_comma(deq, Element::RightSmall, open)?;
                         for mut i in list {
-                            let c = Self::convert_one_group_calc(None, &mut i)?;
+                            let c = Self::convert_one_group_calc(None, &mut i, prec)?;
                             args.push(c);
                         }
-                        Self::convert_one_group_calc(Some(Calc::Function(name, args)), deq)
+                        // if(cond, a, b) 是条件表达式，不是函数
+                        if name == "if" {
+                            let calc = match <[Calc; 3]>::try_from(args) {
+                                Ok([cond, a, b]) => Calc::If(cond.into(), a.into(), b.into()),
+                                Err(_) => {
+                                    return Err(ParseError::syntax(
+                                        "if want args: (condition, value, else value)",
+                                        span,
+                                    ))
+                                }
+                            };
+                            return Self::convert_one_group_calc(Some(calc), deq, prec);
+                        }
+                        Self::convert_one_group_calc(Some(Calc::Function(name, args)), deq, prec)
                     }
-                }
+                };
             }
             Element::LeftSmall => {
                 if calc.is_some() {
-                    return anyhow!("'(' and ')' must bilateral symmetry").err();
+                    return Err(ParseError::UnexpectedToken {
+                        token: "(".into(),
+                        at: Position::new(span),
+                    });
                 }
                 // 拆分出一个单元节点
                 let mut l_deq =
-                    Self::split_deque_by_ele(deq, Element::LeftSmall, Element::RightSmall)?;
-                let lc = Self::convert_one_group_calc(None, &mut l_deq)?;
-                return Self::convert_one_group_calc(Some(lc), deq);
+                    Self::split_deque_by_ele(deq, Element::LeftSmall, Element::RightSmall, span)?;
+                if l_deq.is_empty() {
+                    return Err(ParseError::EmptyExpression {
+                        at: Position::new(span),
+                    });
+                }
+                let lc = Self::convert_one_group_calc(None, &mut l_deq, prec)?;
+                return Self::convert_one_group_calc(Some(lc), deq, prec);
             }
-            Element::RightSmall => return anyhow!("')' must match a '('").err(),
             Element::LeftMed => {
                 //数组
                 if calc.is_some() {
-                    return anyhow!("'[' and ']' mut bilateral symmetry").err();
+                    return Err(ParseError::UnexpectedToken {
+                        token: "[".into(),
+                        at: Position::new(span),
+                    });
                 }
-                let list = Self::split_deque_by_comma(deq, Element::RightMed)?;
+                let list = Self::split_deque_by_comma(deq, Element::RightMed, span)?;
                 let mut array = vec![];
                 for mut i in list {
-                    let c = Self::convert_one_group_calc(None, &mut i)?;
+                    let c = Self::convert_one_group_calc(None, &mut i, prec)?;
                     array.push(c);
                 }
-                return Self::convert_one_group_calc(Some(Calc::Array(array)), deq);
+                return Self::convert_one_group_calc(Some(Calc::Array(array)), deq, prec);
             }
-            Element::RightMed => return anyhow!("']' must match a '['").err(),
-            Element::LeftBig => return anyhow!("reserved character '{{'").err(),
-            Element::RightBig => return anyhow!("reserved character '}}'").err(),
-            Element::Comma => {
-                return anyhow!("You should use multiple operators instead of multiple expressions")
-                    .err()
+            Element::RightSmall | Element::RightMed => Err(ParseError::Unmatched {
+                token: ele.to_string(),
+                at: Position::new(span),
+            }),
+            Element::LeftBig | Element::RightBig => Err(ParseError::Reserved {
+                token: ele.to_string(),
+                at: Position::new(span),
+            }),
+            Element::Comma => Err(ParseError::syntax(
+                "You should use multiple operators instead of multiple expressions",
+                span,
+            )),
+            Element::Question => {
+                // cond ? a : b，右结合，else分支取剩余的全部
+                let cond = match calc {
+                    Some(s) => s,
+                    None => {
+                        return Err(ParseError::MissingOperand {
+                            opt: "?".into(),
+                            at: Position::new(span),
+                        })
+                    }
+                };
+                let mut a_deq = Self::split_deque_by_colon(deq, span)?;
+                if a_deq.is_empty() || deq.is_empty() {
+                    return Err(ParseError::EmptyExpression {
+                        at: Position::new(span),
+                    });
+                }
+                let a = Self::convert_one_group_calc(None, &mut a_deq, prec)?;
+                let b = Self::convert_one_group_calc(None, deq, prec)?;
+                Ok(Calc::If(cond.into(), a.into(), b.into()))
+            }
+            Element::Case => {
+                if calc.is_some() {
+                    return Err(ParseError::UnexpectedToken {
+                        token: "case".into(),
+                        at: Position::new(span),
+                    });
+                }
+                let body = Self::split_deque_by_ele(deq, Element::Case, Element::End, span)?;
+                let case = Self::convert_case(body, span, prec)?;
+                Self::convert_one_group_calc(Some(case), deq, prec)
+            }
+            Element::Colon | Element::When | Element::Then | Element::Else | Element::End => {
+                Err(ParseError::UnexpectedToken {
+                    token: ele.to_string(),
+                    at: Position::new(span),
+                })
+            }
+            Element::Arrow => {
+                // lambda的参数只能是一个名字，箭头右边全部是函数体
+                let param = match calc {
+                    Some(Calc::Field(f)) if !f.contains(['.', '[']) => f.to_string(),
+                    _ => {
+                        return Err(ParseError::syntax(
+                            "lambda parameter must be a name, like: x -> x.price > 100",
+                            span,
+                        ))
+                    }
+                };
+                if deq.is_empty() {
+                    return Err(ParseError::EmptyExpression {
+                        at: Position::new(span),
+                    });
+                }
+                let body = Self::convert_one_group_calc(None, deq, prec)?;
+                Ok(Calc::Lambda(param, Box::new(body)))
             }
         }
         // return Ok(Calc::NULL)
     }
+    fn missing_operand(opt: &Opt, span: Span) -> ParseError {
+        ParseError::MissingOperand {
+            opt: opt.as_ref().to_string(),
+            at: Position::new(span),
+        }
+    }
+    // 取运算符右边的算子，右边为空时报告运算符的位置
+    fn convert_right_calc(
+        deq: &mut VecDeque<Token>,
+        opt: &Opt,
+        unary: bool,
+        span: Span,
+        prec: Precedence,
+    ) -> Result<Calc, ParseError> {
+        let mut r_deq = Self::split_deque_by_opt(deq, prec.level(opt, unary), prec)?;
+        if r_deq.is_empty() {
+            return Err(Self::missing_operand(opt, span));
+        }
+        Self::convert_one_group_calc(None, &mut r_deq, prec)
+    }
+    // case和end之间的部分：when cond then value ... [else value]
+    fn convert_case(
+        body: VecDeque<Token>,
+        open: Span,
+        prec: Precedence,
+    ) -> Result<Calc, ParseError> {
+        // 按同一层的 when then else 拆分
+        let mut parts: Vec<(Token, VecDeque<Token>)> = vec![];
+        let mut depth = 0;
+        for t in body {
+            match t.ele {
+                Element::Case => depth += 1,
+                Element::End => depth -= 1,
+                Element::When | Element::Then | Element::Else if depth == 0 => {
+                    parts.push((t, VecDeque::new()));
+                    continue;
+                }
+                _ => {}
+            }
+            match parts.last_mut() {
+                Some((_, part)) => part.push_back(t),
+                None => {
+                    return Err(ParseError::syntax("case must start with 'when'", t.span));
+                }
+            }
+        }
+        let mut branches = vec![];
+        let mut default = Calc::NULL;
+        let mut parts = parts.into_iter().peekable();
+        while let Some((kw, mut part)) = parts.next() {
+            if part.is_empty() {
+                return Err(ParseError::EmptyExpression {
+                    at: Position::new(kw.span),
+                });
+            }
+            let calc = Self::convert_one_group_calc(None, &mut part, prec)?;
+            match kw.ele {
+                Element::When => match parts.next() {
+                    Some((then, mut part)) if then == Element::Then && !part.is_empty() => {
+                        let value = Self::convert_one_group_calc(None, &mut part, prec)?;
+                        branches.push((calc, value));
+                    }
+                    _ => {
+                        return Err(ParseError::syntax(
+                            "case when want: when condition then value",
+                            kw.span,
+                        ))
+                    }
+                },
+                Element::Else if parts.peek().is_none() => default = calc,
+                _ => {
+                    return Err(ParseError::UnexpectedToken {
+                        token: kw.ele.to_string(),
+                        at: Position::new(kw.span),
+                    })
+                }
+            }
+        }
+        if branches.is_empty() {
+            return Err(ParseError::syntax("case must have 'when'", open));
+        }
+        Ok(Calc::Case(branches, Box::new(default)))
+    }
+    // 取 ? 和对应的 : 之间的部分，嵌套的 ?: 和括号中的 : 跳过
+    fn split_deque_by_colon(
+        deq: &mut VecDeque<Token>,
+        open: Span,
+    ) -> Result<VecDeque<Token>, ParseError> {
+        let mut sub_deq = VecDeque::new();
+        let (mut count, mut nested) = (0i32, 0i32);
+        while let Some(e) = deq.pop_front() {
+            match e.ele {
+                Element::LeftSmall | Element::LeftMed | Element::Case => count += 1,
+                Element::RightSmall | Element::RightMed | Element::End => count -= 1,
+                Element::Question if count == 0 => nested += 1,
+                Element::Colon if count == 0 && nested == 0 => return Ok(sub_deq),
+                Element::Colon if count == 0 => nested -= 1,
+                _ => {}
+            }
+            sub_deq.push_back(e);
+        }
+        Err(ParseError::syntax("'?' want ':' after it", open))
+    }
     pub(crate) fn split_deque_by_comma(
-        deq: &mut VecDeque<Element>,
+        deq: &mut VecDeque<Token>,
         le: Element,
-    ) -> anyhow::Result<Vec<VecDeque<Element>>> {
+        open: Span,
+    ) -> Result<Vec<VecDeque<Token>>, ParseError> {
         let mut sub_deq = VecDeque::new();
         let mut deq_list = vec![];
         let (mut count_small, mut count_med) = match le {
             Element::RightSmall => (1, 0),
             Element::RightMed => (0, 1),
-            _ => return anyhow!("Calc.split_deque_by_comma nonsupport {:?}", le).err(),
+            _ => {
+                return Err(ParseError::syntax(
+                    format!("Calc.split_deque_by_comma nonsupport {:?}", le),
+                    open,
+                ))
+            }
         };
+        let mut closed = false;
         while let Some(e) = deq.pop_front() {
             let comma = match le {
                 Element::RightSmall => e == Element::Comma && count_med == 0 && count_small == 1,
@@ -466,55 +783,84 @@ impl Calc {
                 _ => panic!(""),
             };
             if comma {
+                if sub_deq.is_empty() {
+                    return Err(ParseError::UnexpectedToken {
+                        token: ",".into(),
+                        at: Position::new(e.span),
+                    });
+                }
                 deq_list.push(sub_deq.clone());
                 sub_deq.clear();
                 continue;
             }
-            if Element::LeftSmall == e {
+            if e == Element::LeftSmall {
                 count_small += 1;
-            } else if Element::RightSmall == e {
+            } else if e == Element::RightSmall {
                 count_small -= 1;
-            } else if Element::LeftMed == e {
+            } else if e == Element::LeftMed {
                 count_med += 1;
-            } else if Element::RightMed == e {
+            } else if e == Element::RightMed {
                 count_med -= 1;
             }
-            if le == e && count_small == 0 && count_med == 0 {
+            if e == le && count_small == 0 && count_med == 0 {
+                closed = true;
                 break;
             }
             sub_deq.push_back(e);
         }
+        if !closed {
+            let token = if le == Element::RightSmall { "(" } else { "[" };
+            return Err(ParseError::Unmatched {
+                token: token.into(),
+                at: Position::new(open),
+            });
+        }
         if !sub_deq.is_empty() {
             deq_list.push(sub_deq.clone());
         }
         return Ok(deq_list);
     }
+    // 取出结合比level更紧的部分，遇到同级或更松的运算符时停止，同级时左结合
     pub(crate) fn split_deque_by_opt(
-        deq: &mut VecDeque<Element>,
-        opt: &Opt,
-    ) -> anyhow::Result<VecDeque<Element>> {
-        let mut sub_deq = VecDeque::new();
+        deq: &mut VecDeque<Token>,
+        level: u8,
+        prec: Precedence,
+    ) -> Result<VecDeque<Token>, ParseError> {
+        let mut sub_deq: VecDeque<Token> = VecDeque::new();
         let mut count = 0i32;
         while let Some(e) = deq.pop_front() {
-            if let Element::OPT(o) = e.clone() {
-                if !(&o < opt) && count == 0 {
-                    deq.push_front(Element::OPT(o));
+            // 开头或者紧跟在运算符后面的是前缀运算符，属于右边的算子
+            let prefix = matches!(
+                sub_deq.back(),
+                None | Some(Token {
+                    ele: Element::OPT(_),
+                    ..
+                })
+            );
+            match e.ele {
+                Element::OPT(ref o) if !prefix && count == 0 && prec.level(o, false) >= level => {
+                    deq.push_front(e);
                     break;
                 }
-            } else if Element::LeftSmall == e {
-                count += 1;
-            } else if Element::RightSmall == e {
-                count -= 1;
+                // 条件表达式的优先级最低
+                Element::Question | Element::Colon if count == 0 => {
+                    deq.push_front(e);
+                    break;
+                }
+                Element::LeftSmall | Element::LeftMed | Element::Case => count += 1,
+                Element::RightSmall | Element::RightMed | Element::End => count -= 1,
+                _ => {}
             }
             sub_deq.push_back(e);
         }
         return Ok(sub_deq);
     }
     pub(crate) fn split_deque_by_ele(
-        deq: &mut VecDeque<Element>,
+        deq: &mut VecDeque<Token>,
         le: Element,
         re: Element,
-    ) -> anyhow::Result<VecDeque<Element>> {
+        open: Span,
+    ) -> Result<VecDeque<Token>, ParseError> {
         let mut sub_deq = VecDeque::new();
         let mut count = 1i32;
         while let Some(e) = deq.pop_front() {
@@ -528,7 +874,10 @@ impl Calc {
             }
             sub_deq.push_back(e);
         }
-        return anyhow!("element[{le:?} {re:?}] mismatch").err();
+        Err(ParseError::Unmatched {
+            token: le.to_string(),
+            at: Position::new(open),
+        })
     }
 
     #[allow(dead_code)]
@@ -537,17 +886,74 @@ impl Calc {
 
         print!("expression_split -->");
         for i in deq.iter() {
-            print!("_{}_", i.to_string());
+            print!("_{}_", i.ele.to_string());
         }
         println!(" <--- over");
 
-        Self::convert_one_group_calc(None, &mut deq)
+        Ok(Self::convert_one_group_calc(
+            None,
+            &mut deq,
+            Precedence::Standard,
+        )?)
+    }
+}
+
+// case后面紧跟when时是关键字，case中的 when then else end 是关键字，其它情况是字段
+fn case_keyword(word: &str, rest: &str, depth: i32) -> Option<Element> {
+    let kw = match word.to_lowercase().as_str() {
+        "case" => {
+            let rest = rest.trim_start();
+            let next = rest.find(|c: char| !c.is_alphanumeric() && c != '_');
+            let next = &rest[..next.unwrap_or(rest.len())];
+            return next.eq_ignore_ascii_case("when").then_some(Element::Case);
+        }
+        "when" => Element::When,
+        "then" => Element::Then,
+        "else" => Element::Else,
+        "end" => Element::End,
+        _ => return None,
+    };
+    (depth > 0).then_some(kw)
+}
+
+// s以@开始，取能解析为日期、时间或者时长的最长部分，返回规范的文本和长度
+fn temporal_literal(s: &str) -> Option<(String, usize)> {
+    let body = &s[1..];
+    let n = body
+        .find(|c: char| !(c.is_ascii_alphanumeric() || ":.+-".contains(c)))
+        .unwrap_or(body.len());
+    (1..=n)
+        .rev()
+        .find_map(|i| Temporal::parse(&body[..i]).map(|t| (t.to_string(), i + 1)))
+}
+
+// ? 后面是 .字段 或者下标时是可选链
+fn optional_chain(s: &str) -> bool {
+    match s.strip_prefix('.') {
+        Some(rest) => rest.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '['),
+        None => field_index_len(s).is_some(),
+    }
+}
+
+// 字段下标的长度，不是下标返回None
+fn field_index_len(s: &str) -> Option<usize> {
+    let end = s.strip_prefix('[')?.find(']')? + 1;
+    let inner = s[1..end].trim();
+    let digits = inner.strip_prefix('-').unwrap_or(inner);
+    if inner == "*" || !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(end + 1)
+    } else {
+        None
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::Calc;
+    use crate::{Calc, CalcBuilder, ParseError, Precedence, Span};
+    use rush_core::{Function, FunctionSet};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
 
     //cargo test --color=always --lib calc_parse::test::test_parse_remove_annotation --no-fail-fast --  --exact  unstable-options --nocapture
     #[test]
@@ -574,7 +980,7 @@ mod test {
         println!("test expression --> {data}");
         print!("split result -->");
         for i in deq.iter() {
-            print!("_{}_", i.to_string());
+            print!("_{}_", i.ele.to_string());
         }
         println!(" <--- over")
     }
@@ -597,4 +1003,151 @@ mod test {
         let calc = Calc::expression_parse(expr.into()).unwrap();
         println!("--->{}", calc.to_string());
     }
+
+    //cargo test --color=always --lib calc_parse::test::test_precedence_conformance --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_precedence_conformance() {
+        let parse = |expr: &str, prec: Precedence| -> String {
+            let calc = CalcBuilder::new(expr).precedence(prec).build().unwrap();
+            calc.to_string()
+        };
+        // (表达式, 标准优先级, 旧版本优先级)
+        let cases = [
+            ("1 + 2 * 3", "(1 + (2 * 3))", "((1 + 2) * 3)"),
+            ("1 * 2 + 3", "((1 * 2) + 3)", "((1 * 2) + 3)"),
+            ("10 - 4 - 3", "((10 - 4) - 3)", "((10 - 4) - 3)"),
+            ("8 / 4 / 2", "((8 / 4) / 2)", "((8 / 4) / 2)"),
+            ("1 + 6 % 4", "(1 + (6 % 4))", "((1 + 6) % 4)"),
+            ("1 << 2 + 1", "(1 << (2 + 1))", "((1 << 2) + 1)"),
+            (
+                "a + 1 > b * 2",
+                "((a + 1) > (b * 2))",
+                "((a + 1) > (b * 2))",
+            ),
+            (
+                "a < b == c > d",
+                "((a < b) == (c > d))",
+                "(((a < b) == c) > d)",
+            ),
+            ("a & 1 == 1", "(a & (1 == 1))", "((a & 1) == 1)"),
+            (
+                "a | b ^ c & d",
+                "(a | (b ^ (c & d)))",
+                "(((a | b) ^ c) & d)",
+            ),
+            ("a || b && c", "(a || (b && c))", "((a || b) && c)"),
+            (
+                "a && b || c && d",
+                "((a && b) || (c && d))",
+                "(((a && b) || c) && d)",
+            ),
+            ("!a && b", "((! a) && b)", "((! a) && b)"),
+            ("-a * b", "((- a) * b)", "((- a) * b)"),
+            ("a * -b + c", "((a * (- b)) + c)", "((a * (- b)) + c)"),
+            ("!!a", "(! (! a))", "(! (! a))"),
+            ("~a & b", "((~ a) & b)", "((~ a) & b)"),
+            (
+                "a in b || c like 'x%'",
+                r#"((a in b) || (c like "x%"))"#,
+                r#"((a in b) || (c like "x%"))"#,
+            ),
+            (
+                "a > 1 ? b + 1 : c * 2",
+                "((a > 1) ? (b + 1) : (c * 2))",
+                "((a > 1) ? (b + 1) : (c * 2))",
+            ),
+            ("(1 + 2) * 3", "((1 + 2) * 3)", "((1 + 2) * 3)"),
+        ];
+        for (expr, standard, legacy) in cases {
+            assert_eq!(parse(expr, Precedence::Standard), standard, "{expr}");
+            assert_eq!(parse(expr, Precedence::Legacy), legacy, "{expr}");
+        }
+
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let value = |expr: &str| -> Value {
+            let calc: Calc = expr.parse().unwrap();
+            calc.value(&fs, &Value::Null).unwrap()
+        };
+        assert_eq!(value("1 + 2 * 3"), 7);
+        assert_eq!(value("2 * 3 - 4 / 2 - 1"), 3);
+        assert_eq!(value("1 << 2 + 1"), 8);
+        assert_eq!(value("true || false && false"), true);
+        assert_eq!(value("-2 * -3"), 6);
+    }
+
+    //cargo test --color=always --lib calc_parse::test::test_parse_error_position --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_parse_error_position() {
+        let parse_error = |expr: &str| {
+            let err = expr.parse::<Calc>().unwrap_err();
+            err.downcast::<ParseError>().unwrap()
+        };
+        let err = parse_error("a > 1 &&\n  b # 2");
+        assert!(matches!(err, ParseError::UnknownChar { ch: '#', .. }));
+        assert_eq!(err.position().line, 2);
+        assert_eq!(err.position().column, 5);
+        assert_eq!(err.span(), Span::new(13, 14));
+
+        let err = parse_error("(a + b");
+        assert!(matches!(err, ParseError::Unmatched { .. }));
+        assert_eq!(err.span(), Span::new(0, 1));
+
+        let err = parse_error("a > ");
+        assert!(matches!(err, ParseError::MissingOperand { .. }));
+        assert_eq!(err.span(), Span::new(2, 3));
+
+        let err = parse_error("name == 'abc");
+        assert!(matches!(err, ParseError::UnterminatedString { .. }));
+        assert_eq!(err.position().column, 9);
+
+        // 字段路径在解析时检查，下标溢出在这里就报错
+        let err = parse_error("a > 1 && items[99999999999999999999] > 0");
+        assert!(matches!(err, ParseError::Syntax { .. }));
+        assert_eq!(err.span(), Span::new(9, 36));
+
+        let expr = "a > 1 && contain(tags, b c)";
+        let err = parse_error(expr);
+        assert_eq!(
+            err.render(expr),
+            "error: unexpected token(c)\n --> 1:26\n  |\n1 | a > 1 && contain(tags, b c)\n  |                          ^"
+        );
+    }
+
+    //cargo test --color=always --lib calc_parse::test::test_temporal_literal --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_temporal_literal() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let input = serde_json::json!({"created":"2024-01-05T10:00:00+08:00","age":"36h","n":3});
+        let eval = |expr: &str| {
+            let calc: Calc = expr.parse()?;
+            calc.value(&fs, &input)
+        };
+        assert_eq!(
+            eval("@2024-01-05T10:00:00+08:00").unwrap(),
+            Value::from("2024-01-05T10:00:00+08:00")
+        );
+        assert_eq!(eval("@90m").unwrap(), Value::from("1h30m"));
+        assert_eq!(eval("created > @2024-01-05").unwrap(), Value::Bool(true));
+        assert_eq!(
+            eval("created == @2024-01-05T02:00:00Z").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval("created < @2024-01-05T01:00Z").unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(eval("age >= @1d && age < @2d").unwrap(), Value::Bool(true));
+        assert_eq!(eval("@1d != @24h").unwrap(), Value::Bool(false));
+        assert_eq!(eval("@1d == '24h'").unwrap(), Value::Bool(true));
+        // 没有时间类型的一边时按普通字符串比较
+        assert_eq!(eval("'1d' == '24h'").unwrap(), Value::Bool(false));
+        assert_eq!(eval("'1m' != '60s'").unwrap(), Value::Bool(true));
+        assert_eq!(
+            eval("created == '2024-01-05T02:00:00Z'").unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(eval("n > 2").unwrap(), Value::Bool(true));
+        assert!(eval("age > @2024-01-05").is_err());
+        assert!(eval("a > @tomorrow").is_err());
+    }
 }
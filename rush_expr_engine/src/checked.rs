@@ -0,0 +1,302 @@
+use crate::{Calc, Opt};
+use anyhow::anyhow;
+use rush_core::{ArithmeticError, FunctionSet};
+use serde_json::{Number, Value};
+use std::sync::Arc;
+use wd_tools::{PFErr, PFOk};
+
+/// 算术运算除以0或者溢出时的处理方式
+/// Error：返回ArithmeticError，flow中会带上出错的规则
+/// Null：结果为null，继续参与算术运算和比较时结果仍然是null
+/// Saturate：溢出时取对应方向的边界值，除以0仍然报错
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ArithmeticPolicy {
+    #[default]
+    Error,
+    Null,
+    Saturate,
+}
+
+pub(crate) fn operator_expr(opt: &Opt, args: &[Calc]) -> String {
+    Calc::Operator(opt.clone(), args.to_vec()).to_string()
+}
+
+// 整数运算，除以0和溢出时返回ArithmeticError
+pub(crate) fn checked_i64(opt: &Opt, i1: i64, i2: i64, args: &[Calc]) -> anyhow::Result<Value> {
+    if matches!(opt, Opt::DIV | Opt::REM) && i2 == 0 {
+        return Err(ArithmeticError::division_by_zero(operator_expr(opt, args)).into());
+    }
+    let (checked, saturated) = match opt {
+        Opt::ADD => (i1.checked_add(i2), i1.saturating_add(i2)),
+        Opt::SUB => (i1.checked_sub(i2), i1.saturating_sub(i2)),
+        Opt::MUL => (i1.checked_mul(i2), i1.saturating_mul(i2)),
+        Opt::DIV => (i1.checked_div(i2), i1.saturating_div(i2)),
+        // i64::MIN % -1 的结果是0，不算溢出
+        Opt::REM => return Value::from(i1.wrapping_rem(i2)).ok(),
+        _ => return anyhow!("operator[{:?}] can not support args:[{:?}]", opt, args).err(),
+    };
+    match checked {
+        Some(n) => Value::from(n).ok(),
+        None => {
+            Err(ArithmeticError::overflow(operator_expr(opt, args), Value::from(saturated)).into())
+        }
+    }
+}
+
+// 浮点数运算，结果不是有限数时溢出
+pub(crate) fn checked_f64(opt: &Opt, f1: f64, f2: f64, args: &[Calc]) -> anyhow::Result<Value> {
+    if matches!(opt, Opt::DIV | Opt::REM) && f2 == 0.0 {
+        return Err(ArithmeticError::division_by_zero(operator_expr(opt, args)).into());
+    }
+    let f = match opt {
+        Opt::ADD => f1 + f2,
+        Opt::SUB => f1 - f2,
+        Opt::MUL => f1 * f2,
+        Opt::DIV => f1 / f2,
+        Opt::REM => f1 % f2,
+        _ => return anyhow!("operator[{:?}] can not support args:[{:?}]", opt, args).err(),
+    };
+    match Number::from_f64(f) {
+        Some(n) => Value::Number(n).ok(),
+        None => {
+            let saturated = if f < 0.0 { f64::MIN } else { f64::MAX };
+            Err(ArithmeticError::overflow(operator_expr(opt, args), Value::from(saturated)).into())
+        }
+    }
+}
+
+// 移位数不在0..64之间或者左移丢失有效位时溢出
+pub(crate) fn checked_shift(opt: &Opt, i1: i64, i2: i64, args: &[Calc]) -> anyhow::Result<Value> {
+    let n = u32::try_from(i2).ok().filter(|x| *x < 64);
+    let v = match (opt, n) {
+        (Opt::SHL, Some(n)) if (i1 << n) >> n == i1 => i1 << n,
+        (Opt::SHR, Some(n)) => i1 >> n,
+        _ => {
+            let saturated = match opt {
+                Opt::SHR if i1 < 0 => -1,
+                Opt::SHR => 0,
+                _ if i1 < 0 => i64::MIN,
+                _ if i1 > 0 => i64::MAX,
+                _ => 0,
+            };
+            return Err(ArithmeticError::overflow(
+                operator_expr(opt, args),
+                Value::from(saturated),
+            )
+            .into());
+        }
+    };
+    Value::from(v).ok()
+}
+
+// -i64::MIN 溢出
+pub(crate) fn checked_neg(n: &Number, args: &[Calc]) -> anyhow::Result<Value> {
+    if let Some(i) = n.as_i64() {
+        if let Some(i) = i.checked_neg() {
+            return Value::from(i).ok();
+        }
+    } else if let Some(i) = n.as_u64() {
+        if let Some(i) = 0i64.checked_sub_unsigned(i) {
+            return Value::from(i).ok();
+        }
+    } else if let Some(f) = n.as_f64() {
+        return Value::from(-f).ok();
+    }
+    let saturated = if n.as_u64().is_some() {
+        i64::MIN
+    } else {
+        i64::MAX
+    };
+    Err(ArithmeticError::overflow(operator_expr(&Opt::SUB, args), Value::from(saturated)).into())
+}
+
+impl Calc {
+    /// 按算术策略转换表达式，Null和Saturate时算术运算符包装为Checked
+    pub fn arithmetic_policy(self, policy: ArithmeticPolicy) -> Self {
+        match policy {
+            ArithmeticPolicy::Error => self,
+            _ => self.checked(policy),
+        }
+    }
+    // Null策略下比较也包装，null参与比较时结果为null
+    fn checked(self, policy: ArithmeticPolicy) -> Self {
+        let calc = self.checked_children(policy);
+        if calc.is_arithmetic() || (policy == ArithmeticPolicy::Null && calc.is_comparison()) {
+            Calc::Checked(policy, Box::new(calc))
+        } else {
+            calc
+        }
+    }
+    // NullSafe和Decimal包装的运算符只转换参数，Checked放在最外层
+    fn checked_children(self, policy: ArithmeticPolicy) -> Self {
+        let all = |list: Vec<Calc>| list.into_iter().map(|x| x.checked(policy)).collect();
+        match self {
+            Calc::Operator(opt, args) => Calc::Operator(opt, all(args)),
+            Calc::NullSafe(calc) => Calc::NullSafe(Box::new(calc.checked_children(policy))),
+            Calc::Decimal(calc) => Calc::Decimal(Box::new(calc.checked_children(policy))),
+            Calc::Array(list) => Calc::Array(all(list)),
+            Calc::Function(name, args) => Calc::Function(name, all(args)),
+            Calc::Lambda(param, body) => Calc::Lambda(param, Box::new(body.checked(policy))),
+            Calc::If(cond, a, b) => Calc::If(
+                Box::new(cond.checked(policy)),
+                Box::new(a.checked(policy)),
+                Box::new(b.checked(policy)),
+            ),
+            Calc::Case(branches, default) => Calc::Case(
+                branches
+                    .into_iter()
+                    .map(|(c, v)| (c.checked(policy), v.checked(policy)))
+                    .collect(),
+                Box::new(default.checked(policy)),
+            ),
+            _ => self,
+        }
+    }
+    fn is_arithmetic(&self) -> bool {
+        match self {
+            Calc::Operator(opt, _) => matches!(
+                opt,
+                Opt::ADD | Opt::SUB | Opt::MUL | Opt::DIV | Opt::REM | Opt::SHL | Opt::SHR
+            ),
+            Calc::NullSafe(calc) | Calc::Decimal(calc) => calc.is_arithmetic(),
+            _ => false,
+        }
+    }
+    fn is_comparison(&self) -> bool {
+        match self {
+            Calc::Operator(opt, _) => matches!(opt, Opt::GT | Opt::GE | Opt::LT | Opt::LE),
+            Calc::NullSafe(calc) | Calc::Decimal(calc) => calc.is_comparison(),
+            _ => false,
+        }
+    }
+    fn operator_args(&self) -> Option<&[Calc]> {
+        match self {
+            Calc::Operator(_, args) => Some(args),
+            Calc::NullSafe(calc) | Calc::Decimal(calc) => calc.operator_args(),
+            _ => None,
+        }
+    }
+    fn with_operator_args(&self, args: Vec<Calc>) -> Calc {
+        match self {
+            Calc::Operator(opt, _) => Calc::Operator(opt.clone(), args),
+            Calc::NullSafe(calc) => Calc::NullSafe(Box::new(calc.with_operator_args(args))),
+            Calc::Decimal(calc) => Calc::Decimal(Box::new(calc.with_operator_args(args))),
+            _ => self.clone(),
+        }
+    }
+    // 参数为null时结果为null，避免出错得到的null被外层运算当作0
+    fn null_value(&self, fs: &Arc<dyn FunctionSet>, input: &Value) -> anyhow::Result<Value> {
+        let args = match self.operator_args() {
+            Some(args) => args,
+            None => return self.value(fs, input),
+        };
+        let mut consts = vec![];
+        for i in args.iter() {
+            let v = i.value(fs, input)?;
+            if v.is_null() && *i != Calc::NULL {
+                return Value::Null.ok();
+            }
+            consts.push(i.evaluated(v));
+        }
+        self.with_operator_args(consts).value(fs, input)
+    }
+    pub(crate) fn checked_value(
+        &self,
+        policy: ArithmeticPolicy,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        let res = match policy {
+            ArithmeticPolicy::Null => self.null_value(fs, input),
+            _ => self.value(fs, input),
+        };
+        let e = match res {
+            Err(e) => e,
+            res => return res,
+        };
+        match (policy, e.downcast_ref::<ArithmeticError>()) {
+            (ArithmeticPolicy::Null, Some(_)) => Value::Null.ok(),
+            (ArithmeticPolicy::Saturate, Some(ae)) if ae.saturated.is_some() => {
+                ae.saturated.clone().unwrap_or_default().ok()
+            }
+            _ => e.err(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ArithmeticPolicy, CalcBuilder, NumberMode};
+    use rush_core::{ArithmeticError, ArithmeticErrorKind, Function, FunctionSet};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn eval(expr: &str, policy: ArithmeticPolicy, input: &Value) -> anyhow::Result<Value> {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let calc = CalcBuilder::new(expr).arithmetic_policy(policy).build()?;
+        calc.value(&fs, input)
+    }
+
+    //cargo test --color=always --lib checked::test::test_checked_arithmetic --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_checked_arithmetic() {
+        let input = json!({"x":10,"y":0,"f":1.5,"big":1e308,"max":i64::MAX,"min":i64::MIN});
+        let kind = |expr: &str| {
+            let e = eval(expr, ArithmeticPolicy::Error, &input).unwrap_err();
+            e.downcast_ref::<ArithmeticError>().map(|x| x.kind)
+        };
+        assert_eq!(kind("x / y"), Some(ArithmeticErrorKind::DivisionByZero));
+        assert_eq!(kind("x % y"), Some(ArithmeticErrorKind::DivisionByZero));
+        assert_eq!(kind("f / 0"), Some(ArithmeticErrorKind::DivisionByZero));
+        assert_eq!(kind("max + 1"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("min - 1"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("max * 2"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("min / -1"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("-min"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("x << 64"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("x << -1"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("max << 1"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("big * 10"), Some(ArithmeticErrorKind::Overflow));
+        assert_eq!(kind("sum([max, 1])"), Some(ArithmeticErrorKind::Overflow));
+
+        let error = ArithmeticPolicy::Error;
+        assert_eq!(eval("min % -1", error, &input).unwrap(), json!(0));
+        assert_eq!(eval("x << 2 >> 1", error, &input).unwrap(), json!(20));
+        assert_eq!(eval("x / 4", error, &input).unwrap(), json!(2));
+
+        let null = ArithmeticPolicy::Null;
+        assert_eq!(eval("x / y", null, &input).unwrap(), Value::Null);
+        assert_eq!(eval("(x / y) ?? -1", null, &input).unwrap(), json!(-1));
+        assert_eq!(eval("max + 1", null, &input).unwrap(), Value::Null);
+        // 出错得到的null继续参与运算时仍然是null，不会当作0
+        assert_eq!(eval("x / y + 1", null, &input).unwrap(), Value::Null);
+        assert_eq!(eval("(x / y) * 5", null, &input).unwrap(), Value::Null);
+        assert_eq!(eval("-(x / y)", null, &input).unwrap(), Value::Null);
+        assert_eq!(eval("(x / y) > 0", null, &input).unwrap(), Value::Null);
+        assert_eq!(eval("x / 5 + 1", null, &input).unwrap(), json!(3));
+        assert_eq!(eval("x > 5", null, &input).unwrap(), json!(true));
+
+        let saturate = ArithmeticPolicy::Saturate;
+        assert_eq!(eval("max + 1", saturate, &input).unwrap(), json!(i64::MAX));
+        assert_eq!(eval("min * 2", saturate, &input).unwrap(), json!(i64::MIN));
+        assert_eq!(
+            eval("(max + 1) - 1", saturate, &input).unwrap(),
+            json!(i64::MAX - 1)
+        );
+        assert_eq!(
+            eval("-big * 10", saturate, &input).unwrap(),
+            json!(f64::MIN)
+        );
+        assert!(eval("x / y", saturate, &input).is_err());
+
+        // 十进制模式同样检查除以0
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let calc = CalcBuilder::new("f / (x - 10)")
+            .number_mode(NumberMode::Decimal)
+            .arithmetic_policy(null)
+            .build()
+            .unwrap();
+        assert_eq!(calc.value(&fs, &input).unwrap(), Value::Null);
+    }
+}
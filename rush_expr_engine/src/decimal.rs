@@ -0,0 +1,606 @@
+use crate::{operator_expr, Calc, Opt};
+use anyhow::anyhow;
+use rush_core::{ArithmeticError, FunctionSet};
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use wd_tools::{PFErr, PFOk};
+
+// 小数位数上限，超出时按银行家舍入
+const MAX_SCALE: u32 = 28;
+// 除不尽时保留的小数位数
+const DIV_SCALE: u32 = 18;
+
+/// 数字的计算方式
+/// Float：整数和浮点数混合计算，与旧版本一致
+/// Decimal：算术和比较运算按十进制精确计算，0.1 + 0.2 == 0.3
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum NumberMode {
+    #[default]
+    Float,
+    Decimal,
+}
+
+/// 十进制数，值为 mantissa / 10^scale，mantissa是i128
+/// 最多38位有效数字，计算结果超出时报溢出错误；最多28位小数，更多的小数位按银行家舍入
+/// 没有开启arbitrary-precision时JSON数字是f64，转为JSON时只保留约17位有效数字，
+/// 小数部分按最接近的f64舍入，整数部分超出f64精度（大于2^53）时报错而不是丢失整数位
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(n: u32) -> anyhow::Result<i128> {
+    match 10i128.checked_pow(n) {
+        Some(p) => p.ok(),
+        None => anyhow!("decimal overflow").err(),
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    pub fn new(mantissa: i128, scale: u32) -> anyhow::Result<Self> {
+        // 保证取反不会溢出
+        if mantissa == i128::MIN {
+            return anyhow!("decimal overflow").err();
+        }
+        let d = Decimal { mantissa, scale };
+        if scale > MAX_SCALE {
+            return d.round(MAX_SCALE, RoundingMode::HalfEven);
+        }
+        d.normalize().ok()
+    }
+    /// serde_json的数字按文本转换，0.1 转换为 0.1 而不是最接近的二进制浮点数
+    pub fn from_number(n: &Number) -> anyhow::Result<Self> {
+        n.to_string().parse()
+    }
+    /// 转为JSON数字，整数不带小数点，整数部分不能被JSON数字精确表示时报错
+    pub fn to_value(&self) -> anyhow::Result<Value> {
+        let n = self.json_number()?;
+        #[cfg(not(feature = "arbitrary-precision"))]
+        {
+            let int = |d: &Decimal| d.round(0, RoundingMode::Down);
+            if int(&Decimal::from_number(&n)?)? != int(self)? {
+                return anyhow!(
+                    "decimal[{self}] exceeds the precision of a json number, enable feature arbitrary-precision"
+                )
+                .err();
+            }
+        }
+        Value::Number(n).ok()
+    }
+    // 没有开启arbitrary-precision时超过f64精度的部分会丢失
+    fn json_number(&self) -> anyhow::Result<Number> {
+        Number::from_str(&self.to_string())
+            .map_err(|e| anyhow!("decimal[{self}] to json number failed:{e}"))
+    }
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+    // 去掉小数末尾的0，保证相同的值只有一种表示
+    fn normalize(mut self) -> Self {
+        if self.mantissa == 0 {
+            return Decimal::ZERO;
+        }
+        while self.scale > 0 && self.mantissa % 10 == 0 {
+            self.mantissa /= 10;
+            self.scale -= 1;
+        }
+        self
+    }
+    // 放大到指定的小数位数
+    fn rescale(&self, scale: u32) -> anyhow::Result<i128> {
+        match self.mantissa.checked_mul(pow10(scale - self.scale)?) {
+            Some(m) => m.ok(),
+            None => anyhow!("decimal overflow").err(),
+        }
+    }
+    pub fn neg(&self) -> Self {
+        Decimal {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+    pub fn add(&self, other: &Decimal) -> anyhow::Result<Self> {
+        let scale = self.scale.max(other.scale);
+        match self.rescale(scale)?.checked_add(other.rescale(scale)?) {
+            Some(m) => Decimal::new(m, scale),
+            None => anyhow!("decimal overflow").err(),
+        }
+    }
+    pub fn sub(&self, other: &Decimal) -> anyhow::Result<Self> {
+        self.add(&other.neg())
+    }
+    pub fn mul(&self, other: &Decimal) -> anyhow::Result<Self> {
+        match self.mantissa.checked_mul(other.mantissa) {
+            Some(m) => Decimal::new(m, self.scale + other.scale),
+            None => anyhow!("decimal overflow").err(),
+        }
+    }
+    /// 除不尽时保留18位小数，银行家舍入
+    pub fn div(&self, other: &Decimal) -> anyhow::Result<Self> {
+        if other.is_zero() {
+            return anyhow!("decimal division by zero").err();
+        }
+        // 被除数放大后溢出时逐步减少保留的小数位数
+        let min = self.scale.saturating_sub(other.scale);
+        let mut scale = DIV_SCALE.max(self.scale);
+        let num = loop {
+            let exp = scale + other.scale - self.scale;
+            if let Some(n) = pow10(exp)?.checked_mul(self.mantissa) {
+                break n;
+            }
+            if scale == min {
+                return anyhow!("decimal overflow").err();
+            }
+            scale -= 1;
+        };
+        let (q, r) = (num / other.mantissa, num % other.mantissa);
+        let half = r
+            .unsigned_abs()
+            .cmp(&(other.mantissa.unsigned_abs() - r.unsigned_abs()));
+        let up = half.is_gt() || (half.is_eq() && q % 2 != 0);
+        let sign = if (num < 0) == (other.mantissa < 0) {
+            1
+        } else {
+            -1
+        };
+        let q = if up && r != 0 { q + sign } else { q };
+        Decimal::new(q, scale)
+    }
+    /// 余数的符号与被除数一致
+    pub fn rem(&self, other: &Decimal) -> anyhow::Result<Self> {
+        if other.is_zero() {
+            return anyhow!("decimal division by zero").err();
+        }
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescale(scale)? % other.rescale(scale)?, scale)
+    }
+    /// 保留digits位小数
+    pub fn round(&self, digits: u32, mode: RoundingMode) -> anyhow::Result<Self> {
+        if self.scale <= digits {
+            return (*self).ok();
+        }
+        let d = pow10(self.scale - digits)?;
+        let (q, r) = (self.mantissa / d, self.mantissa % d);
+        if r == 0 {
+            return Decimal::new(q, digits);
+        }
+        let half = r.unsigned_abs().cmp(&(d.unsigned_abs() - r.unsigned_abs()));
+        let away = match mode {
+            RoundingMode::Up => true,
+            RoundingMode::Down => false,
+            RoundingMode::Ceiling => r > 0,
+            RoundingMode::Floor => r < 0,
+            RoundingMode::HalfUp => half.is_ge(),
+            RoundingMode::HalfDown => half.is_gt(),
+            RoundingMode::HalfEven => half.is_gt() || (half.is_eq() && q % 2 != 0),
+        };
+        let q = if away { q + r.signum() } else { q };
+        Decimal::new(q, digits)
+    }
+}
+
+impl Ord for Decimal {
+    // 整数部分和小数部分分别比较，避免对齐小数位时溢出
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (p1, p2) = (10i128.pow(self.scale), 10i128.pow(other.scale));
+        let (i1, i2) = (self.mantissa / p1, other.mantissa / p2);
+        if i1 != i2 {
+            return i1.cmp(&i2);
+        }
+        let scale = self.scale.max(other.scale);
+        let f1 = self.mantissa % p1 * 10i128.pow(scale - self.scale);
+        let f2 = other.mantissa % p2 * 10i128.pow(scale - other.scale);
+        f1.cmp(&f2)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = anyhow::Error;
+
+    /// 支持 -12.345 和 1.5e-7 形式
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || anyhow!("[{s}] is not a decimal");
+        let (neg, body) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (num, exp) = match body.find(['e', 'E']) {
+            Some(i) => (&body[..i], body[i + 1..].parse::<i32>().map_err(|_| err())?),
+            None => (body, 0),
+        };
+        let (int, frac) = num.split_once('.').unwrap_or((num, ""));
+        let all_digit = |x: &str| x.bytes().all(|c| c.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !all_digit(int) || !all_digit(frac) {
+            return err().err();
+        }
+        let digits = format!("{int}{frac}");
+        let digits = digits.trim_start_matches('0');
+        let scale = frac.len() as i64 - exp as i64;
+        // 超出的小数位按银行家舍入
+        let (keep, scale, up) = if scale > MAX_SCALE as i64 {
+            let cut = (scale - MAX_SCALE as i64) as usize;
+            let keep = &digits[..digits.len().saturating_sub(cut)];
+            let dropped = &digits[keep.len()..];
+            let first = match cut > digits.len() {
+                true => b'0',
+                false => dropped.as_bytes()[0],
+            };
+            let tie = first == b'5' && dropped[1..].bytes().all(|c| c == b'0');
+            let odd = keep.bytes().last().map(|c| (c - b'0') % 2 == 1) == Some(true);
+            (
+                keep,
+                MAX_SCALE as i64,
+                first > b'5' || (first == b'5' && (!tie || odd)),
+            )
+        } else {
+            (digits, scale, false)
+        };
+        let mut mantissa = 0i128;
+        for c in keep.bytes() {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add((c - b'0') as i128))
+                .ok_or_else(|| anyhow!("decimal[{s}] overflow"))?;
+        }
+        if up {
+            mantissa += 1;
+        }
+        if scale < 0 && mantissa != 0 {
+            mantissa = u32::try_from(-scale)
+                .ok()
+                .and_then(|x| 10i128.checked_pow(x))
+                .and_then(|p| mantissa.checked_mul(p))
+                .ok_or_else(|| anyhow!("decimal[{s}] overflow"))?;
+        }
+        if neg {
+            mantissa = -mantissa;
+        }
+        Decimal::new(mantissa, scale.max(0) as u32)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{sign}{int}.{frac}")
+    }
+}
+
+/// 内置的舍入函数：round_half_up(x, 2) 保留两位小数，省略位数时保留到整数
+/// 参数可以是数字或者数字字符串，按十进制计算；同名函数已注册时，调用注册的函数
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RoundingMode {
+    /// 四舍五入
+    HalfUp,
+    /// 五舍六入
+    HalfDown,
+    /// 银行家舍入，四舍六入五成双
+    HalfEven,
+    /// 远离0
+    Up,
+    /// 截断，趋向0
+    Down,
+    /// 向正无穷
+    Ceiling,
+    /// 向负无穷
+    Floor,
+}
+
+impl RoundingMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mode = match name {
+            "round_half_up" => RoundingMode::HalfUp,
+            "round_half_down" => RoundingMode::HalfDown,
+            "round_half_even" => RoundingMode::HalfEven,
+            "round_up" => RoundingMode::Up,
+            "round_down" => RoundingMode::Down,
+            "round_ceiling" => RoundingMode::Ceiling,
+            "round_floor" => RoundingMode::Floor,
+            _ => return None,
+        };
+        Some(mode)
+    }
+    pub fn call(
+        &self,
+        args: &[Calc],
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        if args.is_empty() || args.len() > 2 {
+            return anyhow!("function[{:?}] want one or two args", self).err();
+        }
+        let x = match args[0].value(fs, input)? {
+            Value::Null => return Value::Null.ok(),
+            Value::Number(n) => Decimal::from_number(&n)?,
+            Value::String(s) => s.trim().parse()?,
+            v => return anyhow!("function[{:?}] want number, found {v}", self).err(),
+        };
+        let digits = match args.get(1).map(|x| x.value(fs, input)).transpose()? {
+            None => 0,
+            Some(Value::Number(n)) if n.as_u64().map(|x| x <= MAX_SCALE as u64) == Some(true) => {
+                n.as_u64().unwrap_or_default() as u32
+            }
+            Some(v) => {
+                return anyhow!(
+                    "function[{:?}] digits must in 0..={MAX_SCALE}, found {v}",
+                    self
+                )
+                .err()
+            }
+        };
+        x.round(digits, *self)?.to_value()
+    }
+}
+
+impl Calc {
+    /// 按数字模式转换表达式，Decimal时算术和比较运算符包装为Decimal
+    pub fn number_mode(self, mode: NumberMode) -> Self {
+        match mode {
+            NumberMode::Float => self,
+            NumberMode::Decimal => self.decimal(),
+        }
+    }
+    fn decimal(self) -> Self {
+        let all = |list: Vec<Calc>| list.into_iter().map(Calc::decimal).collect();
+        let exact = |opt: &Opt| {
+            matches!(
+                opt,
+                Opt::ADD
+                    | Opt::SUB
+                    | Opt::MUL
+                    | Opt::DIV
+                    | Opt::REM
+                    | Opt::GT
+                    | Opt::GE
+                    | Opt::LT
+                    | Opt::LE
+                    | Opt::EQ
+                    | Opt::NQ
+            )
+        };
+        match self {
+            Calc::Operator(opt, args) if exact(&opt) => {
+                Calc::Decimal(Box::new(Calc::Operator(opt, all(args))))
+            }
+            Calc::Operator(opt, args) => Calc::Operator(opt, all(args)),
+            Calc::NullSafe(calc) => match *calc {
+                Calc::Operator(opt, args) if exact(&opt) => Calc::Decimal(Box::new(
+                    Calc::NullSafe(Box::new(Calc::Operator(opt, all(args)))),
+                )),
+                Calc::Operator(opt, args) => {
+                    Calc::NullSafe(Box::new(Calc::Operator(opt, all(args))))
+                }
+                calc => Calc::NullSafe(Box::new(calc)),
+            },
+            Calc::Checked(policy, calc) => Calc::Checked(policy, Box::new(calc.decimal())),
+            Calc::Array(list) => Calc::Array(all(list)),
+            Calc::Function(name, args) => Calc::Function(name, all(args)),
+            Calc::Lambda(param, body) => Calc::Lambda(param, Box::new(body.decimal())),
+            Calc::If(cond, a, b) => Calc::If(
+                Box::new(cond.decimal()),
+                Box::new(a.decimal()),
+                Box::new(b.decimal()),
+            ),
+            Calc::Case(branches, default) => Calc::Case(
+                branches
+                    .into_iter()
+                    .map(|(c, v)| (c.decimal(), v.decimal()))
+                    .collect(),
+                Box::new(default.decimal()),
+            ),
+            _ => self,
+        }
+    }
+    // 参数都是数字时按十进制计算，否则（字符串拼接、时间比较等）按原来的方式计算
+    pub(crate) fn decimal_value(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        let (null_safe, opt, args) = match self {
+            Calc::Operator(opt, args) => (false, opt, args),
+            Calc::NullSafe(calc) => match calc.as_ref() {
+                Calc::Operator(opt, args) => (true, opt, args),
+                _ => return self.value(fs, input),
+            },
+            _ => return self.value(fs, input),
+        };
+        let equal = matches!(opt, Opt::EQ | Opt::NQ);
+        let mut values = vec![];
+        let mut numbers = vec![];
+        for i in args.iter() {
+            let v = i.value(fs, input)?;
+            // 与浮点模式一致：三值逻辑下null参与运算为null，否则null按0计算
+            match &v {
+                Value::Number(n) => numbers.push(Decimal::from_number(n)?),
+                Value::Null if equal => {}
+                Value::Null if null_safe && *i != Calc::NULL => return Value::Null.ok(),
+                Value::Null => numbers.push(Decimal::ZERO),
+                _ => {}
+            }
+            values.push(v);
+        }
+        if numbers.len() == args.len() {
+            return Self::decimal_operator(opt, &numbers, args);
+        }
+        let consts = args
+            .iter()
+            .zip(values)
+            .map(|(c, v)| c.evaluated(v))
+            .collect();
+        match null_safe {
+            true => Self::null_safe_operator(opt, &consts, fs, input),
+            false => Self::operator(opt, &consts, fs, input),
+        }
+    }
+    fn decimal_operator(opt: &Opt, args: &[Decimal], calcs: &[Calc]) -> anyhow::Result<Value> {
+        let (a, b) = match args {
+            [a] if *opt == Opt::SUB => return a.neg().to_value(),
+            [a, b] => (a, b),
+            _ => return anyhow!("operator[{:?}] args count must hava two", opt).err(),
+        };
+        if matches!(opt, Opt::DIV | Opt::REM) && b.is_zero() {
+            return Err(ArithmeticError::division_by_zero(operator_expr(opt, calcs)).into());
+        }
+        let n = match opt {
+            Opt::ADD => a.add(b),
+            Opt::SUB => a.sub(b),
+            Opt::MUL => a.mul(b),
+            Opt::DIV => a.div(b),
+            Opt::REM => a.rem(b),
+            Opt::GT => return Value::Bool(a > b).ok(),
+            Opt::GE => return Value::Bool(a >= b).ok(),
+            Opt::LT => return Value::Bool(a < b).ok(),
+            Opt::LE => return Value::Bool(a <= b).ok(),
+            Opt::EQ => return Value::Bool(a == b).ok(),
+            Opt::NQ => return Value::Bool(a != b).ok(),
+            _ => return anyhow!("operator[{:?}] not support decimal", opt).err(),
+        };
+        // 超出38位有效数字时溢出，按浮点数估算溢出的方向
+        let n = match n {
+            Ok(n) => n,
+            Err(_) => {
+                let f = |d: &Decimal| d.to_string().parse::<f64>().unwrap_or_default();
+                let negative = match opt {
+                    Opt::ADD => f(a) + f(b) < 0.0,
+                    Opt::SUB => f(a) - f(b) < 0.0,
+                    _ => (f(a) < 0.0) != (f(b) < 0.0),
+                };
+                let bound = Decimal::new(i128::MAX, 0)?;
+                let bound = if negative { bound.neg() } else { bound };
+                // 饱和值只是边界的近似，允许丢失精度
+                let bound = Value::Number(bound.json_number()?);
+                let e = ArithmeticError::overflow(operator_expr(opt, calcs), bound);
+                return Err(e.into());
+            }
+        };
+        n.to_value()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CalcBuilder, Decimal, NullMode, NumberMode, RoundingMode};
+    use rush_core::{Function, FunctionSet};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn eval(expr: &str, input: &Value) -> anyhow::Result<Value> {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let calc = CalcBuilder::new(expr)
+            .number_mode(NumberMode::Decimal)
+            .build()?;
+        calc.value(&fs, input)
+    }
+
+    //cargo test --color=always --lib decimal::test::test_decimal --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_decimal() {
+        let d = |s: &str| s.parse::<Decimal>().unwrap();
+        assert_eq!(d("0.1").add(&d("0.2")).unwrap(), d("0.3"));
+        assert_eq!(d("1.50").to_string(), "1.5");
+        assert_eq!(d("-0.05").to_string(), "-0.05");
+        assert_eq!(d("1.5e-7").to_string(), "0.00000015");
+        assert_eq!(d("12e3").to_string(), "12000");
+        assert_eq!(
+            d("10").div(&d("3")).unwrap().to_string(),
+            "3.333333333333333333"
+        );
+        assert_eq!(
+            d("-2").div(&d("3")).unwrap().to_string(),
+            "-0.666666666666666667"
+        );
+        assert_eq!(d("7.5").rem(&d("2")).unwrap(), d("1.5"));
+        assert!(d("1").div(&d("0")).is_err());
+        assert!(d("-1.5") < d("-1.25") && d("0.3") > d("0.29999"));
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("1e40".parse::<Decimal>().is_err());
+
+        let round = |s: &str, mode| d(s).round(1, mode).unwrap().to_string();
+        assert_eq!(round("2.25", RoundingMode::HalfUp), "2.3");
+        assert_eq!(round("2.25", RoundingMode::HalfDown), "2.2");
+        assert_eq!(round("2.25", RoundingMode::HalfEven), "2.2");
+        assert_eq!(round("2.35", RoundingMode::HalfEven), "2.4");
+        assert_eq!(round("-2.25", RoundingMode::HalfUp), "-2.3");
+        assert_eq!(round("-2.21", RoundingMode::Up), "-2.3");
+        assert_eq!(round("-2.29", RoundingMode::Down), "-2.2");
+        assert_eq!(round("-2.21", RoundingMode::Floor), "-2.3");
+        assert_eq!(round("-2.29", RoundingMode::Ceiling), "-2.2");
+    }
+
+    //cargo test --color=always --lib decimal::test::test_number_mode --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_number_mode() {
+        let input = json!({"price":19.99,"qty":3,"rate":0.1,"name":"fee","n":null});
+        assert_eq!(eval("0.1 + 0.2 == 0.3", &input).unwrap(), json!(true));
+        assert_eq!(eval("price * qty", &input).unwrap(), json!(59.97));
+        assert_eq!(eval("price * rate", &input).unwrap(), json!(1.999));
+        assert_eq!(eval("1.5 * 2", &input).unwrap(), json!(3));
+        assert_eq!(eval("1 == 1.0", &input).unwrap(), json!(true));
+        assert_eq!(eval("-(price - 20)", &input).unwrap(), json!(0.01));
+        assert_eq!(eval("name + '1'", &input).unwrap(), json!("fee1"));
+        assert!(eval("name + 1", &input).is_err());
+        assert_eq!(eval("n + 1", &input).unwrap(), json!(1));
+        assert_eq!(
+            eval("round_half_up(price * rate, 2)", &input).unwrap(),
+            json!(2)
+        );
+        assert_eq!(
+            eval("round_half_up(2.675, 2)", &input).unwrap(),
+            json!(2.68)
+        );
+        assert_eq!(
+            eval("round_down(\"12.349\", 2)", &input).unwrap(),
+            json!(12.34)
+        );
+        assert!(eval("round_up(1.5, -1)", &input).is_err());
+        assert!(eval("price / (qty - 3)", &input).is_err());
+
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let calc = CalcBuilder::new("n + 1 > 0.3")
+            .null_mode(NullMode::ThreeValued)
+            .number_mode(NumberMode::Decimal)
+            .build()
+            .unwrap();
+        assert_eq!(calc.value(&fs, &input).unwrap(), Value::Null);
+
+        // 超过f64精度的数字只有开启arbitrary-precision时才能原样输出，否则报错
+        #[cfg(feature = "arbitrary-precision")]
+        {
+            let input: Value = r#"{"a":12345678901234567.89}"#.parse().unwrap();
+            let out = eval("a + 0.01", &input).unwrap();
+            assert_eq!(out.to_string(), "12345678901234567.9");
+        }
+        #[cfg(not(feature = "arbitrary-precision"))]
+        {
+            let err = eval("12345678901234567 + 0.01", &input).unwrap_err();
+            assert!(err.to_string().contains("precision"), "{err}");
+            // 小数部分按f64舍入
+            assert_eq!(eval("10 / 3", &input).unwrap(), json!(3.3333333333333335));
+        }
+    }
+}
@@ -0,0 +1,770 @@
+use crate::{Assign, Calc};
+use anyhow::anyhow;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::str::FromStr;
+use wd_tools::{PFErr, PFOk};
+
+const WILDCARD: &str = "-";
+const OUTPUT_PREFIX: &str = "=>";
+const PRIORITY_COLUMN: &str = "priority";
+const DIRECTIVE_HIT_POLICY: &str = "hit_policy";
+const DIRECTIVE_COMPLETE: &str = "complete";
+// 缺失分析最多报告的条数
+const MAX_GAPS: usize = 10;
+
+/// 命中策略
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum HitPolicy {
+    // 最多命中一行，构建时发现重叠的行会报错
+    #[default]
+    Unique,
+    // 按行的顺序，第一个命中的行生效
+    First,
+    // 按priority列从大到小，第一个命中的行生效
+    Priority,
+    // 所有命中的行都生效，输出收集为数组
+    Collect,
+}
+
+impl FromStr for HitPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hp = match s.trim().to_lowercase().as_str() {
+            "u" | "unique" => HitPolicy::Unique,
+            "f" | "first" => HitPolicy::First,
+            "p" | "priority" => HitPolicy::Priority,
+            "c" | "collect" => HitPolicy::Collect,
+            _ => return anyhow!("unknown hit policy[{s}]").err(),
+        };
+        hp.ok()
+    }
+}
+
+/// 决策表的一行，单元格为表达式文本
+#[derive(Debug, Clone, Default)]
+pub struct TableRow {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub priority: i32,
+}
+
+/// 决策表分析结果，行号从1开始
+#[derive(Debug, Clone, Default)]
+pub struct TableReport {
+    // 一定会同时命中的两行
+    pub overlaps: Vec<(usize, usize)>,
+    // 没有任何行命中的输入组合
+    pub gaps: Vec<String>,
+}
+
+/// 决策表，每一行编译为一条规则
+/// 输入单元格：`-`或空表示任意值，`[18..30)`表示区间，`> 18`等比较，其它表示相等
+/// 输出单元格：表达式，`-`或空表示不赋值
+#[derive(Debug, Clone, Default)]
+pub struct DecisionTable {
+    pub name: String,
+    pub hit_policy: HitPolicy,
+    // 为true时构建会检查是否有缺失的输入组合
+    pub complete: bool,
+    // 输入列的表达式，例如 age, user.level
+    pub inputs: Vec<String>,
+    // 输出列的字段路径，例如 member.grade
+    pub outputs: Vec<String>,
+    pub rows: Vec<TableRow>,
+}
+
+impl DecisionTable {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+    pub fn hit_policy(mut self, hit_policy: HitPolicy) -> Self {
+        self.hit_policy = hit_policy;
+        self
+    }
+    pub fn complete(mut self, complete: bool) -> Self {
+        self.complete = complete;
+        self
+    }
+
+    /// CSV格式，第一行为表头，=>开始的列为输出，priority列为优先级
+    /// 表头前 # 开始的行为注释，可以包含指令：# hit_policy: first，# complete: true
+    pub fn from_csv<S: Into<String>>(name: S, csv: &str) -> anyhow::Result<Self> {
+        let mut table = DecisionTable::new(name);
+        let mut header: Option<Vec<Column>> = None;
+        for (n, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if header.is_none() {
+                    table.directive(comment)?;
+                }
+                continue;
+            }
+            let cells = split_csv_line(line);
+            let columns = match header {
+                Some(ref c) => c,
+                None => {
+                    header = Some(table.header(cells)?);
+                    continue;
+                }
+            };
+            if cells.len() != columns.len() {
+                return anyhow!(
+                    "decision table[{}] line {} has {} cells, header has {}",
+                    table.name,
+                    n + 1,
+                    cells.len(),
+                    columns.len()
+                )
+                .err();
+            }
+            let mut row = TableRow::default();
+            for (c, cell) in columns.iter().zip(cells) {
+                match c {
+                    Column::Input => row.inputs.push(cell),
+                    Column::Output => row.outputs.push(cell),
+                    Column::Priority => {
+                        row.priority = cell.parse().map_err(|e| {
+                            anyhow!("line {} priority[{}] parse failed:{}", n + 1, cell, e)
+                        })?
+                    }
+                }
+            }
+            table.rows.push(row);
+        }
+        if header.is_none() {
+            return anyhow!("decision table[{}] has no header", table.name).err();
+        }
+        table.ok()
+    }
+    fn directive(&mut self, comment: &str) -> anyhow::Result<()> {
+        if let Some((k, v)) = comment.split_once(':') {
+            match k.trim().to_lowercase().as_str() {
+                DIRECTIVE_HIT_POLICY => self.hit_policy = v.parse()?,
+                DIRECTIVE_COMPLETE => self.complete = v.trim().eq_ignore_ascii_case("true"),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    fn header(&mut self, cells: Vec<String>) -> anyhow::Result<Vec<Column>> {
+        let mut columns = vec![];
+        for cell in cells {
+            if let Some(out) = cell.strip_prefix(OUTPUT_PREFIX) {
+                self.outputs.push(out.trim().to_string());
+                columns.push(Column::Output);
+            } else if cell.eq_ignore_ascii_case(PRIORITY_COLUMN) {
+                columns.push(Column::Priority);
+            } else if cell.is_empty() {
+                return anyhow!("decision table[{}] header has empty column", self.name).err();
+            } else {
+                self.inputs.push(cell);
+                columns.push(Column::Input);
+            }
+        }
+        if self.outputs.is_empty() {
+            return anyhow!(
+                "decision table[{}] header has no output column(start with '=>')",
+                self.name
+            )
+            .err();
+        }
+        Ok(columns)
+    }
+
+    /// JSON格式：
+    /// {"name":"","hit_policy":"first","complete":false,"inputs":[],"outputs":[],
+    ///  "rows":[{"when":[],"then":[],"priority":0}]}
+    pub fn from_json(value: &Value) -> anyhow::Result<Self> {
+        let str_list = |key: &str| -> anyhow::Result<Vec<String>> {
+            match value.get(key) {
+                Some(Value::Array(list)) => Ok(list.iter().map(cell_text).collect()),
+                _ => anyhow!("decision table json want array field[{key}]").err(),
+            }
+        };
+        let mut table =
+            DecisionTable::new(value.get("name").and_then(|x| x.as_str()).unwrap_or(""));
+        if let Some(hp) = value.get(DIRECTIVE_HIT_POLICY).and_then(|x| x.as_str()) {
+            table.hit_policy = hp.parse()?;
+        }
+        table.complete = value
+            .get(DIRECTIVE_COMPLETE)
+            .and_then(|x| x.as_bool())
+            .unwrap_or(false);
+        table.inputs = str_list("inputs")?;
+        table.outputs = str_list("outputs")?;
+        let rows = match value.get("rows") {
+            Some(Value::Array(rows)) => rows,
+            _ => return anyhow!("decision table json want array field[rows]").err(),
+        };
+        for (i, r) in rows.iter().enumerate() {
+            let cells = |key: &str| -> Vec<String> {
+                match r.get(key) {
+                    Some(Value::Array(list)) => list.iter().map(cell_text).collect(),
+                    _ => vec![],
+                }
+            };
+            let row = TableRow {
+                inputs: cells("when"),
+                outputs: cells("then"),
+                priority: r.get(PRIORITY_COLUMN).and_then(|x| x.as_i64()).unwrap_or(0) as i32,
+            };
+            if row.inputs.len() != table.inputs.len() || row.outputs.len() != table.outputs.len() {
+                return anyhow!(
+                    "decision table[{}] row {} cells do not match inputs/outputs",
+                    table.name,
+                    i + 1
+                )
+                .err();
+            }
+            table.rows.push(row);
+        }
+        table.ok()
+    }
+
+    /// 分析重叠和缺失的行，无法静态判断的单元格（非字面量）不参与分析
+    pub fn analyze(&self) -> TableReport {
+        let conds = self.cell_conds();
+        let mut report = TableReport::default();
+        for i in 0..conds.len() {
+            for j in i + 1..conds.len() {
+                let overlap = conds[i]
+                    .iter()
+                    .zip(conds[j].iter())
+                    .all(|(a, b)| a.intersect(b) == Some(true));
+                if overlap {
+                    report.overlaps.push((i + 1, j + 1));
+                }
+            }
+        }
+        let segs = (0..self.inputs.len())
+            .map(|col| Segment::split(&conds.iter().map(|r| &r[col]).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let rows = (0..conds.len()).collect::<Vec<_>>();
+        self.find_gaps(&conds, &segs, 0, rows, &mut vec![], &mut report.gaps);
+        report
+    }
+    fn cell_conds(&self) -> Vec<Vec<CellCond>> {
+        self.rows
+            .iter()
+            .map(|r| r.inputs.iter().map(|c| CellCond::parse(c)).collect())
+            .collect()
+    }
+    // 逐列把取值范围切分为基本区间，每个区间找出覆盖它的行，没有行覆盖即为缺失
+    fn find_gaps(
+        &self,
+        conds: &[Vec<CellCond>],
+        segs: &[Vec<Segment>],
+        col: usize,
+        rows: Vec<usize>,
+        path: &mut Vec<String>,
+        gaps: &mut Vec<String>,
+    ) {
+        if gaps.len() >= MAX_GAPS {
+            return;
+        }
+        if rows.is_empty() {
+            let mut desc = path.clone();
+            for i in self.inputs.iter().skip(col) {
+                desc.push(format!("{i}={WILDCARD}"));
+            }
+            gaps.push(desc.join(", "));
+            return;
+        }
+        if col >= self.inputs.len() {
+            return;
+        }
+        for seg in segs[col].iter() {
+            let covered = rows
+                .iter()
+                .filter(|r| conds[**r][col].covers(seg))
+                .cloned()
+                .collect::<Vec<_>>();
+            path.push(format!("{}={}", self.inputs[col], seg));
+            self.find_gaps(conds, segs, col + 1, covered, path, gaps);
+            path.pop();
+        }
+    }
+
+    /// 编译为规则，规则名为 表名_行号
+    pub fn build(&self) -> anyhow::Result<Vec<(String, Vec<Calc>, Assign)>> {
+        let report = self.analyze();
+        if self.hit_policy == HitPolicy::Unique && !report.overlaps.is_empty() {
+            let rows = report
+                .overlaps
+                .iter()
+                .map(|(a, b)| format!("{a}&{b}"))
+                .collect::<Vec<_>>();
+            return anyhow!(
+                "decision table[{}] hit policy is unique, but rows overlap: {}",
+                self.name,
+                rows.join(", ")
+            )
+            .err();
+        }
+        if self.complete && !report.gaps.is_empty() {
+            return anyhow!(
+                "decision table[{}] missing rows: {}",
+                self.name,
+                report.gaps.join("; ")
+            )
+            .err();
+        }
+        let mut order = (0..self.rows.len()).collect::<Vec<_>>();
+        if self.hit_policy == HitPolicy::Priority {
+            order.sort_by(|a, b| self.rows[*b].priority.cmp(&self.rows[*a].priority));
+        }
+        let mut matches: Vec<String> = vec![];
+        let mut rules = vec![];
+        for i in order {
+            let name = format!("{}_{}", self.name, i + 1);
+            let row = &self.rows[i];
+            let mut conds = vec![];
+            for (input, cell) in self.inputs.iter().zip(row.inputs.iter()) {
+                if let Some(expr) = cell_expr(input, cell)? {
+                    conds.push(expr);
+                }
+            }
+            let mut calc = vec![];
+            // first和priority策略排除前面已经命中的行
+            if matches!(self.hit_policy, HitPolicy::First | HitPolicy::Priority) {
+                for m in matches.iter() {
+                    calc.push(self.parse_cell(&name, &format!("!({m})"))?);
+                }
+            }
+            for c in conds.iter() {
+                calc.push(self.parse_cell(&name, c)?);
+            }
+            matches.push(match conds.is_empty() {
+                true => "true".into(),
+                false => conds.join(" && "),
+            });
+            let mut assign = Assign::new()
+                .set_salience(row.priority)
+                .set_collect(self.hit_policy == HitPolicy::Collect);
+            for (output, cell) in self.outputs.iter().zip(row.outputs.iter()) {
+                if is_wildcard(cell) {
+                    continue;
+                }
+                assign = assign.add_exec(output.as_str(), self.parse_cell(&name, cell)?)?;
+            }
+            rules.push((name, calc, assign));
+        }
+        Ok(rules)
+    }
+    fn parse_cell(&self, rule: &str, expr: &str) -> anyhow::Result<Calc> {
+        Calc::from_str(expr)
+            .map_err(|e| e.context(format!("decision table rule[{rule}] cell[{expr}]")))
+    }
+}
+
+enum Column {
+    Input,
+    Output,
+    Priority,
+}
+
+fn is_wildcard(cell: &str) -> bool {
+    let cell = cell.trim();
+    cell.is_empty() || cell == WILDCARD
+}
+
+fn cell_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => WILDCARD.into(),
+        _ => v.to_string(),
+    }
+}
+
+// 按逗号拆分，引号中的逗号不拆分，双引号包围的单元格去掉引号，""表示一个双引号
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('"') if c == '"' => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    cell.push('"');
+                } else {
+                    quote = None;
+                }
+            }
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                cell.push(c);
+            }
+            None if c == ',' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            None if c == '"' && cell.trim().is_empty() => {
+                cell.clear();
+                quote = Some('"');
+            }
+            None => {
+                if c == '\'' {
+                    quote = Some(c);
+                }
+                cell.push(c);
+            }
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+// 区间单元格 [18..30) (..18) [60..]
+fn parse_range(cell: &str) -> Option<(&str, bool, &str, bool)> {
+    let lo_incl = match cell.chars().next()? {
+        '[' => true,
+        '(' => false,
+        _ => return None,
+    };
+    let hi_incl = match cell.chars().last()? {
+        ']' => true,
+        ')' => false,
+        _ => return None,
+    };
+    if cell.len() < 2 {
+        return None;
+    }
+    let (lo, hi) = cell[1..cell.len() - 1].split_once("..")?;
+    Some((lo.trim(), lo_incl, hi.trim(), hi_incl))
+}
+
+fn split_compare(cell: &str) -> Option<(&str, &str)> {
+    for opt in [">=", "<=", "!=", "==", ">", "<"] {
+        if let Some(s) = cell.strip_prefix(opt) {
+            return Some((opt, s.trim()));
+        }
+    }
+    None
+}
+
+// 单元格转为条件表达式，任意值返回None
+fn cell_expr(input: &str, cell: &str) -> anyhow::Result<Option<String>> {
+    let cell = cell.trim();
+    if is_wildcard(cell) {
+        return Ok(None);
+    }
+    if let Some((lo, lo_incl, hi, hi_incl)) = parse_range(cell) {
+        let mut parts = vec![];
+        if !lo.is_empty() {
+            parts.push(format!(
+                "({input}) {} ({lo})",
+                if lo_incl { ">=" } else { ">" }
+            ));
+        }
+        if !hi.is_empty() {
+            parts.push(format!(
+                "({input}) {} ({hi})",
+                if hi_incl { "<=" } else { "<" }
+            ));
+        }
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(format!("({})", parts.join(" && "))));
+    }
+    if let Some((opt, value)) = split_compare(cell) {
+        if value.is_empty() {
+            return anyhow!("cell[{cell}] missing value after [{opt}]").err();
+        }
+        return Ok(Some(format!("(({input}) {opt} ({value}))")));
+    }
+    Ok(Some(format!("(({input}) == ({cell}))")))
+}
+
+type Bound = Option<(f64, bool)>;
+
+// 单元格的静态条件，用于重叠和缺失分析
+#[derive(Debug, Clone, PartialEq)]
+enum CellCond {
+    Any,
+    Range(Bound, Bound),
+    Eq(Value),
+    Unknown,
+}
+
+impl CellCond {
+    fn parse(cell: &str) -> Self {
+        let cell = cell.trim();
+        if is_wildcard(cell) {
+            return CellCond::Any;
+        }
+        if let Some((lo, lo_incl, hi, hi_incl)) = parse_range(cell) {
+            let bound = |s: &str, incl: bool| -> Result<Bound, ()> {
+                if s.is_empty() {
+                    return Ok(None);
+                }
+                match number(s) {
+                    Some(n) => Ok(Some((n, incl))),
+                    None => Err(()),
+                }
+            };
+            return match (bound(lo, lo_incl), bound(hi, hi_incl)) {
+                (Ok(lo), Ok(hi)) => CellCond::Range(lo, hi),
+                _ => CellCond::Unknown,
+            };
+        }
+        let (opt, value) = split_compare(cell).unwrap_or(("==", cell));
+        if opt == "==" {
+            return match Calc::from_str(value) {
+                Ok(Calc::Number(n)) => {
+                    CellCond::Range(Some((n as f64, true)), Some((n as f64, true)))
+                }
+                Ok(Calc::Float(f)) => CellCond::Range(Some((f, true)), Some((f, true))),
+                Ok(Calc::String(s)) => CellCond::Eq(Value::String(s)),
+                Ok(Calc::Bool(b)) => CellCond::Eq(Value::Bool(b)),
+                _ => CellCond::Unknown,
+            };
+        }
+        let n = match number(value) {
+            Some(n) => n,
+            None => return CellCond::Unknown,
+        };
+        match opt {
+            ">" => CellCond::Range(Some((n, false)), None),
+            ">=" => CellCond::Range(Some((n, true)), None),
+            "<" => CellCond::Range(None, Some((n, false))),
+            "<=" => CellCond::Range(None, Some((n, true))),
+            _ => CellCond::Unknown,
+        }
+    }
+    // Some(true)一定相交，Some(false)一定不相交，None无法判断
+    fn intersect(&self, other: &CellCond) -> Option<bool> {
+        match (self, other) {
+            (CellCond::Unknown, _) | (_, CellCond::Unknown) => None,
+            (CellCond::Any, _) | (_, CellCond::Any) => Some(true),
+            (CellCond::Eq(a), CellCond::Eq(b)) => Some(a == b),
+            (CellCond::Range(al, ah), CellCond::Range(bl, bh)) => {
+                let lo = max_lower(*al, *bl);
+                let hi = min_upper(*ah, *bh);
+                Some(match (lo, hi) {
+                    (Some((l, li)), Some((h, hi))) => l < h || l == h && li && hi,
+                    _ => true,
+                })
+            }
+            _ => Some(false),
+        }
+    }
+    fn covers(&self, seg: &Segment) -> bool {
+        match (self, seg) {
+            (CellCond::Any, _) => true,
+            (CellCond::Range(lo, hi), Segment::Number { point, .. }) => {
+                let x = *point;
+                let above = match lo {
+                    Some((l, true)) => x >= *l,
+                    Some((l, false)) => x > *l,
+                    None => true,
+                };
+                let below = match hi {
+                    Some((h, true)) => x <= *h,
+                    Some((h, false)) => x < *h,
+                    None => true,
+                };
+                above && below
+            }
+            (CellCond::Eq(a), Segment::Value(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+fn number(s: &str) -> Option<f64> {
+    match Calc::from_str(s) {
+        Ok(Calc::Number(n)) => Some(n as f64),
+        Ok(Calc::Float(f)) => Some(f),
+        _ => None,
+    }
+}
+
+fn max_lower(a: Bound, b: Bound) -> Bound {
+    match (a, b) {
+        (None, x) | (x, None) => x,
+        (Some(x), Some(y)) => match x.0.partial_cmp(&y.0) {
+            Some(Ordering::Greater) => Some(x),
+            Some(Ordering::Less) => Some(y),
+            _ => Some((x.0, x.1 && y.1)),
+        },
+    }
+}
+
+fn min_upper(a: Bound, b: Bound) -> Bound {
+    match (a, b) {
+        (None, x) | (x, None) => x,
+        (Some(x), Some(y)) => match x.0.partial_cmp(&y.0) {
+            Some(Ordering::Less) => Some(x),
+            Some(Ordering::Greater) => Some(y),
+            _ => Some((x.0, x.1 && y.1)),
+        },
+    }
+}
+
+// 一列取值范围中的基本区间，区间内部没有任何单元格的边界
+#[derive(Debug, Clone)]
+enum Segment {
+    // 整个取值范围
+    All,
+    // 数值区间，point是区间内的代表值
+    Number { point: f64, lo: Bound, hi: Bound },
+    Value(Value),
+    // 列出的值之外的其它值
+    Other(Vec<Value>),
+}
+
+impl Segment {
+    fn split(cells: &[&CellCond]) -> Vec<Segment> {
+        let mut bounds = vec![];
+        let mut values = vec![];
+        let mut numeric = false;
+        for c in cells {
+            match c {
+                CellCond::Range(lo, hi) => {
+                    numeric = true;
+                    bounds.extend(lo.iter().chain(hi.iter()).map(|x| x.0));
+                }
+                CellCond::Eq(v) if !values.contains(v) => values.push(v.clone()),
+                _ => {}
+            }
+        }
+        if !numeric && values.is_empty() {
+            return vec![Segment::All];
+        }
+        let mut segs = vec![];
+        if numeric {
+            bounds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            bounds.dedup();
+            let mut lo: Bound = None;
+            for b in bounds.iter() {
+                let point = match lo {
+                    Some((l, _)) => (l + b) / 2.0,
+                    None => b - 1.0,
+                };
+                segs.push(Segment::Number {
+                    point,
+                    lo,
+                    hi: Some((*b, false)),
+                });
+                segs.push(Segment::Number {
+                    point: *b,
+                    lo: Some((*b, true)),
+                    hi: Some((*b, true)),
+                });
+                lo = Some((*b, false));
+            }
+            segs.push(Segment::Number {
+                point: lo.map(|x| x.0 + 1.0).unwrap_or(0.0),
+                lo,
+                hi: None,
+            });
+        }
+        if !values.is_empty() {
+            segs.extend(values.iter().cloned().map(Segment::Value));
+            segs.push(Segment::Other(values));
+        }
+        segs
+    }
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::All => write!(f, "{WILDCARD}"),
+            Segment::Number { point, lo, hi } if lo.is_some() && lo == hi => write!(f, "{point}"),
+            Segment::Number { lo, hi, .. } => {
+                let (lb, l) = match lo {
+                    Some((l, true)) => ('[', l.to_string()),
+                    Some((l, false)) => ('(', l.to_string()),
+                    None => ('(', String::new()),
+                };
+                let (hb, h) = match hi {
+                    Some((h, true)) => (']', h.to_string()),
+                    Some((h, false)) => (')', h.to_string()),
+                    None => (')', String::new()),
+                };
+                write!(f, "{lb}{l}..{h}{hb}")
+            }
+            Segment::Value(v) => write!(f, "{v}"),
+            Segment::Other(list) => {
+                let list = list.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+                write!(f, "not in [{}]", list.join(","))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DecisionTable, HitPolicy};
+
+    const MEMBER_TABLE: &str = "
+    # hit_policy: first
+    age, channel, => member.grade, => member.discount
+    [18..30), 'app', 'young', 0.8
+    [18..30), -, 'young', 0.9
+    >= 30, -, 'adult', 1
+    ";
+
+    //cargo test --color=always --lib decision_table::test::test_csv_table --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_csv_table() {
+        let table = DecisionTable::from_csv("MEMBER", MEMBER_TABLE).unwrap();
+        assert_eq!(table.hit_policy, HitPolicy::First);
+        assert_eq!(table.inputs, vec!["age", "channel"]);
+        assert_eq!(table.outputs, vec!["member.grade", "member.discount"]);
+        assert_eq!(table.rows.len(), 3);
+
+        let report = table.analyze();
+        assert_eq!(report.overlaps, vec![(1, 2)]);
+        assert_eq!(report.gaps, vec!["age=(..18), channel=-"]);
+
+        let rules = table.build().unwrap();
+        assert_eq!(rules.len(), 3);
+        // 第二行排除第一行
+        assert_eq!(rules[1].1.len(), 2);
+
+        let err = table
+            .clone()
+            .hit_policy(HitPolicy::Unique)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("1&2"), "{}", err);
+        let err = table.complete(true).build().unwrap_err();
+        assert!(err.to_string().contains("(..18)"), "{}", err);
+    }
+
+    //cargo test --color=always --lib decision_table::test::test_gap_values --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_gap_values() {
+        let table = DecisionTable::from_csv(
+            "T",
+            "level, channel, => tag\n1, 'app', 'a'\n[2..], 'app', 'b'\n-, 'web', 'c'",
+        )
+        .unwrap();
+        let report = table.analyze();
+        assert!(report.overlaps.is_empty());
+        assert_eq!(
+            report.gaps,
+            vec![
+                "level=(..1), channel=\"app\"",
+                "level=(..1), channel=not in [\"app\",\"web\"]",
+                "level=1, channel=not in [\"app\",\"web\"]",
+                "level=(1..2), channel=\"app\"",
+                "level=(1..2), channel=not in [\"app\",\"web\"]",
+                "level=2, channel=not in [\"app\",\"web\"]",
+                "level=(2..), channel=not in [\"app\",\"web\"]",
+            ]
+        );
+    }
+}
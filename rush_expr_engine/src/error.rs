@@ -1,3 +1,5 @@
+use rush_core::LexError;
+pub use rush_core::Span;
 use std::error::Error;
 use std::fmt;
 
@@ -11,3 +13,214 @@ impl fmt::Display for NotFoundFieldError {
 }
 
 impl Error for NotFoundFieldError {}
+
+/// 规则没有通过schema检查
+#[derive(Default, Debug)]
+pub struct SchemaError {
+    pub rule: String,
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rule[{}] schema check failed: {}",
+            self.rule,
+            self.errors.join("; ")
+        )
+    }
+}
+
+impl Error for SchemaError {}
+
+/// 错误位置，line和column从1开始，column按字符计数
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct Position {
+    pub rule: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub span: Span,
+}
+
+impl Position {
+    pub fn new(span: Span) -> Self {
+        Self {
+            span,
+            ..Default::default()
+        }
+    }
+}
+
+/// 表达式和规则的解析错误
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    UnknownChar { ch: char, at: Position },
+    UnterminatedString { at: Position },
+    UnterminatedComment { at: Position },
+    InvalidNumber { token: String, at: Position },
+    Reserved { token: String, at: Position },
+    // 括号没有成对出现
+    Unmatched { token: String, at: Position },
+    MissingOperand { opt: String, at: Position },
+    UnexpectedToken { token: String, at: Position },
+    EmptyExpression { at: Position },
+    Syntax { message: String, at: Position },
+}
+
+impl From<LexError> for ParseError {
+    fn from(e: LexError) -> Self {
+        match e {
+            LexError::UnterminatedString(span) => ParseError::UnterminatedString {
+                at: Position::new(span),
+            },
+            LexError::UnterminatedComment(span) => ParseError::UnterminatedComment {
+                at: Position::new(span),
+            },
+        }
+    }
+}
+
+impl ParseError {
+    pub fn syntax<S: Into<String>>(message: S, span: Span) -> Self {
+        ParseError::Syntax {
+            message: message.into(),
+            at: Position::new(span),
+        }
+    }
+    pub fn position(&self) -> &Position {
+        match self {
+            ParseError::UnknownChar { at, .. }
+            | ParseError::UnterminatedString { at }
+            | ParseError::UnterminatedComment { at }
+            | ParseError::InvalidNumber { at, .. }
+            | ParseError::Reserved { at, .. }
+            | ParseError::Unmatched { at, .. }
+            | ParseError::MissingOperand { at, .. }
+            | ParseError::UnexpectedToken { at, .. }
+            | ParseError::EmptyExpression { at }
+            | ParseError::Syntax { at, .. } => at,
+        }
+    }
+    pub fn position_mut(&mut self) -> &mut Position {
+        match self {
+            ParseError::UnknownChar { at, .. }
+            | ParseError::UnterminatedString { at }
+            | ParseError::UnterminatedComment { at }
+            | ParseError::InvalidNumber { at, .. }
+            | ParseError::Reserved { at, .. }
+            | ParseError::Unmatched { at, .. }
+            | ParseError::MissingOperand { at, .. }
+            | ParseError::UnexpectedToken { at, .. }
+            | ParseError::EmptyExpression { at }
+            | ParseError::Syntax { at, .. } => at,
+        }
+    }
+    pub fn span(&self) -> Span {
+        self.position().span
+    }
+    /// 表达式是从更大的文本中截取时，把区间平移到原文本中
+    pub fn offset(mut self, n: usize) -> Self {
+        let at = self.position_mut();
+        at.span = at.span.offset(n);
+        self
+    }
+    pub fn with_rule<S: Into<String>>(mut self, rule: S) -> Self {
+        self.position_mut().rule = Some(rule.into());
+        self
+    }
+    /// 根据原文本计算行列号
+    pub fn locate(mut self, source: &str) -> Self {
+        let at = self.position_mut();
+        let start = at.span.start.min(source.len());
+        let before = &source[..floor_char_boundary(source, start)];
+        at.line = before.matches('\n').count() + 1;
+        at.column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        self
+    }
+    // child是source的子串时，把child中的位置换算到source中
+    pub(crate) fn relocate(err: anyhow::Error, child: &str, source: &str) -> anyhow::Error {
+        match err.downcast::<ParseError>() {
+            Ok(pe) => {
+                let offset = (child.as_ptr() as usize).saturating_sub(source.as_ptr() as usize);
+                pe.offset(offset).locate(source).into()
+            }
+            Err(e) => e,
+        }
+    }
+    pub(crate) fn attach_rule(err: anyhow::Error, rule: &str) -> anyhow::Error {
+        match err.downcast::<ParseError>() {
+            Ok(pe) => pe.with_rule(rule).into(),
+            Err(e) => e,
+        }
+    }
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnknownChar { ch, .. } => format!("unknown char({ch})"),
+            ParseError::UnterminatedString { .. } => "string is not closed".into(),
+            ParseError::UnterminatedComment { .. } => "/* and */ mismatch".into(),
+            ParseError::InvalidNumber { token, .. } => format!("invalid number({token})"),
+            ParseError::Reserved { token, .. } => format!("reserved token({token})"),
+            ParseError::Unmatched { token, .. } => format!("'{token}' is not matched"),
+            ParseError::MissingOperand { opt, .. } => {
+                format!("operator[{opt}] is missing an operand")
+            }
+            ParseError::UnexpectedToken { token, .. } => format!("unexpected token({token})"),
+            ParseError::EmptyExpression { .. } => "expression is null".into(),
+            ParseError::Syntax { message, .. } => message.clone(),
+        }
+    }
+    /// 生成带^标记的诊断信息，source需要是计算span时使用的文本
+    pub fn render(&self, source: &str) -> String {
+        let err = self.clone().locate(source);
+        let at = err.position();
+        let line_text = source.split('\n').nth(at.line - 1).unwrap_or("");
+        let line_text = line_text.trim_end_matches('\r');
+        let width = source
+            .get(at.span.start..at.span.end)
+            .map(|s| s.split('\n').next().unwrap_or("").chars().count())
+            .unwrap_or(0)
+            .max(1);
+        let number = at.line.to_string();
+        let pad = " ".repeat(number.len());
+        let mut out = format!("error: {}\n", err.message());
+        match at.rule {
+            Some(ref rule) => out.push_str(&format!(
+                "{pad}--> rule[{rule}] {}:{}\n",
+                at.line, at.column
+            )),
+            None => out.push_str(&format!("{pad}--> {}:{}\n", at.line, at.column)),
+        }
+        out.push_str(&format!("{pad} |\n{number} | {line_text}\n"));
+        out.push_str(&format!(
+            "{pad} | {}{}",
+            " ".repeat(at.column - 1),
+            "^".repeat(width)
+        ));
+        out
+    }
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = self.position();
+        if let Some(ref rule) = at.rule {
+            write!(f, "rule[{rule}] ")?;
+        }
+        if at.line > 0 {
+            write!(f, "line {} column {}: ", at.line, at.column)?;
+        } else {
+            write!(f, "at {}..{}: ", at.span.start, at.span.end)?;
+        }
+        write!(f, "{}", self.message())
+    }
+}
+
+impl Error for ParseError {}
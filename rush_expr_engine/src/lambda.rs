@@ -0,0 +1,331 @@
+use crate::{Calc, NotFoundFieldError, ValueType};
+use anyhow::anyhow;
+use rush_core::{ArithmeticError, FunctionSet};
+use serde_json::{Map, Number, Value};
+use std::cmp::Ordering;
+use std::sync::Arc;
+use wd_tools::{PFErr, PFOk};
+
+/// 内置的高阶函数，参数为数组和lambda：any(items, x -> x.price > 100)
+/// 同名函数已注册且没有lambda参数时，调用注册的函数
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HigherOrder {
+    Any,
+    All,
+    Filter,
+    Map,
+    Count,
+    Sum,
+    Min,
+    Max,
+    SortBy,
+}
+
+impl HigherOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let hf = match name {
+            "any" => HigherOrder::Any,
+            "all" => HigherOrder::All,
+            "filter" => HigherOrder::Filter,
+            "map" => HigherOrder::Map,
+            "count" => HigherOrder::Count,
+            "sum" => HigherOrder::Sum,
+            "min" => HigherOrder::Min,
+            "max" => HigherOrder::Max,
+            "sort_by" => HigherOrder::SortBy,
+            _ => return None,
+        };
+        Some(hf)
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            HigherOrder::Any => "any",
+            HigherOrder::All => "all",
+            HigherOrder::Filter => "filter",
+            HigherOrder::Map => "map",
+            HigherOrder::Count => "count",
+            HigherOrder::Sum => "sum",
+            HigherOrder::Min => "min",
+            HigherOrder::Max => "max",
+            HigherOrder::SortBy => "sort_by",
+        }
+    }
+    // count, sum, min, max 的lambda可以省略
+    fn lambda_optional(&self) -> bool {
+        matches!(
+            self,
+            HigherOrder::Count | HigherOrder::Sum | HigherOrder::Min | HigherOrder::Max
+        )
+    }
+    /// 返回值类型，用于schema检查
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            HigherOrder::Any | HigherOrder::All => ValueType::Bool,
+            HigherOrder::Count | HigherOrder::Sum => ValueType::Number,
+            HigherOrder::Filter | HigherOrder::Map | HigherOrder::SortBy => ValueType::Array,
+            HigherOrder::Min | HigherOrder::Max => ValueType::Any,
+        }
+    }
+
+    pub fn call(
+        &self,
+        args: &[Calc],
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        let name = self.name();
+        let lambda = match args {
+            [_] if self.lambda_optional() => None,
+            [_, Calc::Lambda(param, body)] => Some((param.as_str(), body.as_ref())),
+            _ => return anyhow!("function[{name}] want args: (array, x -> expression)").err(),
+        };
+        let list = match args[0].value(fs, input)? {
+            Value::Array(list) => list,
+            Value::Null => vec![],
+            v => return anyhow!("function[{name}] first arg must is array, found:{v}").err(),
+        };
+        let (param, body) = match lambda {
+            Some(s) => s,
+            None => return self.reduce(list),
+        };
+        let mut scope = Scope::new(input, param);
+        match self {
+            HigherOrder::Any => {
+                for i in list {
+                    if scope.test(body, fs, i)? {
+                        return Value::Bool(true).ok();
+                    }
+                }
+                Value::Bool(false).ok()
+            }
+            HigherOrder::All => {
+                for i in list {
+                    if !scope.test(body, fs, i)? {
+                        return Value::Bool(false).ok();
+                    }
+                }
+                Value::Bool(true).ok()
+            }
+            HigherOrder::Filter => {
+                let mut result = vec![];
+                for i in list {
+                    if scope.test(body, fs, i.clone())? {
+                        result.push(i);
+                    }
+                }
+                Value::Array(result).ok()
+            }
+            HigherOrder::Count => {
+                let mut count = 0i64;
+                for i in list {
+                    if scope.test(body, fs, i)? {
+                        count += 1;
+                    }
+                }
+                Value::Number(count.into()).ok()
+            }
+            HigherOrder::Map | HigherOrder::Sum | HigherOrder::Min | HigherOrder::Max => {
+                let mut result = vec![];
+                for i in list {
+                    result.push(scope.apply(body, fs, i)?);
+                }
+                match self {
+                    HigherOrder::Map => Value::Array(result).ok(),
+                    _ => self.reduce(result),
+                }
+            }
+            HigherOrder::SortBy => {
+                let mut keyed = vec![];
+                for i in list {
+                    keyed.push((scope.apply(body, fs, i.clone())?, i));
+                }
+                let mut err = None;
+                keyed.sort_by(|a, b| {
+                    compare(&a.0, &b.0).unwrap_or_else(|e| {
+                        err.get_or_insert(e);
+                        Ordering::Equal
+                    })
+                });
+                if let Some(e) = err {
+                    return Err(e);
+                }
+                Value::Array(keyed.into_iter().map(|x| x.1).collect()).ok()
+            }
+        }
+    }
+    // 没有lambda时直接对数组元素计算
+    fn reduce(&self, list: Vec<Value>) -> anyhow::Result<Value> {
+        match self {
+            HigherOrder::Count => Value::Number((list.len() as i64).into()).ok(),
+            HigherOrder::Sum => {
+                let (mut int, mut float, mut is_float) = (0i64, 0f64, false);
+                for i in list.iter() {
+                    match i {
+                        Value::Null => {}
+                        Value::Number(n) if n.is_i64() && !is_float => {
+                            let x = n.as_i64().unwrap_or(0);
+                            int = match int.checked_add(x) {
+                                Some(s) => s,
+                                None => {
+                                    let bound = Value::from(int.saturating_add(x));
+                                    return Err(ArithmeticError::overflow("sum", bound).into());
+                                }
+                            };
+                        }
+                        Value::Number(n) => {
+                            if !is_float {
+                                is_float = true;
+                                float = int as f64;
+                            }
+                            float += n.as_f64().unwrap_or(0.0)
+                        }
+                        _ => return anyhow!("function[sum] want number, found:{i}").err(),
+                    }
+                }
+                if !is_float {
+                    return Value::Number(int.into()).ok();
+                }
+                match Number::from_f64(float) {
+                    Some(n) => Value::Number(n).ok(),
+                    None => {
+                        let bound = Value::from(if float < 0.0 { f64::MIN } else { f64::MAX });
+                        Err(ArithmeticError::overflow("sum", bound).into())
+                    }
+                }
+            }
+            HigherOrder::Min | HigherOrder::Max => {
+                let mut best: Option<Value> = None;
+                for i in list {
+                    if i.is_null() {
+                        continue;
+                    }
+                    best = match best {
+                        None => Some(i),
+                        Some(b) => {
+                            let ord = compare(&i, &b)?;
+                            let better = match self {
+                                HigherOrder::Min => ord == Ordering::Less,
+                                _ => ord == Ordering::Greater,
+                            };
+                            Some(if better { i } else { b })
+                        }
+                    };
+                }
+                best.unwrap_or(Value::Null).ok()
+            }
+            _ => anyhow!("function[{}] must have a lambda", self.name()).err(),
+        }
+    }
+}
+
+// 数字和字符串可以比较大小
+fn compare(a: &Value, b: &Value) -> anyhow::Result<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal).ok()
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y).ok(),
+        _ => anyhow!("can not compare [{a}] and [{b}]").err(),
+    }
+}
+
+/// lambda的作用域，参数覆盖输入中的同名字段，其它字段仍然可以访问
+struct Scope {
+    value: Value,
+    param: String,
+}
+
+impl Scope {
+    fn new(input: &Value, param: &str) -> Self {
+        let map = match input {
+            Value::Object(map) => map.clone(),
+            _ => Map::new(),
+        };
+        Self {
+            value: Value::Object(map),
+            param: param.to_string(),
+        }
+    }
+    fn bind(&mut self, arg: Value) -> &Value {
+        if let Value::Object(ref mut map) = self.value {
+            map.insert(self.param.clone(), arg);
+        }
+        &self.value
+    }
+    // 字段不存在时为null
+    fn apply(
+        &mut self,
+        body: &Calc,
+        fs: &Arc<dyn FunctionSet>,
+        arg: Value,
+    ) -> anyhow::Result<Value> {
+        let scope = self.bind(arg);
+        match body.value(fs, scope) {
+            Ok(v) => Ok(v),
+            Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => Ok(Value::Null),
+            Err(e) => Err(e),
+        }
+    }
+    // 字段不存在视为不满足
+    fn test(&mut self, body: &Calc, fs: &Arc<dyn FunctionSet>, arg: Value) -> anyhow::Result<bool> {
+        let scope = self.bind(arg);
+        match body.bool(fs, scope) {
+            Ok(b) => Ok(b),
+            Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Calc;
+    use rush_core::{Function, FunctionSet};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    //cargo test --color=always --lib lambda::test::test_higher_order --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_higher_order() {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let input = json!({"limit":10,"items":[{"sku":"b","price":20},{"sku":"a","price":5.5},{"sku":"c"}]});
+        let value = |expr: &str| -> Value {
+            let calc: Calc = expr.parse().unwrap();
+            calc.value(&fs, &input).unwrap()
+        };
+        assert_eq!(value("any(items, x -> x.price > limit)"), json!(true));
+        assert_eq!(value("all(items, x -> x.price > 1)"), json!(false));
+        assert_eq!(value("count(items, x -> x.price > 1)"), json!(2));
+        assert_eq!(value("count(items)"), json!(3));
+        assert_eq!(
+            value("map(filter(items, x -> x.price < limit), x -> x.sku)"),
+            json!(["a"])
+        );
+        assert_eq!(value("map(items, x -> x.sku)"), json!(["b", "a", "c"]));
+        assert_eq!(value("map(items, x -> x.price)"), json!([20, 5.5, null]));
+        assert_eq!(value("sum(items, x -> x.price)"), json!(25.5));
+        assert_eq!(value("sum([1, 2, 3])"), json!(6));
+        assert_eq!(value("max(map(items, x -> x.sku))"), json!("c"));
+        assert_eq!(value("min(items[*].price)"), json!(5.5));
+        assert_eq!(
+            value("map(sort_by(items, x -> x.sku), x -> x.sku)"),
+            json!(["a", "b", "c"])
+        );
+        // 嵌套的lambda可以访问外层参数
+        assert_eq!(
+            value("any(items, x -> any(items, y -> y.price > x.price))"),
+            json!(true)
+        );
+
+        let calc: Calc = "any(items, x -> x.price > 1)".parse().unwrap();
+        assert_eq!(calc.to_string(), "any(items,x -> (x.price > 1))");
+        assert!("any(items, x.a -> x)".parse::<Calc>().is_err());
+        assert!("filter(items)"
+            .parse::<Calc>()
+            .unwrap()
+            .value(&fs, &input)
+            .is_err());
+    }
+}
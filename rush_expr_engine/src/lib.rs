@@ -4,12 +4,29 @@ mod assign;
 mod calc;
 mod calc_builder;
 mod calc_parse;
+mod checked;
+mod decimal;
+mod decision_table;
 mod error;
+mod lambda;
+mod null_safe;
+mod path;
+mod pattern;
 mod rule_builder;
+mod rule_parse;
+mod schema;
 
 pub use assign::*;
 pub use calc::*;
 pub use calc_builder::*;
 pub use calc_parse::*;
+pub use checked::*;
+pub use decimal::*;
+pub use decision_table::*;
 pub use error::*;
+pub use lambda::*;
+pub use null_safe::*;
+pub use path::*;
+pub use pattern::*;
 pub use rule_builder::*;
+pub use schema::*;
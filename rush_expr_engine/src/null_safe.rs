@@ -0,0 +1,280 @@
+use crate::{contains, get_path, Calc, NotFoundFieldError, Opt, PathSegment};
+use anyhow::anyhow;
+use rush_core::FunctionSet;
+use serde_json::Value;
+use std::sync::Arc;
+use wd_tools::{PFErr, PFOk};
+
+/// 字段不存在和null的处理方式
+/// Strict：字段不存在时报错，条件为false，赋值失败
+/// ThreeValued：字段不存在视为null，运算遇到null结果为null，&& || ! 按三值逻辑计算
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum NullMode {
+    #[default]
+    Strict,
+    ThreeValued,
+}
+
+/// 内置的空值判断：exists(path) 路径存在（值可以是null），is_null(path) 路径不存在或者值为null
+/// 同名函数已注册时，调用注册的函数
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NullPredicate {
+    Exists,
+    IsNull,
+}
+
+impl NullPredicate {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exists" => Some(NullPredicate::Exists),
+            "is_null" => Some(NullPredicate::IsNull),
+            _ => None,
+        }
+    }
+    pub fn call(
+        &self,
+        args: &[Calc],
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        let arg = match args {
+            [Calc::NullSafe(arg)] => arg.as_ref(),
+            [arg] => arg,
+            _ => return anyhow!("function[{:?}] want one arg", self).err(),
+        };
+        // 路径上任意一段不存在或者不是对象、数组都视为不存在，忽略可选链，a?.b 与 a.b 一致
+        let val = match arg {
+            Calc::Field(field) => {
+                let segs = field
+                    .segments()
+                    .iter()
+                    .filter(|x| **x != PathSegment::Optional)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                get_path(field, &segs, input).ok()
+            }
+            _ => match arg.value(fs, input) {
+                Ok(v) => Some(v),
+                Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => None,
+                Err(e) => return e.err(),
+            },
+        };
+        let b = match self {
+            NullPredicate::Exists => val.is_some(),
+            NullPredicate::IsNull => matches!(val, None | Some(Value::Null)),
+        };
+        Value::Bool(b).ok()
+    }
+}
+
+impl Calc {
+    /// 按null模式转换表达式，ThreeValued时字段和运算符包装为NullSafe
+    pub fn null_mode(self, mode: NullMode) -> Self {
+        match mode {
+            NullMode::Strict => self,
+            NullMode::ThreeValued => self.null_safe(),
+        }
+    }
+    fn null_safe(self) -> Self {
+        let all = |list: Vec<Calc>| list.into_iter().map(Calc::null_safe).collect();
+        match self {
+            Calc::Field(_) => Calc::NullSafe(Box::new(self)),
+            Calc::Operator(opt, args) => Calc::NullSafe(Box::new(Calc::Operator(opt, all(args)))),
+            Calc::Array(list) => Calc::Array(all(list)),
+            Calc::Function(name, args) => Calc::Function(name, all(args)),
+            Calc::Lambda(param, body) => Calc::Lambda(param, Box::new(body.null_safe())),
+            Calc::Decimal(calc) => Calc::Decimal(Box::new(calc.null_safe())),
+            Calc::Checked(policy, calc) => Calc::Checked(policy, Box::new(calc.null_safe())),
+            Calc::If(cond, a, b) => Calc::If(
+                Box::new(cond.null_safe()),
+                Box::new(a.null_safe()),
+                Box::new(b.null_safe()),
+            ),
+            Calc::Case(branches, default) => Calc::Case(
+                branches
+                    .into_iter()
+                    .map(|(c, v)| (c.null_safe(), v.null_safe()))
+                    .collect(),
+                Box::new(default.null_safe()),
+            ),
+            _ => self,
+        }
+    }
+    pub(crate) fn null_safe_value(
+        &self,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        match self {
+            Calc::Field(_) => match self.field(input) {
+                Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => Value::Null.ok(),
+                res => res,
+            },
+            Calc::Operator(opt, args) => Self::null_safe_operator(opt, args, fs, input),
+            _ => self.value(fs, input),
+        }
+    }
+    pub(crate) fn null_safe_operator(
+        opt: &Opt,
+        args: &Vec<Calc>,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Value> {
+        match (opt, args.as_slice()) {
+            (Opt::NOT, [a]) => match Self::truth(a, fs, input)? {
+                Some(b) => Value::Bool(!b).ok(),
+                None => Value::Null.ok(),
+            },
+            // false && null = false，true || null = true，其余有null时为null
+            (Opt::AT | Opt::OT, [a, b]) => {
+                let short = matches!(opt, Opt::OT);
+                let left = Self::truth(a, fs, input)?;
+                if left == Some(short) {
+                    return Value::Bool(short).ok();
+                }
+                match (left, Self::truth(b, fs, input)?) {
+                    (_, Some(r)) if r == short => Value::Bool(short).ok(),
+                    (Some(_), Some(_)) => Value::Bool(!short).ok(),
+                    _ => Value::Null.ok(),
+                }
+            }
+            // 相等比较和 ?? 本身可以处理null
+            (Opt::EQ | Opt::NQ | Opt::NC, _) => Self::operator(opt, args, fs, input),
+            _ => {
+                let mut values = vec![];
+                for i in args.iter() {
+                    let v = i.value(fs, input)?;
+                    if v.is_null() && *i != Calc::NULL {
+                        return Value::Null.ok();
+                    }
+                    values.push(v);
+                }
+                if let (Opt::IN, [a, b]) = (opt, values.as_slice()) {
+                    return Value::Bool(contains(a, b)?).ok();
+                }
+                // 已经计算过的参数转为常量，避免重复计算
+                let consts = args
+                    .iter()
+                    .zip(values)
+                    .map(|(c, v)| c.evaluated(v))
+                    .collect();
+                Self::operator(opt, &consts, fs, input)
+            }
+        }
+    }
+    // null为None，其他值按bool的规则转换
+    fn truth(
+        calc: &Calc,
+        fs: &Arc<dyn FunctionSet>,
+        input: &Value,
+    ) -> anyhow::Result<Option<bool>> {
+        let b = match calc.value(fs, input)? {
+            Value::Null => None,
+            Value::Bool(b) => Some(b),
+            _ => Some(true),
+        };
+        b.ok()
+    }
+    // 对象等无法表示为常量的值返回None
+    pub(crate) fn constant(val: Value) -> Option<Calc> {
+        let c = match val {
+            Value::Null => Calc::NULL,
+            Value::Bool(b) => Calc::Bool(b),
+            Value::String(s) => Calc::String(s),
+            Value::Number(n) => match (n.as_i64(), n.is_f64()) {
+                (Some(i), _) => Calc::Number(i),
+                (None, true) => Calc::Float(n.as_f64()?),
+                _ => return None,
+            },
+            Value::Array(list) => {
+                let mut array = vec![];
+                for i in list {
+                    array.push(Self::constant(i)?);
+                }
+                Calc::Array(array)
+            }
+            Value::Object(_) => return None,
+        };
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Calc, CalcBuilder, NullMode};
+    use rush_core::{CalcNode, Function, FunctionSet};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn eval(expr: &str, mode: NullMode, input: &Value) -> anyhow::Result<Value> {
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let calc = CalcBuilder::new(expr).null_mode(mode).build()?;
+        calc.value(&fs, input)
+    }
+
+    //cargo test --color=always --lib null_safe::test::test_null_safe --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_null_safe() {
+        let input = json!({"user":{"name":"ann","tags":null},"n":null,"age":20});
+        let strict = NullMode::Strict;
+        assert_eq!(eval("user?.name", strict, &input).unwrap(), json!("ann"));
+        assert_eq!(eval("n?.name", strict, &input).unwrap(), json!(null));
+        assert_eq!(eval("user.tags?[0]", strict, &input).unwrap(), json!(null));
+        assert!(eval("n.name", strict, &input).is_err());
+        assert_eq!(
+            eval("user.nick ?? user.name", strict, &input).unwrap(),
+            json!("ann")
+        );
+        assert_eq!(eval("n ?? 1 + 2", strict, &input).unwrap(), json!(3));
+        assert_eq!(eval("age ?? 0", strict, &input).unwrap(), json!(20));
+        assert_eq!(
+            eval("exists(user.tags)", strict, &input).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            eval("exists(user.nick)", strict, &input).unwrap(),
+            json!(false)
+        );
+        assert_eq!(
+            eval("is_null(user.tags)", strict, &input).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            eval("is_null(user.nick)", strict, &input).unwrap(),
+            json!(true)
+        );
+        assert_eq!(eval("is_null(age)", strict, &input).unwrap(), json!(false));
+        assert!(eval("missing + 1", strict, &input).is_err());
+
+        let tv = NullMode::ThreeValued;
+        assert_eq!(eval("missing + 1", tv, &input).unwrap(), json!(null));
+        assert_eq!(
+            eval("missing > 1 || age > 18", tv, &input).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            eval("missing > 1 && age > 18", tv, &input).unwrap(),
+            json!(null)
+        );
+        assert_eq!(
+            eval("missing > 1 && age > 30", tv, &input).unwrap(),
+            json!(false)
+        );
+        assert_eq!(eval("!(missing > 1)", tv, &input).unwrap(), json!(null));
+        assert_eq!(eval("missing == null", tv, &input).unwrap(), json!(true));
+        assert_eq!(eval("(missing + 1) ?? 0", tv, &input).unwrap(), json!(0));
+        assert_eq!(eval("user.name + '!'", tv, &input).unwrap(), json!("ann!"));
+        assert_eq!(eval("'a' in user.tags", tv, &input).unwrap(), json!(null));
+        assert_eq!(eval("'name' in user", tv, &input).unwrap(), json!(true));
+        assert_eq!(eval("exists(missing)", tv, &input).unwrap(), json!(false));
+        assert_eq!(eval("exists(n?.name)", tv, &input).unwrap(), json!(false));
+
+        let calc = CalcBuilder::new("!(missing > 1)").build().unwrap();
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        assert!(!calc.when(fs.clone(), &input).unwrap());
+        let calc: Calc = calc.null_mode(tv);
+        assert_eq!(calc.to_string(), "(! (missing > 1))");
+        assert!(!calc.when(fs, &input).unwrap());
+    }
+}
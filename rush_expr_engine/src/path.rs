@@ -0,0 +1,362 @@
+use crate::NotFoundFieldError;
+use anyhow::anyhow;
+use serde_json::{Map, Value};
+use wd_tools::{PFErr, PFOk};
+
+/// 字段路径的一段：a.b[0].c[-1].d[*].e?.f
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    // 负数从末尾开始
+    Index(i64),
+    // 投影数组中的每一个元素
+    Wildcard,
+    // ?. 前一段不存在或者为null时，整个路径的值为null
+    Optional,
+}
+
+impl std::fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathSegment::Key(k) => write!(f, "{k}"),
+            PathSegment::Index(i) => write!(f, "[{i}]"),
+            PathSegment::Wildcard => write!(f, "[*]"),
+            PathSegment::Optional => write!(f, "?"),
+        }
+    }
+}
+
+impl PathSegment {
+    /// 解析路径，只有 . 分隔时与之前的行为一致
+    pub fn parse(path: &str) -> anyhow::Result<Vec<PathSegment>> {
+        let mut segs = vec![];
+        for part in path.split('.') {
+            let (part, optional) = match part.strip_suffix('?') {
+                Some(p) => (p, true),
+                None => (part, false),
+            };
+            let (key, mut rest) = match part.find('[') {
+                Some(i) => (&part[..i], &part[i..]),
+                None => (part, ""),
+            };
+            if let Some(key) = key.strip_suffix('?') {
+                // a?[0]
+                segs.push(PathSegment::Key(key.to_string()));
+                segs.push(PathSegment::Optional);
+            } else if !key.is_empty() || rest.is_empty() {
+                segs.push(PathSegment::Key(key.to_string()));
+            }
+            while !rest.is_empty() {
+                let end = match (rest.starts_with('['), rest.find(']')) {
+                    (true, Some(end)) => end,
+                    _ => return anyhow!("field[{path}] has invalid index at [{rest}]").err(),
+                };
+                segs.push(Self::index(path, &rest[1..end])?);
+                rest = &rest[end + 1..];
+            }
+            if optional {
+                segs.push(PathSegment::Optional);
+            }
+        }
+        segs.ok()
+    }
+    fn index(path: &str, s: &str) -> anyhow::Result<PathSegment> {
+        let s = s.trim();
+        if s == "*" {
+            return PathSegment::Wildcard.ok();
+        }
+        match s.parse::<i64>() {
+            Ok(i) => PathSegment::Index(i).ok(),
+            Err(_) => anyhow!("field[{path}] index[{s}] must be integer or *").err(),
+        }
+    }
+    // 按长度把负数下标转为正数，越界返回None
+    fn position(i: i64, len: usize) -> Option<usize> {
+        let i = if i < 0 { len as i64 + i } else { i };
+        if i < 0 || i as usize >= len {
+            None
+        } else {
+            Some(i as usize)
+        }
+    }
+}
+
+/// 解析好的字段路径，解析表达式时生成并保存在Calc中，避免每次执行时重复解析
+#[derive(Clone)]
+pub struct FieldPath {
+    path: String,
+    segs: Vec<PathSegment>,
+}
+
+impl FieldPath {
+    pub fn parse<S: Into<String>>(path: S) -> anyhow::Result<Self> {
+        let path = path.into();
+        let segs = PathSegment::parse(&path)?;
+        Self { path, segs }.ok()
+    }
+    pub fn path(&self) -> &str {
+        self.path.as_str()
+    }
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segs
+    }
+    pub fn get(&self, input: &Value) -> anyhow::Result<Value> {
+        get_path(&self.path, &self.segs, input)
+    }
+}
+
+impl std::ops::Deref for FieldPath {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.path.as_str()
+    }
+}
+
+impl PartialEq for FieldPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl std::fmt::Debug for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.path)
+    }
+}
+
+/// 按路径取值，字段或下标不存在时返回NotFoundFieldError
+/// 通配符返回数组，缺少后续字段的元素被跳过
+/// 后面跟着 ? 的段不存在或者为null时返回null
+pub fn get_path(path: &str, segs: &[PathSegment], input: &Value) -> anyhow::Result<Value> {
+    let mut input = input;
+    for (n, seg) in segs.iter().enumerate() {
+        let optional = segs.get(n + 1) == Some(&PathSegment::Optional);
+        input = match (seg, input) {
+            (PathSegment::Optional, Value::Null) => return Value::Null.ok(),
+            (PathSegment::Optional, v) => v,
+            (PathSegment::Key(k), Value::Object(obj)) => match obj.get(k) {
+                Some(s) => s,
+                None if optional => return Value::Null.ok(),
+                None => return Err(NotFoundFieldError(k.to_string()).into()),
+            },
+            (PathSegment::Index(i), Value::Array(list)) => {
+                match PathSegment::position(*i, list.len()) {
+                    Some(p) => &list[p],
+                    None if optional => return Value::Null.ok(),
+                    None => return Err(NotFoundFieldError(format!("{path}[{i}]")).into()),
+                }
+            }
+            (PathSegment::Wildcard, Value::Array(list)) => {
+                let mut values = vec![];
+                for i in list {
+                    match get_path(path, &segs[n + 1..], i) {
+                        Ok(v) => values.push(v),
+                        Err(e) if e.downcast_ref::<NotFoundFieldError>().is_some() => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                return Value::Array(values).ok();
+            }
+            (PathSegment::Key(_), _) => {
+                return anyhow!("not found object at field[{path}]").err();
+            }
+            (_, _) => return anyhow!("not found array at field[{path}]").err(),
+        };
+    }
+    input.clone().ok()
+}
+
+/// 按路径写入，中间缺少的对象会自动创建，下标等于数组长度时追加
+/// 通配符写入数组中的每一个元素；collect为true时把值追加到目标数组中
+pub fn set_path(
+    path: &str,
+    segs: &[PathSegment],
+    out: &mut Value,
+    value: Value,
+    collect: bool,
+) -> anyhow::Result<()> {
+    let (seg, rest) = match segs.split_first() {
+        Some((PathSegment::Optional, rest)) => return set_path(path, rest, out, value, collect),
+        Some(s) => s,
+        None => return anyhow!("field[{path}] is empty").err(),
+    };
+    // 缺少中间节点时按下一段的类型创建
+    let next = rest.iter().find(|x| **x != PathSegment::Optional);
+    let last = next.is_none();
+    let empty = || match next {
+        Some(PathSegment::Key(_)) | None => Value::Object(Map::new()),
+        Some(_) => Value::Array(vec![]),
+    };
+    let slot = match (seg, out) {
+        (PathSegment::Key(k), Value::Object(map)) => {
+            if last && !collect {
+                map.insert(k.clone(), value);
+                return Ok(());
+            }
+            if !map.contains_key(k) {
+                map.insert(k.clone(), empty());
+            }
+            map.get_mut(k).unwrap()
+        }
+        (PathSegment::Index(i), Value::Array(list)) => {
+            if *i == list.len() as i64 {
+                list.push(empty());
+            }
+            match PathSegment::position(*i, list.len()) {
+                Some(p) if last && !collect => {
+                    list[p] = value;
+                    return Ok(());
+                }
+                Some(p) => &mut list[p],
+                None => return anyhow!("field[{path}] index[{i}] out of range").err(),
+            }
+        }
+        (PathSegment::Wildcard, Value::Array(list)) => {
+            for i in list.iter_mut() {
+                match (last, collect) {
+                    (true, false) => *i = value.clone(),
+                    (true, true) => collect_into(i, value.clone()),
+                    _ => set_path(path, rest, i, value.clone(), collect)?,
+                }
+            }
+            return Ok(());
+        }
+        (PathSegment::Key(_), _) => {
+            return anyhow!("want insert at field[{path}],but the path is not obj").err()
+        }
+        (_, _) => return anyhow!("want insert at field[{path}],but the path is not array").err(),
+    };
+    if !last {
+        return set_path(path, rest, slot, value, collect);
+    }
+    collect_into(slot, value);
+    Ok(())
+}
+
+/// 按路径删除，路径不存在或者中间节点类型不对时不做任何事，删除数组元素时后面的元素前移
+pub fn remove_path(path: &str, segs: &[PathSegment], out: &mut Value) -> anyhow::Result<()> {
+    if segs.contains(&PathSegment::Wildcard) {
+        return anyhow!("field[{path}] can not delete by [*]").err();
+    }
+    let segs: Vec<_> = segs
+        .iter()
+        .filter(|x| **x != PathSegment::Optional)
+        .collect();
+    let (last, parents) = match segs.split_last() {
+        Some(s) => s,
+        None => return anyhow!("field[{path}] is empty").err(),
+    };
+    let mut out = out;
+    for seg in parents {
+        let next = match (seg, out) {
+            (PathSegment::Key(k), Value::Object(map)) => map.get_mut(k),
+            (PathSegment::Index(i), Value::Array(list)) => {
+                match PathSegment::position(*i, list.len()) {
+                    Some(p) => list.get_mut(p),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        out = match next {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+    }
+    match (last, out) {
+        (PathSegment::Key(k), Value::Object(map)) => {
+            map.remove(k);
+        }
+        (PathSegment::Index(i), Value::Array(list)) => {
+            if let Some(p) = PathSegment::position(*i, list.len()) {
+                list.remove(p);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// 追加到数组，原来不是数组时替换为数组
+fn collect_into(slot: &mut Value, value: Value) {
+    match slot {
+        Value::Array(list) => list.push(value),
+        _ => *slot = Value::Array(vec![value]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{get_path, remove_path, set_path, PathSegment};
+    use serde_json::{json, Value};
+
+    //cargo test --color=always --lib path::test::test_path --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_path() {
+        let segs = PathSegment::parse("order.items[-1].tags[*]").unwrap();
+        assert_eq!(
+            segs,
+            vec![
+                PathSegment::Key("order".into()),
+                PathSegment::Key("items".into()),
+                PathSegment::Index(-1),
+                PathSegment::Key("tags".into()),
+                PathSegment::Wildcard,
+            ]
+        );
+        assert!(PathSegment::parse("items[a]").is_err());
+        assert_eq!(
+            PathSegment::parse("a?.b[0]?.c").unwrap(),
+            vec![
+                PathSegment::Key("a".into()),
+                PathSegment::Optional,
+                PathSegment::Key("b".into()),
+                PathSegment::Index(0),
+                PathSegment::Optional,
+                PathSegment::Key("c".into()),
+            ]
+        );
+
+        let input = json!({"items":[{"sku":"a","price":1},{"price":2},{"sku":"c","price":3}]});
+        let get = |path: &str| {
+            let segs = PathSegment::parse(path).unwrap();
+            get_path(path, &segs, &input)
+        };
+        assert_eq!(get("items[0].price").unwrap(), json!(1));
+        assert_eq!(get("items[-1].sku").unwrap(), json!("c"));
+        assert_eq!(get("items[*].sku").unwrap(), json!(["a", "c"]));
+        assert!(get("items[3]").is_err());
+        assert!(get("items.price").is_err());
+        assert_eq!(get("user?.name").unwrap(), Value::Null);
+        assert_eq!(get("items[5]?.sku").unwrap(), Value::Null);
+        assert_eq!(get("items[1].sku?.len").unwrap(), Value::Null);
+        assert!(get("user?.name.first").is_ok());
+        assert!(get("items[0].name?").is_ok());
+
+        let mut out = Value::Object(Default::default());
+        let mut set = |path: &str, value: Value| {
+            let segs = PathSegment::parse(path).unwrap();
+            set_path(path, &segs, &mut out, value, false).unwrap();
+        };
+        set("list[0].name", json!("a"));
+        set("list[1].name", json!("b"));
+        set("list[*].checked", json!(true));
+        set("list[-1].name", json!("c"));
+        assert_eq!(
+            out.to_string(),
+            r#"{"list":[{"checked":true,"name":"a"},{"checked":true,"name":"c"}]}"#
+        );
+
+        let mut remove = |path: &str| {
+            let segs = PathSegment::parse(path).unwrap();
+            remove_path(path, &segs, &mut out)
+        };
+        remove("list[0].checked").unwrap();
+        remove("list[-1]").unwrap();
+        remove("list[5].name").unwrap();
+        remove("missing.name").unwrap();
+        assert!(remove("list[*].name").is_err());
+        assert_eq!(out.to_string(), r#"{"list":[{"name":"a"}]}"#);
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::anyhow;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::Arc;
+use wd_tools::{PFErr, PFOk};
+
+/// 编译好的匹配模式，常量模式在解析时编译并保存在Calc中，避免每次执行时重复编译
+/// like：% 匹配任意个字符，_ 匹配一个字符，\ 转义；=~：正则，匹配字符串中的任意位置
+#[derive(Clone)]
+pub struct Pattern {
+    source: String,
+    like: bool,
+    regex: Arc<Regex>,
+}
+
+impl Pattern {
+    pub fn regex(source: &str) -> anyhow::Result<Self> {
+        let regex = match Regex::new(source) {
+            Ok(o) => o,
+            Err(e) => return anyhow!("regex[{source}] is invalid:{e}").err(),
+        };
+        Self {
+            source: source.to_string(),
+            like: false,
+            regex: Arc::new(regex),
+        }
+        .ok()
+    }
+    pub fn like(source: &str) -> anyhow::Result<Self> {
+        let mut expr = String::from("(?s)^");
+        let mut chars = source.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => expr.push_str(".*"),
+                '_' => expr.push('.'),
+                '\\' => {
+                    let e = chars.next().unwrap_or('\\');
+                    expr.push_str(&regex::escape(e.encode_utf8(&mut [0; 4])));
+                }
+                _ => expr.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        expr.push('$');
+        let regex = Regex::new(&expr)?;
+        Self {
+            source: source.to_string(),
+            like: true,
+            regex: Arc::new(regex),
+        }
+        .ok()
+    }
+    pub fn source(&self) -> &str {
+        self.source.as_str()
+    }
+    pub fn is_like(&self) -> bool {
+        self.like
+    }
+    pub fn is_match(&self, s: &str) -> bool {
+        self.regex.is_match(s)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.like == other.like
+    }
+}
+
+impl std::fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pattern")
+            .field("source", &self.source)
+            .field("like", &self.like)
+            .finish()
+    }
+}
+
+/// 字符串拼接，null视为空字符串，数字等其它类型需要先用to_string转换，避免 '5' + 1 得到 "51"
+pub fn concat(a: &Value, b: &Value) -> anyhow::Result<Value> {
+    let mut s = String::new();
+    for v in [a, b] {
+        match v {
+            Value::Null => {}
+            Value::String(x) => s.push_str(x),
+            _ => return anyhow!("operator[+] can not concat [{a}] and [{b}], use to_string").err(),
+        }
+    }
+    Value::String(s).ok()
+}
+
+/// a in b：b为数组时判断元素，为字符串时判断子串，为对象时判断key，null为false
+pub fn contains(a: &Value, b: &Value) -> anyhow::Result<bool> {
+    match (a, b) {
+        (_, Value::Array(list)) => list.contains(a).ok(),
+        (Value::String(x), Value::String(y)) => y.contains(x.as_str()).ok(),
+        (Value::String(x), Value::Object(obj)) => obj.contains_key(x).ok(),
+        (_, Value::Null) => false.ok(),
+        _ => anyhow!("operator[in] can not support [{a}] in [{b}]").err(),
+    }
+}
+
+// 左边必须是字符串，null不匹配
+pub(crate) fn string_arg<'a>(opt: &str, v: &'a Value) -> anyhow::Result<Option<&'a str>> {
+    match v {
+        Value::String(s) => Some(s.as_str()).ok(),
+        Value::Null => None.ok(),
+        _ => anyhow!("operator[{opt}] want string, found:{v}").err(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{concat, contains, Pattern};
+    use serde_json::json;
+
+    //cargo test --color=always --lib pattern::test::test_pattern --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_pattern() {
+        let like = Pattern::like("VIP_%").unwrap();
+        assert!(like.is_match("VIP_gold"));
+        assert!(like.is_match("VIPxgold"));
+        assert!(!like.is_match("a VIP_gold"));
+        let like = Pattern::like(r"100\%").unwrap();
+        assert!(like.is_match("100%"));
+        assert!(!like.is_match("1000"));
+        assert!(Pattern::like("a.b").unwrap().is_match("a.b"));
+        assert!(!Pattern::like("a.b").unwrap().is_match("axb"));
+
+        let re = Pattern::regex(r"^1[3-9]\d{9}$").unwrap();
+        assert!(re.is_match("13800000000"));
+        assert!(!re.is_match("1380000000"));
+        assert!(Pattern::regex("(").is_err());
+
+        assert_eq!(concat(&json!("a"), &json!("1")).unwrap(), json!("a1"));
+        assert!(concat(&json!("5"), &json!(1)).is_err());
+        assert_eq!(concat(&json!(null), &json!("b")).unwrap(), json!("b"));
+        assert!(concat(&json!("a"), &json!([1])).is_err());
+        assert!(contains(&json!(1), &json!([1, 2])).unwrap());
+        assert!(contains(&json!("el"), &json!("hello")).unwrap());
+        assert!(contains(&json!("k"), &json!({"k": 1})).unwrap());
+        assert!(!contains(&json!("k"), &json!(null)).unwrap());
+        assert!(contains(&json!(1), &json!("a")).is_err());
+    }
+}
@@ -1,30 +1,111 @@
-use crate::{Assign, Calc};
-use anyhow::anyhow;
-use wd_tools::PFErr;
-
-const RULE_FORMAT: &str = "\n\
-The keyword cannot be repeated: when,then
-rule [name] [description] [engine/default:expr] [...]
-when
-    [condition 1];
-    [condition 2];
-    ...
-    [condition n];
-then
-    [key1 = execute 1];
-    [key2 = execute 2];
-    ...
-    [keyn = execute n];
-";
+use crate::rule_parse::{
+    parse_calc, parse_rules, split_statements, statement_span, tokenize, RuleDecl,
+};
+use crate::{
+    ArithmeticPolicy, Assign, Calc, DecisionTable, MissingPolicy, NullMode, NumberMode, ParseError,
+    Precedence, Schema, SchemaChecker, SchemaError, Span,
+};
+use rush_core::{CompiledRule, RuleEngine, RuleSource};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
 const EXPR_ENGINE: &str = "expr";
-const RULE_TAG: &str = "rule";
+const SALIENCE_TAG: &str = "salience";
+const MISSING_TAG: &str = "missing";
 
 #[derive(Debug, Default)]
 pub struct ExprEngine {
     rules: Vec<(String, Vec<Calc>, Assign)>,
+    checker: SchemaChecker,
+    precedence: Precedence,
+    null_mode: NullMode,
+    number_mode: NumberMode,
+    arithmetic_policy: ArithmeticPolicy,
+    missing: MissingPolicy,
+    // then中key的默认值，MissingPolicy::Default时使用
+    defaults: HashMap<String, Value>,
 }
 
 impl ExprEngine {
+    /// 设置输入的JSON Schema，之后注册的规则会检查字段路径和运算类型
+    /// Rust类型可以通过schemars等工具生成schema
+    pub fn set_input_schema(&mut self, schema: &Value) -> anyhow::Result<()> {
+        self.checker.input = Some(Schema::from_value(schema)?);
+        Ok(())
+    }
+    /// 设置输出的JSON Schema，检查then中的赋值路径和类型
+    pub fn set_output_schema(&mut self, schema: &Value) -> anyhow::Result<()> {
+        self.checker.output = Some(Schema::from_value(schema)?);
+        Ok(())
+    }
+    /// 声明函数的参数个数，用于检查函数调用
+    pub fn declare_function<S: Into<String>>(&mut self, name: S, arity: usize) {
+        self.checker.functions.insert(name.into(), arity);
+    }
+    /// 设置之后注册的规则使用的运算符优先级，依赖旧版本优先级的规则可以设置为Legacy
+    pub fn set_precedence(&mut self, precedence: Precedence) {
+        self.precedence = precedence;
+    }
+    /// 设置之后注册的规则的null模式，ThreeValued时字段不存在视为null，条件和赋值都不会因此失败
+    pub fn set_null_mode(&mut self, null_mode: NullMode) {
+        self.null_mode = null_mode;
+    }
+    /// 设置之后注册的规则的数字模式，Decimal时算术和比较运算按十进制精确计算
+    pub fn set_number_mode(&mut self, number_mode: NumberMode) {
+        self.number_mode = number_mode;
+    }
+    /// 设置之后注册的规则除以0和溢出时的处理方式，默认报错
+    pub fn set_arithmetic_policy(&mut self, policy: ArithmeticPolicy) {
+        self.arithmetic_policy = policy;
+    }
+    /// 设置之后注册的规则在then中引用不存在的字段时的处理策略，规则可以用 missing=skip 单独设置
+    pub fn set_missing_policy(&mut self, missing: MissingPolicy) {
+        self.missing = missing;
+    }
+    /// 声明then中key的默认值，用于MissingPolicy::Default
+    pub fn declare_default<S: Into<String>>(&mut self, key: S, value: Value) {
+        self.defaults.insert(key.into(), value);
+    }
+    // 把引擎的null模式、数字模式和算术策略应用到条件上
+    fn apply_calc(&self, calc: Calc) -> Calc {
+        calc.null_mode(self.null_mode)
+            .number_mode(self.number_mode)
+            .arithmetic_policy(self.arithmetic_policy)
+    }
+    // 把引擎的null模式、缺失字段策略和默认值应用到赋值上
+    fn apply_assign(&self, assign: Assign) -> Assign {
+        let mut assign = assign
+            .null_mode(self.null_mode)
+            .number_mode(self.number_mode)
+            .arithmetic_policy(self.arithmetic_policy)
+            .set_missing_policy(self.missing);
+        for (k, v) in self.defaults.iter() {
+            assign = assign.add_default(k.clone(), v.clone());
+        }
+        assign
+    }
+    /// 按已设置的schema检查全部规则，用于先注册规则后设置schema的情况
+    pub fn check(&self) -> anyhow::Result<()> {
+        for (name, calc, assign) in self.rules.iter() {
+            self.check_rule(name, calc, assign)?;
+        }
+        Ok(())
+    }
+    fn check_rule(&self, name: &str, calc: &[Calc], assign: &Assign) -> anyhow::Result<()> {
+        if self.checker.is_empty() {
+            return Ok(());
+        }
+        let errors = self.checker.check_rule(calc, assign);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(SchemaError {
+            rule: name.to_string(),
+            errors,
+        }
+        .into())
+    }
     pub fn insert_rule<S: Into<String>>(&mut self, name: S, calc: Vec<Calc>, assign: Assign) {
         self.rules.push((name.into(), calc, assign));
     }
@@ -34,61 +115,151 @@ impl ExprEngine {
         calc: E,
         exec: E,
     ) -> anyhow::Result<()> {
-        //先解析calc
-        let ss: Vec<_> = calc.as_ref().split(";").collect();
+        let name = name.into();
+        let src = calc.as_ref();
         let mut calc = vec![];
-        for s in ss {
-            let s = s.trim_matches(|c| " \n\t\r".contains(c));
-            if s.is_empty() {
-                continue;
-            }
-            calc.push(s.parse()?);
+        let tokens = tokenize(src).map_err(|e| e.with_rule(name.as_str()).locate(src))?;
+        for stmt in split_statements(&tokens) {
+            let c = parse_calc(src, statement_span(stmt), self.precedence)
+                .map_err(|e| ParseError::attach_rule(e, &name))?;
+            calc.push(self.apply_calc(c));
         }
-        let assign = exec.as_ref().parse()?;
-        self.insert_rule(name.into(), calc, assign);
+        let assign = Assign::parse(exec.as_ref(), self.precedence)
+            .map_err(|e| ParseError::attach_rule(e, &name))?;
+        let assign = self.apply_assign(assign);
+        self.check_rule(&name, &calc, &assign)?;
+        self.insert_rule(name, calc, assign);
         Ok(())
     }
+    /// 注册规则，一段文本中可以包含多条规则，任意一条解析失败则全部不注册
     pub fn register_rule<R: AsRef<str>>(&mut self, rule: R) -> anyhow::Result<()> {
-        let rule = rule.as_ref();
-        // 先解头
-        let ce: Vec<_> = rule.split("when").collect();
-        if ce.len() != 2 {
-            return anyhow!(
-                "rule[{}] format error,format that can be parsed：{}",
-                rule,
-                RULE_FORMAT
-            )
-            .err();
+        let rules = self.parse_rule(rule)?;
+        self.rules.extend(rules);
+        Ok(())
+    }
+    /// 解析规则并做schema检查，但不注册
+    pub fn parse_rule<R: AsRef<str>>(
+        &self,
+        rule: R,
+    ) -> anyhow::Result<Vec<(String, Vec<Calc>, Assign)>> {
+        let src = rule.as_ref();
+        let decls = parse_rules(src).map_err(|e| e.locate(src))?;
+        let mut rules = vec![];
+        for decl in decls {
+            let rule = self.build_rule(src, decl)?;
+            let (name, calc, assign) = &rule;
+            self.check_rule(name, calc, assign)?;
+            rules.push(rule);
         }
-        let hs: Vec<_> = ce[0]
-            .trim_matches(|c| " \n\r\t".contains(c))
-            .split(" ")
+        Ok(rules)
+    }
+    /// 加载规则文件，例如 xxx.rush
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path)?;
+        self.register_rule(src)
+            .map_err(|e| e.context(format!("load rule file[{}] failed", path.display())))
+    }
+    /// 注册决策表，每一行为一条规则
+    pub fn register_table(&mut self, table: &DecisionTable) -> anyhow::Result<()> {
+        let rules: Vec<(String, Vec<Calc>, Assign)> = table
+            .build()?
+            .into_iter()
+            .map(|(name, calc, assign)| {
+                let calc = calc.into_iter().map(|c| self.apply_calc(c));
+                (name, calc.collect(), self.apply_assign(assign))
+            })
             .collect();
-        if hs.len() <= 1 {
-            return anyhow!("not found rule name").err();
+        for (name, calc, assign) in rules.iter() {
+            self.check_rule(name, calc, assign)?;
         }
-        if hs[0].trim_matches(|c| " \n\r\t".contains(c)).to_lowercase() != RULE_TAG {
-            return anyhow!("rule must start with 'rule'").err();
+        self.rules.extend(rules);
+        Ok(())
+    }
+    /// 加载决策表文件，支持 .csv 和 .json，csv的表名为文件名
+    pub fn load_table_file<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let table = match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => DecisionTable::from_json(&serde_json::from_str(&text)?),
+            _ => {
+                let name = path.file_stem().and_then(|x| x.to_str()).unwrap_or("");
+                DecisionTable::from_csv(name, &text)
+            }
+        };
+        table
+            .and_then(|t| self.register_table(&t))
+            .map_err(|e| e.context(format!("load decision table[{}] failed", path.display())))
+    }
+    fn build_rule(&self, src: &str, decl: RuleDecl) -> anyhow::Result<(String, Vec<Calc>, Assign)> {
+        let name = decl.name;
+        let error = |msg: String, span: Span| -> anyhow::Error {
+            ParseError::syntax(msg, span)
+                .with_rule(name.as_str())
+                .locate(src)
+                .into()
+        };
+        if let Some((engine, span)) = decl.engine {
+            if engine.to_lowercase() != EXPR_ENGINE {
+                return Err(error(
+                    "ExprEngine only support expression parse".into(),
+                    span,
+                ));
+            }
         }
-        if hs.len() >= 4 {
-            if hs[3].to_lowercase() != EXPR_ENGINE {
-                return anyhow!("ExprEngine only support expression parse").err();
+        let mut salience = 0;
+        let mut missing = self.missing;
+        for (k, v, span) in decl.attributes {
+            if k.to_lowercase() == MISSING_TAG {
+                missing = v.parse().map_err(|e| error(format!("{e}"), span))?;
+                continue;
+            }
+            if k.to_lowercase() != SALIENCE_TAG {
+                return Err(error(format!("unknown rule attribute[{}]", k), span));
             }
+            salience = match v.parse::<i32>() {
+                Ok(o) => o,
+                Err(e) => {
+                    return Err(error(
+                        format!("rule salience[{}] parse failed:{}", v, e),
+                        span,
+                    ))
+                }
+            };
         }
-        let name = hs[1].to_string();
-        //再解析条件
-        let ce: Vec<_> = ce[1].split("then").collect();
-        if ce.len() != 2 {
-            return anyhow!(
-                "rule[{}] format error,format that can be parsed：{}",
-                rule,
-                RULE_FORMAT
-            )
-            .err();
+        let mut calc = vec![];
+        for span in decl.when {
+            let c = parse_calc(src, span, self.precedence)
+                .map_err(|e| ParseError::attach_rule(e, &name))?;
+            calc.push(self.apply_calc(c));
         }
-        // let cs:Vec<_> = ce[0].split(";").collect();
-        self.register_rule_by_expr(name, ce[0], ce[1])?;
-        Ok(())
+        let mut assign = Assign::new().set_salience(salience);
+        for (key, op, span) in decl.then {
+            let c = match span {
+                Some(span) => parse_calc(src, span, self.precedence)
+                    .map_err(|e| ParseError::attach_rule(e, &name))?,
+                None => Calc::NULL,
+            };
+            assign = assign
+                .add_action(key, op, c)
+                .map_err(|e| e.context(format!("rule[{name}]")))?;
+        }
+        let assign = self.apply_assign(assign).set_missing_policy(missing);
+        Ok((name, calc, assign))
+    }
+}
+
+impl RuleEngine for ExprEngine {
+    fn compile(&self, rule: &RuleSource) -> anyhow::Result<Vec<CompiledRule>> {
+        let mut list = vec![];
+        for (name, calc, assign) in self.parse_rule(&rule.source)? {
+            let mut rule = CompiledRule::new(name, assign);
+            for i in calc {
+                rule = rule.add_node(i);
+            }
+            list.push(rule);
+        }
+        Ok(list)
     }
 }
 
@@ -117,7 +288,11 @@ impl<'a, S: IntoIterator<Item = &'a str>> From<S> for ExprEngine {
 
 #[cfg(test)]
 mod test {
-    use crate::ExprEngine;
+    use crate::{ExprEngine, MissingPolicy, NullMode, ParseError, Precedence, Span};
+    use rush_core::{Exec, Function, FunctionSet, Rush};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
 
     #[test]
     fn test_expr_engine_from() {
@@ -149,4 +324,103 @@ mod test {
             println!("<--- over");
         }
     }
+
+    //cargo test --color=always --lib rule_builder::test::test_legacy_precedence --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_legacy_precedence() {
+        let rule = "rule r when a || b && c then x = 1 + 2 * 3";
+        let mut ee = ExprEngine::default();
+        ee.register_rule(rule).unwrap();
+        ee.set_precedence(Precedence::Legacy);
+        ee.register_rule(rule).unwrap();
+        let shown: Vec<_> = ee
+            .rules
+            .iter()
+            .map(|(_, c, a)| (c[0].to_string(), a.iter().next().unwrap().1.to_string()))
+            .collect();
+        assert_eq!(shown[0], ("(a || (b && c))".into(), "(1 + (2 * 3))".into()));
+        assert_eq!(shown[1], ("((a || b) && c)".into(), "((1 + 2) * 3)".into()));
+    }
+
+    //cargo test --color=always --lib rule_builder::test::test_null_mode --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_null_mode() {
+        let rule = "rule r when !(user.level > 3) then out.level = user.level + 1; out.name = user.name ?? 'guest'";
+        let fs: Arc<dyn FunctionSet> = Arc::new(HashMap::<String, Arc<dyn Function>>::new());
+        let input = json!({"user":{}});
+        let run = |ee: ExprEngine| -> (bool, anyhow::Result<Value>) {
+            let (_, calc, assign) = ee.into_iter().next().unwrap();
+            let when = rush_core::CalcNode::when(&calc[0], fs.clone(), &input).unwrap();
+            let mut out = json!({});
+            let res = assign.execute(fs.clone(), &input, &mut out).map(|_| out);
+            (when, res)
+        };
+
+        let mut ee = ExprEngine::default();
+        ee.register_rule(rule).unwrap();
+        let (when, res) = run(ee);
+        assert!(!when);
+        assert!(res.is_err());
+
+        let mut ee = ExprEngine::default();
+        ee.set_null_mode(NullMode::ThreeValued);
+        ee.register_rule(rule).unwrap();
+        let (when, res) = run(ee);
+        assert!(!when);
+        assert_eq!(res.unwrap(), json!({"out":{"level":null,"name":"guest"}}));
+    }
+
+    //cargo test --color=always --lib rule_builder::test::test_missing_policy --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_missing_policy() {
+        let rules = r#"
+        rule r1 salience=1 when true then a = 1; b = x.y
+        rule r2 missing=fail_rule when true then c = 1; d = x.y
+        rule r3 missing=default when true then e = x.y
+        "#;
+        let mut ee = ExprEngine::default();
+        ee.set_missing_policy(MissingPolicy::Skip);
+        ee.declare_default("e", json!("none"));
+        ee.register_rule(rules).unwrap();
+        let (out, report): (Value, _) = Rush::from(ee).flow_report(json!({})).unwrap();
+        assert_eq!(out, json!({"a":1,"e":"none"}));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(
+            (
+                report.skipped[0].rule.as_str(),
+                report.skipped[0].key.as_str()
+            ),
+            ("r1", "b")
+        );
+        assert_eq!(report.failed_rules.len(), 1);
+        assert_eq!(report.failed_rules[0].rule, "r2");
+
+        let err = ExprEngine::default()
+            .register_rule("rule r missing=ignore when true then a = 1")
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown missing policy"));
+    }
+
+    //cargo test --color=always --lib rule_builder::test::test_rule_parse_error --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_rule_parse_error() {
+        let rule = "rule rule_err 错误规则
+        when
+            a > 1;
+            b >> ;
+        then
+            data.code = 0;
+        ";
+        let err = ExprEngine::default().register_rule(rule).unwrap_err();
+        let err = err.downcast::<ParseError>().unwrap();
+        assert_eq!(err.position().rule.as_deref(), Some("rule_err"));
+        assert_eq!(err.position().line, 4);
+        assert_eq!(err.position().column, 15);
+        println!("{}", err.render(rule));
+
+        let rule = "rule rule_err when a > 1 then data.code";
+        let err = ExprEngine::default().register_rule(rule).unwrap_err();
+        let err = err.downcast::<ParseError>().unwrap();
+        assert_eq!(err.span(), Span::new(30, 39));
+    }
 }
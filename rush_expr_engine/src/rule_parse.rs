@@ -0,0 +1,289 @@
+use crate::{AssignOp, Calc, CalcBuilder, ParseError, Position, Precedence, Span};
+use rush_core::{header_items, scan_rule, RULE_TAG};
+pub(crate) use rush_core::{split_statements, statement_span, Lexeme, RuleToken};
+
+const RULE_FORMAT: &str = "\n\
+A text can contain several rules, strings use '...' or \"...\", comments use /* */ or //
+rule [name] [description] [engine/default:expr] [salience=0] [missing=fail] [...]
+when
+    [condition 1];
+    [condition 2];
+    ...
+    [condition n];
+then
+    [key1 = execute 1];
+    [key2 = execute 2];
+    ...
+    [keyn = execute n];
+    [key += / -= / *= / /= / ??= execute];
+    [key.push(execute)];
+    [delete key];
+";
+const DELETE_TAG: &str = "delete";
+const PUSH_SUFFIX: &str = ".push";
+
+/// 一条规则的声明，表达式以原文中的区间保存
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RuleDecl {
+    pub name: String,
+    pub description: String,
+    pub engine: Option<(String, Span)>,
+    pub attributes: Vec<(String, String, Span)>,
+    pub when: Vec<Span>,
+    pub then: Vec<(String, AssignOp, Option<Span>)>,
+}
+
+pub(crate) fn tokenize(src: &str) -> Result<Vec<RuleToken>, ParseError> {
+    rush_core::tokenize(src).map_err(ParseError::from)
+}
+
+/// 解析src中span区间的表达式，错误位置换算到src中
+pub(crate) fn parse_calc(src: &str, span: Span, prec: Precedence) -> anyhow::Result<Calc> {
+    let expr = &src[span.start..span.end];
+    CalcBuilder::new(expr)
+        .precedence(prec)
+        .build()
+        .map_err(|e| ParseError::relocate(e, expr, src))
+}
+
+/// 解析赋值语句，返回key、赋值操作和表达式的区间，delete没有表达式
+/// key = expr; key += expr; key ??= expr; key.push(expr); delete key
+pub(crate) fn parse_assignment(
+    src: &str,
+    stmt: &[RuleToken],
+) -> Result<(String, AssignOp, Option<Span>), ParseError> {
+    let span = statement_span(stmt);
+    let text = |s: &RuleToken| &src[s.span.start..s.span.end];
+    let format_err = || {
+        ParseError::syntax(
+            format!(
+                "parse[{}] failed, expr must format:[argument = expression] [argument += expression] [argument.push(expression)] [delete argument]",
+                &src[span.start..span.end]
+            ),
+            span,
+        )
+    };
+    let assign = stmt.iter().position(|x| x.kind == Lexeme::Assign);
+    let (key, op, expr) = match assign {
+        Some(pos) if pos > 0 => {
+            // 紧挨着 = 的运算符组成复合赋值
+            let adjacent = |i: usize| stmt[i].span.end == stmt[i + 1].span.start;
+            let mut op = AssignOp::Set;
+            let mut key_end = pos;
+            if stmt[pos - 1].kind == Lexeme::Symbol && adjacent(pos - 1) {
+                let two = pos > 1 && text(&stmt[pos - 2]) == "?" && adjacent(pos - 2);
+                let symbol = if two { "??" } else { text(&stmt[pos - 1]) };
+                if let Some(o) = AssignOp::from_symbol(symbol) {
+                    op = o;
+                    key_end = if two { pos - 2 } else { pos - 1 };
+                }
+            }
+            if key_end == 0 {
+                return Err(format_err());
+            }
+            if pos + 1 == stmt.len() {
+                return Err(ParseError::EmptyExpression {
+                    at: Position::new(stmt[pos].span),
+                });
+            }
+            let key = Span::new(span.start, stmt[key_end - 1].span.end);
+            (key, op, Some(Span::new(stmt[pos + 1].span.start, span.end)))
+        }
+        Some(_) => return Err(format_err()),
+        None if stmt.len() > 1 && stmt[0].is_keyword(DELETE_TAG) => {
+            if !matches!(stmt[1].kind, Lexeme::Word(_)) {
+                return Err(format_err());
+            }
+            (
+                Span::new(stmt[1].span.start, span.end),
+                AssignOp::Delete,
+                None,
+            )
+        }
+        None => {
+            // key.push(expr)
+            let open = stmt.iter().position(|x| text(x) == "(");
+            let (open, word) = match open {
+                Some(p) if p > 0 && text(stmt.last().unwrap()) == ")" => (p, &stmt[p - 1]),
+                _ => return Err(format_err()),
+            };
+            let end = match text(word).strip_suffix(PUSH_SUFFIX) {
+                Some(k) if !k.is_empty() || open > 1 => word.span.end - PUSH_SUFFIX.len(),
+                _ => return Err(format_err()),
+            };
+            if open + 2 >= stmt.len() {
+                return Err(ParseError::EmptyExpression {
+                    at: Position::new(Span::new(stmt[open].span.start, span.end)),
+                });
+            }
+            let expr = Span::new(stmt[open + 1].span.start, stmt[stmt.len() - 2].span.end);
+            (Span::new(span.start, end), AssignOp::Push, Some(expr))
+        }
+    };
+    let name = src[key.start..key.end].trim();
+    if name.starts_with('$') {
+        return Err(ParseError::syntax(
+            format!("assign key[{name}] is reserved, key can not start with '$'"),
+            key,
+        ));
+    }
+    if op != AssignOp::Set && name.contains("[*]") {
+        return Err(ParseError::syntax(
+            format!("assign key[{name}] can not use [*] with {}", op.as_ref()),
+            key,
+        ));
+    }
+    Ok((name.to_string(), op, expr))
+}
+
+/// 解析规则文本，一段文本中可以包含多条规则
+/// rule [name] [description] [engine] [key=value ...]
+/// when [condition;]... then [key = expression;]...
+pub(crate) fn parse_rules(src: &str) -> Result<Vec<RuleDecl>, ParseError> {
+    let tokens = tokenize(src)?;
+    let mut rules = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        if !tokens[i].is_keyword(RULE_TAG) {
+            return Err(ParseError::syntax(
+                "rule must start with 'rule'",
+                tokens[i].span,
+            ));
+        }
+        let rule_span = tokens[i].span;
+        let sections = scan_rule(src, &tokens, i);
+        let mut decl = parse_header(src, &tokens[sections.header], rule_span)?;
+        let (when, then) = match (sections.when, sections.then) {
+            (Some(w), Some(t)) => (w, t),
+            (when, _) => {
+                let tag = if when.is_none() { "when" } else { "then" };
+                return Err(ParseError::syntax(
+                    format!("not found keyword '{tag}'，format：{RULE_FORMAT}"),
+                    rule_span,
+                )
+                .with_rule(decl.name));
+            }
+        };
+        decl.when = split_statements(&tokens[when + 1..then])
+            .into_iter()
+            .map(statement_span)
+            .collect();
+        for stmt in split_statements(&tokens[then + 1..sections.end]) {
+            let assign =
+                parse_assignment(src, stmt).map_err(|e| e.with_rule(decl.name.as_str()))?;
+            decl.then.push(assign);
+        }
+        i = sections.end;
+        rules.push(decl);
+    }
+    Ok(rules)
+}
+
+fn parse_header(src: &str, tokens: &[RuleToken], rule: Span) -> Result<RuleDecl, ParseError> {
+    let mut decl = RuleDecl::default();
+    let mut names = vec![];
+    for (i, (item, span, attr)) in header_items(src, tokens).into_iter().enumerate() {
+        // 头部属性 key=value
+        if attr && i > 0 {
+            let (k, v) = item.split_once('=').unwrap_or((item.as_str(), ""));
+            decl.attributes.push((k.to_string(), v.to_string(), span));
+            continue;
+        }
+        names.push((item, span));
+    }
+    let mut names = names.into_iter();
+    match names.next() {
+        Some((name, _)) => decl.name = name,
+        None => return Err(ParseError::syntax("not found rule name", rule)),
+    }
+    if let Some((desc, _)) = names.next() {
+        decl.description = desc;
+    }
+    decl.engine = names.next();
+    Ok(decl)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rule_parse::parse_rules;
+    use crate::{AssignOp, ParseError};
+    use rush_core::scan_string;
+
+    //cargo test --color=always --lib rule_parse::test::test_scan_string --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_scan_string() {
+        assert_eq!(scan_string(r#"'a;b' + 1"#), Some(("a;b".into(), 5)));
+        assert_eq!(scan_string(r#""it\"s" "#), Some((r#"it"s"#.into(), 7)));
+        assert_eq!(scan_string(r#"'\d+'"#), Some((r#"\d+"#.into(), 5)));
+        assert_eq!(scan_string("'abc"), None);
+    }
+
+    //cargo test --color=always --lib rule_parse::test::test_parse_rules --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_parse_rules() {
+        let src = r#"
+        // 多条规则
+        rule r1 "first rule" expr salience=10
+        when
+            whenever > 1; /* then; */
+            tag == 'then; when';
+        then
+            data.msg = "a = b";
+            rule = 1
+        rule r2
+        when
+            true
+        then
+            data.code = 0
+        "#;
+        let rules = parse_rules(src).unwrap();
+        assert_eq!(rules.len(), 2);
+        let r1 = &rules[0];
+        assert_eq!(r1.name, "r1");
+        assert_eq!(r1.description, "first rule");
+        assert_eq!(r1.engine.as_ref().unwrap().0, "expr");
+        assert_eq!(r1.attributes[0].0, "salience");
+        assert_eq!(r1.attributes[0].1, "10");
+        let when: Vec<_> = r1.when.iter().map(|s| &src[s.start..s.end]).collect();
+        assert_eq!(when, vec!["whenever > 1", "tag == 'then; when'"]);
+        let then: Vec<_> = r1
+            .then
+            .iter()
+            .map(|(k, _, s)| (k.as_str(), s.map(|s| &src[s.start..s.end])))
+            .collect();
+        assert_eq!(
+            then,
+            vec![("data.msg", Some(r#""a = b""#)), ("rule", Some("1"))]
+        );
+        assert_eq!(rules[1].name, "r2");
+        assert_eq!(rules[1].then.len(), 1);
+
+        let src = "rule r4 when case when a > 1 then b else c end then x = 1";
+        let rules = parse_rules(src).unwrap();
+        let when = rules[0].when[0];
+        assert_eq!(
+            &src[when.start..when.end],
+            "case when a > 1 then b else c end"
+        );
+
+        let src = "rule r5 when true then a.n += 1; b ??= 2; c[0].push(x + 1); delete d.e";
+        let then: Vec<_> = parse_rules(src).unwrap()[0]
+            .then
+            .iter()
+            .map(|(k, op, s)| (k.clone(), *op, s.map(|s| &src[s.start..s.end])))
+            .collect();
+        assert_eq!(
+            then,
+            vec![
+                ("a.n".into(), AssignOp::Add, Some("1")),
+                ("b".into(), AssignOp::Coalesce, Some("2")),
+                ("c[0]".into(), AssignOp::Push, Some("x + 1")),
+                ("d.e".into(), AssignOp::Delete, None),
+            ]
+        );
+
+        let err = parse_rules("rule r3 when a > 1").unwrap_err();
+        assert!(matches!(err, ParseError::Syntax { .. }));
+        assert_eq!(err.position().rule.as_deref(), Some("r3"));
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{
+    output_path, Assign, AssignOp, Calc, HigherOrder, NullPredicate, Opt, PathSegment,
+    RoundingMode,
+};
+use anyhow::anyhow;
+use serde_json::Value;
+use std::collections::HashMap;
+use wd_tools::{PFErr, PFOk};
+
+/// 表达式的静态类型
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValueType {
+    Any,
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueType {
+    fn from_name(name: &str) -> anyhow::Result<Self> {
+        let ty = match name {
+            "null" => ValueType::Null,
+            "boolean" => ValueType::Bool,
+            "integer" | "number" => ValueType::Number,
+            "string" => ValueType::String,
+            "array" => ValueType::Array,
+            "object" => ValueType::Object,
+            _ => return anyhow!("unknown schema type[{name}]").err(),
+        };
+        ty.ok()
+    }
+    // 类型是否可以当作want使用，Any可以匹配任何类型
+    pub fn compatible(&self, want: ValueType) -> bool {
+        *self == want || *self == ValueType::Any || want == ValueType::Any
+    }
+}
+
+/// JSON Schema的子集：type, properties, items, additionalProperties
+/// 声明了properties的对象默认不允许访问未声明的字段，用于发现拼写错误
+/// 需要放开时设置 "additionalProperties": true
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub ty: ValueType,
+    pub nullable: bool,
+    pub properties: HashMap<String, Schema>,
+    pub items: Option<Box<Schema>>,
+    pub additional: bool,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self {
+            ty: ValueType::Any,
+            nullable: false,
+            properties: HashMap::new(),
+            items: None,
+            additional: true,
+        }
+    }
+}
+
+impl Schema {
+    pub fn from_value(value: &Value) -> anyhow::Result<Self> {
+        let obj = match value {
+            Value::Object(obj) => obj,
+            Value::Bool(true) => return Schema::default().ok(),
+            _ => return anyhow!("schema must is a object, found:{value}").err(),
+        };
+        let mut schema = Schema::default();
+        match obj.get("type") {
+            None => {}
+            Some(Value::String(s)) => schema.ty = ValueType::from_name(s)?,
+            Some(Value::Array(list)) => {
+                let mut tys = vec![];
+                for i in list {
+                    match i.as_str().map(ValueType::from_name) {
+                        Some(Ok(ValueType::Null)) => schema.nullable = true,
+                        Some(Ok(ty)) => tys.push(ty),
+                        Some(Err(e)) => return Err(e),
+                        None => return anyhow!("schema type must is string, found:{i}").err(),
+                    }
+                }
+                if tys.len() == 1 {
+                    schema.ty = tys[0];
+                }
+            }
+            Some(s) => return anyhow!("schema type must is string or array, found:{s}").err(),
+        }
+        if let Some(Value::Object(ps)) = obj.get("properties") {
+            if schema.ty == ValueType::Any {
+                schema.ty = ValueType::Object;
+            }
+            for (k, v) in ps.iter() {
+                schema.properties.insert(k.clone(), Schema::from_value(v)?);
+            }
+            schema.additional = false;
+        }
+        if let Some(Value::Bool(b)) = obj.get("additionalProperties") {
+            schema.additional = *b;
+        }
+        if let Some(items) = obj.get("items") {
+            schema.items = Some(Box::new(Schema::from_value(items)?));
+        }
+        schema.ok()
+    }
+
+    /// 按 a.b[0].c[*] 查找字段的schema，通配符投影的结果为数组
+    pub fn field(&self, path: &str) -> anyhow::Result<Schema> {
+        let segs = PathSegment::parse(path)?;
+        self.field_segs(path, &segs)
+    }
+    fn field_segs(&self, path: &str, segs: &[PathSegment]) -> anyhow::Result<Schema> {
+        let mut schema = self;
+        for (n, seg) in segs.iter().enumerate() {
+            let want = match seg {
+                PathSegment::Optional => continue,
+                PathSegment::Key(_) => ValueType::Object,
+                _ => ValueType::Array,
+            };
+            match schema.ty {
+                ValueType::Any => return Schema::default().ok(),
+                ty if ty == want => {}
+                ty => return anyhow!("field[{path}] at [{seg}] is {ty:?}, not {want:?}").err(),
+            }
+            schema = match seg {
+                PathSegment::Key(key) => match schema.properties.get(key) {
+                    Some(s) => s,
+                    None if schema.additional => return Schema::default().ok(),
+                    None => return anyhow!("unknown field[{path}], [{key}] is not declared").err(),
+                },
+                PathSegment::Index(_) => match schema.items {
+                    Some(ref s) => s,
+                    None => return Schema::default().ok(),
+                },
+                PathSegment::Wildcard => {
+                    let items = match schema.items {
+                        Some(ref s) => s.field_segs(path, &segs[n + 1..])?,
+                        None => Schema::default(),
+                    };
+                    return Schema {
+                        ty: ValueType::Array,
+                        items: Some(Box::new(items)),
+                        ..Default::default()
+                    }
+                    .ok();
+                }
+                PathSegment::Optional => continue,
+            };
+        }
+        schema.clone().ok()
+    }
+}
+
+/// 按schema对规则做类型检查
+#[derive(Debug, Default)]
+pub struct SchemaChecker {
+    pub input: Option<Schema>,
+    pub output: Option<Schema>,
+    // 已知函数的参数个数
+    pub functions: HashMap<String, usize>,
+}
+
+impl SchemaChecker {
+    pub fn is_empty(&self) -> bool {
+        self.input.is_none() && self.output.is_none() && self.functions.is_empty()
+    }
+    /// 返回全部错误
+    pub fn check_rule(&self, calc: &[Calc], assign: &Assign) -> Vec<String> {
+        let mut errors = vec![];
+        for i in calc.iter() {
+            if let Err(e) = self.infer(i) {
+                errors.push(format!("when[{}]: {}", i.to_string(), e));
+            }
+        }
+        for (k, op, c) in assign.actions() {
+            let stmt = match op {
+                AssignOp::Delete => format!("delete {k}"),
+                AssignOp::Push => format!("{k}.push({})", c.to_string()),
+                _ => format!("{k} {} {}", op.as_ref(), c.to_string()),
+            };
+            let ty = match self.infer(c) {
+                Ok(o) => o,
+                Err(e) => {
+                    errors.push(format!("then[{stmt}]: {e}"));
+                    continue;
+                }
+            };
+            if let Some(ref out) = self.output {
+                match out.field(k) {
+                    Ok(s) => {
+                        // push要求目标是数组，+= 可以拼接到字符串
+                        let (want, ok) = match op {
+                            AssignOp::Delete => (s.ty, true),
+                            AssignOp::Push => (ValueType::Array, s.ty.compatible(ValueType::Array)),
+                            AssignOp::Add if s.ty == ValueType::String => (s.ty, true),
+                            _ => (
+                                s.ty,
+                                ty.compatible(s.ty) || ty == ValueType::Null && s.nullable,
+                            ),
+                        };
+                        if !ok {
+                            errors.push(format!(
+                                "then[{stmt}]: output[{k}] want {want:?}, found {:?}",
+                                if op == AssignOp::Push { s.ty } else { ty }
+                            ));
+                        }
+                    }
+                    Err(e) => errors.push(format!("then[{k}]: output {e}")),
+                }
+            }
+        }
+        errors
+    }
+    /// 推导表达式的类型
+    pub fn infer(&self, calc: &Calc) -> anyhow::Result<ValueType> {
+        let ty = match calc {
+            Calc::NULL => ValueType::Null,
+            // $out 按输出的schema检查
+            Calc::Field(f) => match (output_path(f), &self.input, &self.output) {
+                (Some(""), _, _) => ValueType::Object,
+                (Some(path), _, Some(s)) => s.field(path)?.ty,
+                (None, Some(s), _) => s.field(f)?.ty,
+                _ => ValueType::Any,
+            },
+            Calc::String(_) | Calc::Time(_) | Calc::Pattern(_) => ValueType::String,
+            Calc::Number(_) | Calc::Float(_) => ValueType::Number,
+            Calc::Bool(_) => ValueType::Bool,
+            Calc::Array(list) => {
+                for i in list {
+                    self.infer(i)?;
+                }
+                ValueType::Array
+            }
+            Calc::Function(name, args) => {
+                for i in args {
+                    self.infer(i)?;
+                }
+                if let Some(n) = self.functions.get(name) {
+                    if *n != args.len() {
+                        return anyhow!(
+                            "function[{name}] expecting {n} parameters actually finds {}",
+                            args.len()
+                        )
+                        .err();
+                    }
+                } else if let Some(hf) = HigherOrder::from_name(name) {
+                    return hf.value_type().ok();
+                } else if NullPredicate::from_name(name).is_some() {
+                    return ValueType::Bool.ok();
+                } else if RoundingMode::from_name(name).is_some() {
+                    return ValueType::Number.ok();
+                }
+                ValueType::Any
+            }
+            // lambda的参数不在schema中，不做检查
+            Calc::Lambda(_, _) => ValueType::Any,
+            Calc::NullSafe(calc) | Calc::Decimal(calc) | Calc::Checked(_, calc) => self.infer(calc)?,
+            Calc::If(cond, a, b) => {
+                self.infer(cond)?;
+                Self::infer_branches(&[self.infer(a)?, self.infer(b)?])
+            }
+            Calc::Case(branches, default) => {
+                let mut tys = vec![self.infer(default)?];
+                for (cond, val) in branches {
+                    self.infer(cond)?;
+                    tys.push(self.infer(val)?);
+                }
+                // 没有else时默认分支是null，不参与推断
+                if **default == Calc::NULL {
+                    tys.remove(0);
+                }
+                Self::infer_branches(&tys)
+            }
+            Calc::Operator(opt, args) => {
+                let mut tys = vec![];
+                for i in args {
+                    tys.push(self.infer(i)?);
+                }
+                Self::infer_operator(opt, args, &tys)?
+            }
+        };
+        ty.ok()
+    }
+    // 各个分支类型一致时为该类型，否则为Any
+    fn infer_branches(tys: &[ValueType]) -> ValueType {
+        match tys.split_first() {
+            Some((ty, rest)) if rest.iter().all(|x| x == ty) => *ty,
+            _ => ValueType::Any,
+        }
+    }
+    fn infer_operator(opt: &Opt, args: &[Calc], tys: &[ValueType]) -> anyhow::Result<ValueType> {
+        let want_number = || -> anyhow::Result<()> {
+            for (i, ty) in tys.iter().enumerate() {
+                if !ty.compatible(ValueType::Number) && *ty != ValueType::Null {
+                    return anyhow!(
+                        "operator[{}] want number, but [{}] is {:?}",
+                        opt.as_ref(),
+                        args[i].to_string(),
+                        ty
+                    )
+                    .err();
+                }
+            }
+            Ok(())
+        };
+        let want_string = || -> anyhow::Result<()> {
+            for (i, ty) in tys.iter().enumerate() {
+                if !ty.compatible(ValueType::String) && *ty != ValueType::Null {
+                    return anyhow!(
+                        "operator[{}] want string, but [{}] is {:?}",
+                        opt.as_ref(),
+                        args[i].to_string(),
+                        ty
+                    )
+                    .err();
+                }
+            }
+            Ok(())
+        };
+        let ty = match opt {
+            // 有一边是字符串时是拼接
+            Opt::ADD if tys.contains(&ValueType::String) => ValueType::String,
+            Opt::ADD
+            | Opt::SUB
+            | Opt::MUL
+            | Opt::DIV
+            | Opt::REM
+            | Opt::AND
+            | Opt::OR
+            | Opt::XOR
+            | Opt::REV
+            | Opt::SHL
+            | Opt::SHR => {
+                want_number()?;
+                ValueType::Number
+            }
+            // 日期、时间和时长是字符串
+            Opt::GT | Opt::GE | Opt::LT | Opt::LE
+                if tys.iter().all(|x| x.compatible(ValueType::String)) =>
+            {
+                ValueType::Bool
+            }
+            Opt::GT | Opt::GE | Opt::LT | Opt::LE => {
+                want_number()?;
+                ValueType::Bool
+            }
+            Opt::LIKE | Opt::MATCH | Opt::SW => {
+                want_string()?;
+                ValueType::Bool
+            }
+            Opt::NOT | Opt::EQ | Opt::NQ | Opt::IN | Opt::AT | Opt::OT => ValueType::Bool,
+            // 左边为null时取右边的类型
+            Opt::NC => match tys {
+                [ValueType::Null, ty] => *ty,
+                _ => Self::infer_branches(tys),
+            },
+        };
+        ty.ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Assign, Calc, Schema, SchemaChecker, ValueType};
+
+    //cargo test --color=always --lib schema::test::test_schema_check --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_schema_check() {
+        let input = r#"{"type":"object","properties":{"age":{"type":"integer"},"name":{"type":"string"},"tags":{"type":"array"}}}"#;
+        let output = r#"{"properties":{"stage":{"type":"string"}}}"#;
+        let checker = SchemaChecker {
+            input: Some(Schema::from_value(&input.parse().unwrap()).unwrap()),
+            output: Some(Schema::from_value(&output.parse().unwrap()).unwrap()),
+            functions: [("contain".to_string(), 2)].into(),
+        };
+        let check = |when: &str, then: &str| {
+            let calc: Calc = when.parse().unwrap();
+            let assign: Assign = then.parse().unwrap();
+            checker.check_rule(&[calc], &assign)
+        };
+        assert!(check("age > 18 && name == 'a'", "stage = 'adult'").is_empty());
+        assert!(check("contain(tags, name)", "stage = name").is_empty());
+
+        let errors = check("agee > 18", "stage = 'adult'");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("agee"), "{}", errors[0]);
+        assert_eq!(check("name * 2 > 1", "stage = 'a'").len(), 1);
+        assert_eq!(check("contain(tags)", "stage = 'a'").len(), 1);
+        assert_eq!(check("age > 1", "stag = 'a'").len(), 1);
+        assert_eq!(check("age > 1", "stage = age + 1").len(), 1);
+        assert!(check("age > 1", "stage = name; stage = $out.stage + '!'").is_empty());
+        assert_eq!(check("age > 1", "stage = $out.stag").len(), 1);
+        assert!(check("age > 1", "stage += age; delete stage").is_empty());
+        assert_eq!(check("age > 1", "stage.push(1)").len(), 1);
+        assert_eq!(check("age > 1", "stage ??= age").len(), 1);
+
+        let input = r#"{"properties":{"items":{"type":"array","items":{"properties":{"price":{"type":"number"}}}}}}"#;
+        let schema = Schema::from_value(&input.parse().unwrap()).unwrap();
+        assert_eq!(
+            schema.field("items[-1].price").unwrap().ty,
+            ValueType::Number
+        );
+        assert_eq!(schema.field("items[*].price").unwrap().ty, ValueType::Array);
+        assert!(schema.field("items[0].prise").is_err());
+        assert!(schema.field("items.price").is_err());
+    }
+}
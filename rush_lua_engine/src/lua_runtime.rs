@@ -3,14 +3,36 @@ use mlua::{Function, Lua, LuaSerdeExt, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::mpsc;
 use std::time::Duration;
 use tokio::sync::oneshot::*;
 use wd_tools::{PFErr, PFOk};
 
+// 同步调用用std的通道等待，不依赖tokio运行时；异步调用用oneshot
+#[derive(Debug)]
+enum Reply {
+    Sync(mpsc::Sender<anyhow::Result<serde_json::Value>>),
+    Async(Sender<anyhow::Result<serde_json::Value>>),
+}
+
+impl Reply {
+    fn send(self, res: anyhow::Result<serde_json::Value>) {
+        // 调用方已经放弃等待时忽略
+        match self {
+            Reply::Sync(s) => {
+                let _ = s.send(res);
+            }
+            Reply::Async(s) => {
+                let _ = s.send(res);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Task {
     input: serde_json::Value,
-    sender: Sender<anyhow::Result<serde_json::Value>>,
+    sender: Reply,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -102,25 +124,21 @@ impl LuaRuntime {
                 let val = match lua.to_value(&input) {
                     Ok(o) => o,
                     Err(e) => {
-                        let _ = sender.send(Err(Error::from(e)));
+                        sender.send(Err(Error::from(e)));
                         continue;
                     }
                 };
                 let val = match func.call::<_, Value>(val) {
                     Ok(o) => o,
                     Err(e) => {
-                        let _ = sender.send(Err(Error::from(e)));
+                        sender.send(Err(Error::from(e)));
                         continue;
                     }
                 };
 
                 match lua.from_value::<serde_json::Value>(val) {
-                    Ok(o) => {
-                        let _ = sender.send(Ok(o));
-                    }
-                    Err(e) => {
-                        let _ = sender.send(Err(Error::from(e)));
-                    }
+                    Ok(o) => sender.send(Ok(o)),
+                    Err(e) => sender.send(Err(Error::from(e))),
                 };
             }
         });
@@ -147,15 +165,19 @@ impl LuaRuntime {
         Ok(LuaRuntime { sender })
     }
 
+    /// 阻塞等待结果，脚本在独立的线程中执行，在tokio运行时中调用也不会死锁
     pub fn call<S: Serialize, Out: for<'a> Deserialize<'a>>(&self, req: S) -> anyhow::Result<Out> {
         let req = serde_json::to_value(req)?;
-        let (sender, receiver) = channel();
-        let task = Task { input: req, sender };
+        let (sender, receiver) = mpsc::channel();
+        let task = Task {
+            input: req,
+            sender: Reply::Sync(sender),
+        };
         if let Err(e) = self.sender.send_blocking(task) {
             let err = e.to_string();
             return anyhow!("lua runtime call failed: {}", err).err();
         }
-        return match receiver.blocking_recv() {
+        return match receiver.recv() {
             Ok(o) => {
                 let out = serde_json::from_value::<Out>(o?)?;
                 Ok(out)
@@ -169,7 +191,10 @@ impl LuaRuntime {
     ) -> anyhow::Result<Out> {
         let req = serde_json::to_value(req)?;
         let (sender, receiver) = channel();
-        let task = Task { input: req, sender };
+        let task = Task {
+            input: req,
+            sender: Reply::Async(sender),
+        };
         if let Err(e) = self.sender.send(task).await {
             let err = e.to_string();
             return anyhow!("lua runtime call failed: {}", err).err();
@@ -210,6 +235,29 @@ impl rush_core::AsyncRuleFlow for LuaRuntime {
     }
 }
 
+// 脚本的返回值合并到输出中，call会阻塞等待结果
+#[cfg(feature = "rule-flow")]
+impl rush_core::Exec for LuaRuntime {
+    fn execute(
+        &self,
+        _fs: std::sync::Arc<dyn rush_core::FunctionSet>,
+        input: &serde_json::Value,
+        output: &mut serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let res: serde_json::Value = self.call(input)?;
+        if output.is_null() {
+            *output = res;
+        } else {
+            rush_core::merge_value(output, &res);
+        }
+        Ok(())
+    }
+    // 只在成功时写入输出
+    fn keeps_output_on_failure(&self) -> bool {
+        true
+    }
+}
+
 impl FromStr for LuaRuntime {
     type Err = anyhow::Error;
 
@@ -244,4 +292,19 @@ mod test {
             .unwrap();
         assert_eq!(result, Value::String("success".into()))
     }
+
+    //cargo test --color=always --lib lua_runtime::test::test_call_in_runtime --no-fail-fast -- --exact unstable-options --nocapture
+    #[test]
+    fn test_call_in_runtime() {
+        let rt = LuaRuntime::new(TEST_LUA_SCRIPT.to_string(), HashMap::new()).unwrap();
+        let tokio_rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        tokio_rt.block_on(async {
+            let result: Value = rt.call(serde_json::json!({})).unwrap();
+            assert_eq!(result, Value::String("success".into()));
+            let result: Value = rt.async_call(serde_json::json!({})).await.unwrap();
+            assert_eq!(result, Value::String("success".into()));
+        });
+    }
 }
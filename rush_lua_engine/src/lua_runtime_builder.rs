@@ -18,12 +18,15 @@ pub trait AsyncCustomScriptLoad: Send + Sync {
 #[derive(Default)]
 pub struct LuaRuntimeFactory {
     loader: HashMap<&'static str, Box<dyn AsyncCustomScriptLoad>>,
+    // 作为RuleEngine使用时，传给每个脚本的环境变量
+    envs: HashMap<String, String>,
 }
 
 impl LuaRuntimeFactory {
     pub fn new() -> Self {
         let loader: HashMap<&'static str, Box<dyn AsyncCustomScriptLoad>> = HashMap::new();
-        let mut lrf = Self { loader };
+        let envs = HashMap::new();
+        let mut lrf = Self { loader, envs };
         lrf.add_loader(LUA_SCRIPT_TAG, AsyncCustomScriptLoadDefaultImpl);
         lrf.add_loader(LUA_FILE_TAG, AsyncCustomScriptLoadFile);
         lrf
@@ -38,6 +41,9 @@ impl LuaRuntimeFactory {
     pub fn remove_loader<S: AsRef<str>>(&mut self, tag: S) {
         self.loader.remove(tag.as_ref());
     }
+    pub fn set_env<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.envs.insert(key.into(), value.into());
+    }
     fn check_engine(buf: &str) -> anyhow::Result<(String, String)> {
         let buf = buf.trim_start_matches(|c| " \n\r\t".contains(c));
         let (head, body) = if let Some(s) = buf.split_once('\n') {
@@ -92,6 +98,18 @@ impl LuaRuntimeFactory {
     }
 }
 
+// 规则中只能使用支持同步加载的loader，例如 lua_script, lua_file
+#[cfg(feature = "rule-flow")]
+impl rush_core::RuleEngine for LuaRuntimeFactory {
+    fn compile(
+        &self,
+        rule: &rush_core::RuleSource,
+    ) -> anyhow::Result<Vec<rush_core::CompiledRule>> {
+        let rt = self.load(&rule.source, self.envs.clone())?;
+        Ok(vec![rush_core::CompiledRule::new(rule.name.as_str(), rt)])
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::LuaRuntimeFactory;
@@ -1,15 +1,37 @@
 use crate::ExportEnv;
 use anyhow::{anyhow, Error};
 use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
 use std::time::Duration;
 use tokio::sync::oneshot::{channel, error, Sender};
 use wasmer::{FunctionEnv, Instance, Module, Store, TypedFunction, WasmPtr};
 use wd_tools::PFErr;
 
+// 同步调用用std的通道等待，不依赖tokio运行时；异步调用用oneshot
+#[derive(Debug)]
+enum Reply {
+    Sync(mpsc::Sender<anyhow::Result<String>>),
+    Async(Sender<anyhow::Result<String>>),
+}
+
+impl Reply {
+    fn send(self, res: anyhow::Result<String>) {
+        // 调用方已经放弃等待时忽略
+        match self {
+            Reply::Sync(s) => {
+                let _ = s.send(res);
+            }
+            Reply::Async(s) => {
+                let _ = s.send(res);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Task {
     input: Vec<u8>,
-    sender: Sender<anyhow::Result<String>>,
+    sender: Reply,
 }
 
 pub struct WasmRuntime {
@@ -75,20 +97,20 @@ impl WasmRuntime {
                 let value = match ptr.slice(&view, input.len() as u32) {
                     Ok(o) => o,
                     Err(e) => {
-                        let _ = sender.send(Err(Error::from(e)));
+                        sender.send(Err(Error::from(e)));
                         continue;
                     }
                 };
                 if let Err(e) = value.write_slice(input.as_slice()) {
-                    let _ = sender.send(Err(Error::from(e)));
+                    sender.send(Err(Error::from(e)));
                     continue;
                 };
                 if let Err(e) = handle.call(&mut store, ptr, input.len() as u32) {
-                    let _ = sender.send(Err(Error::from(e)));
+                    sender.send(Err(Error::from(e)));
                     continue;
                 }
                 let result = env.as_mut(&mut store).get_result();
-                let _ = sender.send(result);
+                sender.send(result);
             }
         });
 
@@ -111,15 +133,19 @@ impl WasmRuntime {
         Ok(Self { sender })
     }
 
+    /// 阻塞等待结果，模块在独立的线程中执行，在tokio运行时中调用也不会死锁
     pub fn call<S: Serialize, Out: for<'a> Deserialize<'a>>(&self, req: S) -> anyhow::Result<Out> {
         let input = serde_json::to_string(&req)?.into_bytes();
-        let (sender, receiver) = channel();
-        let task = Task { input, sender };
+        let (sender, receiver) = mpsc::channel();
+        let task = Task {
+            input,
+            sender: Reply::Sync(sender),
+        };
         if let Err(e) = self.sender.send_blocking(task) {
             let err = e.to_string();
             return anyhow!("wasm runtime call failed: {}", err).err();
         }
-        return match receiver.blocking_recv() {
+        return match receiver.recv() {
             Ok(o) => {
                 let s = o?;
                 let out = serde_json::from_str::<Out>(s.as_str())?;
@@ -135,7 +161,10 @@ impl WasmRuntime {
     ) -> anyhow::Result<Out> {
         let input = serde_json::to_string(&req)?.into_bytes();
         let (sender, receiver) = channel();
-        let task = Task { input, sender };
+        let task = Task {
+            input,
+            sender: Reply::Async(sender),
+        };
         if let Err(e) = self.sender.send(task).await {
             let err = e.to_string();
             return anyhow!("lua runtime call failed: {}", err).err();
@@ -178,6 +207,28 @@ impl rush_core::AsyncRuleFlow for WasmRuntime {
         self.async_call(obj).await
     }
 }
+// 返回值合并到输出中，call会阻塞等待结果
+#[cfg(feature = "rule-flow")]
+impl rush_core::Exec for WasmRuntime {
+    fn execute(
+        &self,
+        _fs: std::sync::Arc<dyn rush_core::FunctionSet>,
+        input: &serde_json::Value,
+        output: &mut serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let res: serde_json::Value = self.call(input)?;
+        if output.is_null() {
+            *output = res;
+        } else {
+            rush_core::merge_value(output, &res);
+        }
+        Ok(())
+    }
+    // 只在成功时写入输出
+    fn keeps_output_on_failure(&self) -> bool {
+        true
+    }
+}
 #[cfg(test)]
 mod test {
     use crate::WasmRuntime;
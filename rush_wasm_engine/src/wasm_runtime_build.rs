@@ -71,6 +71,17 @@ impl WasmRuntimeFactory {
     }
 }
 
+#[cfg(feature = "rule-flow")]
+impl rush_core::RuleEngine for WasmRuntimeFactory {
+    fn compile(
+        &self,
+        rule: &rush_core::RuleSource,
+    ) -> anyhow::Result<Vec<rush_core::CompiledRule>> {
+        let rt = self.build(&rule.source)?;
+        Ok(vec![rush_core::CompiledRule::new(rule.name.as_str(), rt)])
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::WasmRuntimeFactory;